@@ -0,0 +1,9 @@
+-- On-chain parameters and lifecycle of badges created through the transaction builder
+ALTER TABLE badges ADD COLUMN IF NOT EXISTS badge_uri TEXT NOT NULL DEFAULT '';
+ALTER TABLE badges ADD COLUMN IF NOT EXISTS badge_price BIGINT NOT NULL DEFAULT 0 CHECK (badge_price >= 0);
+ALTER TABLE badges ADD COLUMN IF NOT EXISTS badge_decimals SMALLINT NOT NULL DEFAULT 9;
+ALTER TABLE badges ADD COLUMN IF NOT EXISTS status VARCHAR(32) NOT NULL DEFAULT 'pending';
+ALTER TABLE badges ALTER COLUMN badge_description SET DEFAULT '';
+ALTER TABLE badges ALTER COLUMN badge_image SET DEFAULT '';
+
+CREATE INDEX IF NOT EXISTS badges_creator_id_idx ON badges (creator_id);
@@ -0,0 +1,14 @@
+-- Signed transactions relayed through POST /api/transactions and their confirmation status
+CREATE TABLE IF NOT EXISTS transactions (
+    signature VARCHAR(128) PRIMARY KEY,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    recent_blockhash VARCHAR(64) NOT NULL,
+    status VARCHAR(32) NOT NULL DEFAULT 'submitted',
+    error TEXT,
+    slot BIGINT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS transactions_pending_idx ON transactions (created_at)
+    WHERE status IN ('submitted', 'processed', 'confirmed');
@@ -0,0 +1,12 @@
+-- Network fees paid by the server fee payer on behalf of users
+CREATE TABLE IF NOT EXISTS sponsored_fees (
+    id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    badge_address VARCHAR(255) NOT NULL,
+    signature VARCHAR(128) NOT NULL UNIQUE,
+    lamports BIGINT NOT NULL CHECK (lamports >= 0),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS sponsored_fees_user_created_idx ON sponsored_fees (user_id, created_at);
+CREATE INDEX IF NOT EXISTS sponsored_fees_created_idx ON sponsored_fees (created_at);
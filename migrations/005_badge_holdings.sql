@@ -0,0 +1,15 @@
+-- Mint receipts indexed from the program, one row per minted badge
+CREATE TABLE IF NOT EXISTS badge_holdings (
+    receipt_address VARCHAR(64) PRIMARY KEY,
+    badge_address VARCHAR(64) NOT NULL,
+    holder VARCHAR(64) NOT NULL,
+    number BIGINT NOT NULL,
+    price_paid BIGINT NOT NULL,
+    minted_at BIGINT NOT NULL,
+    slot BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS badge_holdings_badge_idx ON badge_holdings (badge_address, number);
+CREATE INDEX IF NOT EXISTS badge_holdings_holder_idx ON badge_holdings (holder);
@@ -0,0 +1,25 @@
+-- Events emitted by the program, parsed from transaction logs
+CREATE TABLE IF NOT EXISTS activity (
+    id BIGSERIAL PRIMARY KEY,
+    signature VARCHAR(128) NOT NULL,
+    event_index INT NOT NULL,
+    slot BIGINT NOT NULL,
+    block_time BIGINT,
+    kind VARCHAR(64) NOT NULL,
+    badge_address VARCHAR(64) NOT NULL,
+    wallet_address VARCHAR(64) NOT NULL,
+    data JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (signature, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS activity_badge_idx ON activity (badge_address, slot DESC, event_index DESC);
+CREATE INDEX IF NOT EXISTS activity_wallet_idx ON activity (wallet_address, slot DESC, event_index DESC);
+
+-- Last processed position of each background indexer
+CREATE TABLE IF NOT EXISTS indexer_cursors (
+    name VARCHAR(64) PRIMARY KEY,
+    signature VARCHAR(128) NOT NULL,
+    slot BIGINT NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,10 @@
+-- Off-chain JSON metadata resolved from badge URIs
+CREATE TABLE IF NOT EXISTS badge_metadata (
+    uri TEXT PRIMARY KEY,
+    etag TEXT,
+    content JSONB,
+    error TEXT,
+    fetched_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
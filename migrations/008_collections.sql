@@ -0,0 +1,19 @@
+-- Creator-defined groupings of badges, shown on storefronts
+CREATE TABLE IF NOT EXISTS collections (
+    id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+    creator_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL CHECK (LENGTH(name) > 0),
+    description TEXT NOT NULL DEFAULT '',
+    cover_image TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS collections_creator_id_idx ON collections (creator_id);
+
+CREATE TABLE IF NOT EXISTS collection_badges (
+    collection_id UUID NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+    badge_address VARCHAR(64) NOT NULL,
+    position INT NOT NULL,
+    PRIMARY KEY (collection_id, badge_address)
+);
@@ -0,0 +1,50 @@
+-- Campaigns handing out a badge by claim code or to an allowlist of wallets
+CREATE TABLE IF NOT EXISTS claim_campaigns (
+    id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+    badge_address VARCHAR(64) NOT NULL,
+    creator_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    mode VARCHAR(16) NOT NULL CHECK (mode IN ('code', 'allowlist')),
+    -- Price a server-signed voucher grants, in the badge's base units. NULL mints at the badge price.
+    price BIGINT CHECK (price >= 0),
+    per_wallet_limit INT NOT NULL DEFAULT 1 CHECK (per_wallet_limit > 0),
+    starts_at TIMESTAMP WITH TIME ZONE,
+    ends_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS claim_campaigns_badge_idx ON claim_campaigns (badge_address);
+
+-- Only the sha256 of a code is kept; the plain code is shown once when generated
+CREATE TABLE IF NOT EXISTS claim_codes (
+    id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+    campaign_id UUID NOT NULL REFERENCES claim_campaigns(id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    max_uses INT NOT NULL CHECK (max_uses > 0),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (campaign_id, code_hash)
+);
+
+CREATE TABLE IF NOT EXISTS claim_allowlist (
+    campaign_id UUID NOT NULL REFERENCES claim_campaigns(id) ON DELETE CASCADE,
+    wallet_address VARCHAR(64) NOT NULL,
+    max_claims INT NOT NULL CHECK (max_claims > 0),
+    PRIMARY KEY (campaign_id, wallet_address)
+);
+
+-- Caps are counted from redemptions. A redemption stays `pending` until its sponsored mint
+-- lands (`landed`) or its blockhash expires unused (`released`), and released ones do not count.
+CREATE TABLE IF NOT EXISTS claim_redemptions (
+    id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+    campaign_id UUID NOT NULL REFERENCES claim_campaigns(id) ON DELETE CASCADE,
+    code_id UUID REFERENCES claim_codes(id) ON DELETE SET NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    wallet_address VARCHAR(64) NOT NULL,
+    signature VARCHAR(128) NOT NULL UNIQUE,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'landed', 'released')),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS claim_redemptions_wallet_idx ON claim_redemptions (campaign_id, wallet_address);
+CREATE INDEX IF NOT EXISTS claim_redemptions_code_idx ON claim_redemptions (code_id);
@@ -0,0 +1,33 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Searchable copy of every on-chain badge with its metadata and creator, rebuilt by the search indexer
+CREATE TABLE IF NOT EXISTS badge_search (
+    badge_address VARCHAR(64) PRIMARY KEY,
+    owner VARCHAR(64) NOT NULL,
+    creator_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    creator_username VARCHAR(255),
+    name TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    image TEXT NOT NULL DEFAULT '',
+    price BIGINT NOT NULL,
+    decimals SMALLINT NOT NULL,
+    supply BIGINT NOT NULL,
+    max_supply BIGINT NOT NULL,
+    paused BOOLEAN NOT NULL DEFAULT FALSE,
+    slot BIGINT NOT NULL,
+    -- Name and symbol outrank the creator, which outranks the description
+    document TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', name), 'A') ||
+        setweight(to_tsvector('simple', symbol), 'A') ||
+        setweight(to_tsvector('simple', COALESCE(creator_username, '')), 'B') ||
+        setweight(to_tsvector('simple', description), 'C')
+    ) STORED,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS badge_search_document_idx ON badge_search USING GIN (document);
+CREATE INDEX IF NOT EXISTS badge_search_name_trgm_idx ON badge_search USING GIN (name gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS badge_search_symbol_trgm_idx ON badge_search USING GIN (symbol gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS badge_search_creator_trgm_idx ON badge_search USING GIN (creator_username gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS users_username_trgm_idx ON users USING GIN (username gin_trgm_ops);
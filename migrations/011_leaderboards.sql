@@ -0,0 +1,29 @@
+-- Snapshots rebuilt by the leaderboard job so trending and collector pages are plain reads
+CREATE TABLE IF NOT EXISTS trending_snapshots (
+    period VARCHAR(8) NOT NULL,
+    badge_address VARCHAR(64) NOT NULL,
+    mints BIGINT NOT NULL,
+    previous_mints BIGINT NOT NULL,
+    unique_minters BIGINT NOT NULL,
+    velocity DOUBLE PRECISION NOT NULL,
+    score DOUBLE PRECISION NOT NULL,
+    rank BIGINT NOT NULL,
+    computed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (period, badge_address)
+);
+
+CREATE INDEX IF NOT EXISTS trending_snapshots_rank_idx ON trending_snapshots (period, rank);
+
+CREATE TABLE IF NOT EXISTS collector_snapshots (
+    holder VARCHAR(64) PRIMARY KEY,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    username VARCHAR(255),
+    badges_held BIGINT NOT NULL,
+    unique_badges BIGINT NOT NULL,
+    score DOUBLE PRECISION NOT NULL,
+    rank BIGINT NOT NULL,
+    computed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS collector_snapshots_rank_idx ON collector_snapshots (rank);
+CREATE INDEX IF NOT EXISTS badge_holdings_minted_at_idx ON badge_holdings (minted_at);
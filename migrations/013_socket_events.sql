@@ -0,0 +1,8 @@
+-- Socket events too large for a NOTIFY payload, fetched by id by the other instances
+CREATE TABLE IF NOT EXISTS socket_events (
+    id BIGSERIAL PRIMARY KEY,
+    event JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS socket_events_created_at_idx ON socket_events (created_at);
@@ -0,0 +1,6 @@
+-- Sponsored fees are reserved when the transaction is built, then settled once its blockhash
+-- expires: `landed` fees stay booked, `released` ones no longer count against the caps
+ALTER TABLE sponsored_fees ADD COLUMN IF NOT EXISTS status VARCHAR(16) NOT NULL DEFAULT 'landed';
+ALTER TABLE sponsored_fees ADD COLUMN IF NOT EXISTS recent_blockhash VARCHAR(64);
+
+CREATE INDEX IF NOT EXISTS sponsored_fees_reserved_idx ON sponsored_fees (created_at) WHERE status = 'reserved';
@@ -0,0 +1,3 @@
+-- Every indexer run stamps the rows it saw with its own generation and drops the rest. Slots
+-- could not tell stale rows apart once a scan came from an endpoint lagging behind the last one.
+ALTER TABLE badge_search ADD COLUMN IF NOT EXISTS generation UUID;
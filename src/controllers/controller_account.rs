@@ -0,0 +1,68 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_account::DecodedAccount;
+use crate::services::service_idl::Idl;
+use std::str::FromStr;
+use std::sync::Arc;
+use axum::{ extract::{ Path, State }, http::StatusCode, Json };
+use solana_sdk::pubkey::Pubkey;
+
+fn loaded_idl(app_state: &AppState) -> Result<&Idl, Error> {
+    app_state.idl
+        .as_ref()
+        .ok_or_else(|| Error::ServiceUnavailable("No program IDL is loaded.".to_string()))
+}
+
+// @route GET /api/accounts
+// @desc Get every program account decoded through the IDL
+// @access Public
+pub async fn get_all_accounts(State(app_state): State<Arc<AppState>>) -> Result<
+    (StatusCode, Json<Vec<DecodedAccount>>),
+    Error
+> {
+    let idl = loaded_idl(&app_state)?;
+    let accounts = app_state.rpc.get_program_accounts(&app_state.program_id).await?;
+
+    let mut decoded = vec![];
+
+    for (pubkey, account) in accounts {
+        match idl.decode_account(&account.data) {
+            Ok((account_type, data)) =>
+                decoded.push(DecodedAccount { address: pubkey.to_string(), account_type, data }),
+            Err(err) => {
+                println!("Failed to decode program account {} with the IDL: {}", pubkey, err);
+                app_state.metrics.incr("idl_account_decode_failures");
+            }
+        }
+    }
+
+    Ok((StatusCode::OK, Json(decoded)))
+}
+
+// @route GET /api/accounts/:address
+// @desc Get a program account decoded through the IDL
+// @access Public
+pub async fn get_account_by_address(
+    Path(address): Path<String>,
+    State(app_state): State<Arc<AppState>>
+) -> Result<(StatusCode, Json<DecodedAccount>), Error> {
+    let idl = loaded_idl(&app_state)?;
+    let pubkey = Pubkey::from_str(&address).map_err(|_|
+        Error::GetAccountError("Account address is invalid.".to_string())
+    )?;
+    let account = app_state.rpc
+        .get_account(&pubkey).await?
+        .ok_or_else(|| Error::GetAccountError("Account not found.".to_string()))?;
+
+    if account.owner != app_state.program_id {
+        return Err(Error::GetAccountError("Account is not owned by the program.".to_string()));
+    }
+
+    let (account_type, data) = idl.decode_account(&account.data).map_err(|err| {
+        println!("Failed to decode program account {} with the IDL: {}", pubkey, err);
+        app_state.metrics.incr("idl_account_decode_failures");
+        Error::GetAccountError(err.to_string())
+    })?;
+
+    Ok((StatusCode::OK, Json(DecodedAccount { address: pubkey.to_string(), account_type, data })))
+}
@@ -0,0 +1,56 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_activity::{ ActivityQuery, ActivityRecord };
+use crate::services::service_activity::{ fetch_activity, ActivityFeed };
+use std::str::FromStr;
+use std::sync::Arc;
+use axum::{ extract::{ Path, Query, State }, http::StatusCode, Json };
+use solana_sdk::pubkey::Pubkey;
+
+// @route GET /api/badges/:address/activity
+// @desc Get on-chain events for a badge, newest first
+// @access Public
+pub async fn get_badge_activity(
+    Path(address): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<ActivityQuery>
+) -> Result<(StatusCode, Json<Vec<ActivityRecord>>), Error> {
+    Pubkey::from_str(&address).map_err(|_|
+        Error::GetAccountError("Badge address is invalid.".to_string())
+    )?;
+
+    let activity = fetch_activity(
+        ActivityFeed::Badge,
+        &address,
+        query.kind.as_deref(),
+        query.limit,
+        query.offset,
+        &app_state.db
+    ).await?;
+
+    Ok((StatusCode::OK, Json(activity)))
+}
+
+// @route GET /api/wallets/:address/activity
+// @desc Get on-chain events involving a wallet, newest first
+// @access Public
+pub async fn get_wallet_activity(
+    Path(address): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<ActivityQuery>
+) -> Result<(StatusCode, Json<Vec<ActivityRecord>>), Error> {
+    Pubkey::from_str(&address).map_err(|_|
+        Error::GetAccountError("Wallet address is invalid.".to_string())
+    )?;
+
+    let activity = fetch_activity(
+        ActivityFeed::Wallet,
+        &address,
+        query.kind.as_deref(),
+        query.limit,
+        query.offset,
+        &app_state.db
+    ).await?;
+
+    Ok((StatusCode::OK, Json(activity)))
+}
@@ -0,0 +1,52 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_analytics::AnalyticsQuery;
+use crate::models::model_user::User;
+use crate::services::service_analytics::{
+    analytics_csv,
+    analytics_range,
+    fetch_creator_analytics,
+};
+use std::sync::Arc;
+use axum::{
+    extract::{ Query, State },
+    http::{ header, StatusCode },
+    response::{ IntoResponse, Response },
+    Extension,
+    Json,
+};
+
+// @route GET /api/creators/me/analytics
+// @desc Get daily or hourly mint, revenue and buyer stats for the user's badges, as JSON or CSV
+// @access Private
+pub async fn get_creator_analytics(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Query(query): Query<AnalyticsQuery>
+) -> Result<Response, Error> {
+    let (bucket, from, to) = analytics_range(query.bucket.as_deref(), query.from, query.to)?;
+    let analytics = fetch_creator_analytics(
+        &user.wallet_address,
+        &bucket,
+        from,
+        to,
+        query.badge.as_deref(),
+        &app_state.db
+    ).await?;
+
+    match query.format.as_deref().unwrap_or("json") {
+        "json" => Ok((StatusCode::OK, Json(analytics)).into_response()),
+        "csv" =>
+            Ok(
+                (
+                    StatusCode::OK,
+                    [
+                        (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                        (header::CONTENT_DISPOSITION, "attachment; filename=\"analytics.csv\""),
+                    ],
+                    analytics_csv(&analytics),
+                ).into_response()
+            ),
+        _ => Err(Error::AnalyticsError("Format must be \"json\" or \"csv\".".to_string())),
+    }
+}
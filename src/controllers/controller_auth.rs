@@ -6,6 +6,7 @@ use crate::services::service_auth::{
     parse_cookies_from_request,
 };
 use crate::models::model_user::LoginPayload;
+use crate::services::service_socket::disconnect_session;
 use crate::services::service_user::{ login, logout };
 use std::sync::Arc;
 
@@ -67,7 +68,8 @@ pub async fn logout_user(
 
     let cookies = parse_cookies_from_request(&request)?;
 
-    logout(cookies.refresh_token, &app_state.db).await?;
+    logout(cookies.refresh_token.clone(), &app_state.db).await?;
+    disconnect_session(&app_state.io, &cookies.refresh_token);
 
     headers.append(
         header::SET_COOKIE,
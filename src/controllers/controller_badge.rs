@@ -1,53 +1,197 @@
 use crate::errors::error::Error;
 use crate::database::db::AppState;
-use crate::models::model_badge::{ Badge, BadgeAccount };
+use crate::models::model_badge::{ Badge, BadgeAccount, BadgeRecord, CreateBadgePayload };
+use crate::models::model_account::PebbleAccount;
+use crate::models::model_transaction::{
+    BuiltTransaction,
+    CreateBadgeTransaction,
+    MintBadgePayload,
+    MintBadgeTransaction,
+};
+use crate::models::model_user::User;
+use crate::services::service_badge::{
+    build_mint_transaction,
+    fetch_badge,
+    validate_create_badge,
+};
+use crate::services::service_metadata::refresh_in_background;
+use crate::services::service_program::{
+    badge_pda,
+    build_transaction,
+    create_badge_instruction,
+    serialize_transaction,
+    CreateBadgeArgs,
+};
 use std::str::FromStr;
 use std::sync::Arc;
 use uuid::Uuid;
-use axum::{ extract::{ Path, State }, http::StatusCode, Json };
+use axum::{
+    extract::{ rejection::JsonRejection, Path, State },
+    http::{ header, HeaderMap, StatusCode },
+    Extension,
+    Json,
+};
 use borsh::{ BorshSerialize, BorshDeserialize };
-use solana_sdk::signature::{ Keypair, Signer };
 use solana_sdk::transaction::Transaction;
 use solana_sdk::pubkey::Pubkey;
-use solana_client::rpc_client::RpcClient;
 
 // @route GET /api/badges
 // @desc Get all badges
 // @access Public
 pub async fn get_all_badges(State(app_state): State<Arc<AppState>>) -> Result<
-    (StatusCode, Json<Vec<BadgeAccount>>),
+    (StatusCode, Json<Vec<Badge>>),
     Error
 > {
-    let rpc_url = std::env::var("RPC_URL").expect("RPC_URL must be set");
-    let program_id = Pubkey::from_str(
-        std::env::var("PROGRAM_ID").expect("PROGRAM_ID must be set").as_str()
-    ).unwrap();
-    let rpc_client = RpcClient::new(rpc_url.to_string());
-    let accounts = rpc_client.get_program_accounts(&program_id).unwrap();
+    let accounts = app_state.rpc.get_program_accounts(&app_state.program_id).await?;
 
     let mut badges = vec![];
 
     for (pubkey, account) in accounts {
-        // Skip the 8-byte discriminator
-        let badge_data = &account.data[8..];
-
-        // Try to deserialize and print how many bytes were read
-        let (badge, _) = match BadgeAccount::deserialize(&mut &badge_data[..]) {
-            Ok(badge) => (Some(badge), badge_data.len()),
-            Err(_) => (None, 0),
-        };
-
-        if let Some(badge) = badge {
-            println!("Account Pubkey: {:?}", pubkey);
-            println!("Account Data Length: {:?}", account.data.len());
-            println!("Badge Data: {:?}", badge);
-            badges.push(badge);
+        match PebbleAccount::decode(&account.data) {
+            Ok(PebbleAccount::Badge(account)) => badges.push(account.into_badge(&pubkey)),
+            Ok(_) => {}
+            Err(err) => {
+                println!("Failed to decode program account {}: {}", pubkey, err);
+                app_state.metrics.incr("program_account_decode_failures");
+            }
         }
     }
 
+    let stale = app_state.metadata.attach_cached(&mut badges, &app_state.db).await?;
+    refresh_in_background(app_state.clone(), stale);
+
     Ok((StatusCode::OK, Json(badges)))
 }
 
+// @route GET /api/badges/:address
+// @desc Get a badge with its resolved metadata
+// @access Public
+pub async fn get_badge(
+    Path(address): Path<String>,
+    State(app_state): State<Arc<AppState>>
+) -> Result<(StatusCode, Json<Badge>), Error> {
+    let badge_address = Pubkey::from_str(&address).map_err(|_|
+        Error::GetAccountError("Badge address is invalid.".to_string())
+    )?;
+
+    let mut badge = fetch_badge(&app_state, &badge_address).await?;
+    badge.metadata = app_state.metadata.resolve(&badge.uri, &app_state.db).await?;
+
+    Ok((StatusCode::OK, Json(badge)))
+}
+
+// @route POST /api/badges/create-tx
+// @desc Build an unsigned transaction creating a badge
+// @access Private
+pub async fn create_badge_tx(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Json(payload): Json<CreateBadgePayload>
+) -> Result<(StatusCode, Json<CreateBadgeTransaction>), Error> {
+    validate_create_badge(&payload)?;
+
+    let owner = Pubkey::from_str(&user.wallet_address).map_err(|_|
+        Error::CreateBadgeError("Wallet address is invalid.".to_string())
+    )?;
+
+    let (badge_address, _) = badge_pda(&app_state.program_id, &owner, &payload.symbol);
+    let record = BadgeRecord::new(user.id, badge_address.to_string(), &payload);
+
+    // The record holds the trimmed name, so the chain and the database agree on it
+    let args = CreateBadgeArgs {
+        name: record.badge_name.clone(),
+        symbol: payload.symbol.clone(),
+        uri: payload.uri.clone(),
+        price: payload.price,
+        max_supply: payload.max_supply,
+        decimals: payload.decimals.unwrap_or(9),
+    };
+
+    let (recent_blockhash, last_valid_block_height) = app_state.rpc.get_latest_blockhash().await?;
+    let (instructions, compute_budget) = app_state.fees.with_compute_budget(
+        &app_state.rpc,
+        &owner,
+        vec![create_badge_instruction(&app_state.program_id, &owner, &args)?],
+        recent_blockhash
+    ).await?;
+    let transaction = build_transaction(&owner, &instructions, recent_blockhash);
+
+    let mut session = app_state.db
+        .begin().await
+        .map_err(|_| Error::CreateBadgeError("Database connection failed.".to_string()))?;
+
+    match record.save(&mut session).await {
+        Ok(_) => {
+            session
+                .commit().await
+                .map_err(|_| Error::CreateBadgeError("Database commit failed.".to_string()))?;
+        }
+        Err(err) => {
+            session
+                .rollback().await
+                .map_err(|_| Error::CreateBadgeError("Database rollback failed.".to_string()))?;
+            return Err(err);
+        }
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateBadgeTransaction {
+            badge_address: badge_address.to_string(),
+            built: BuiltTransaction {
+                transaction: serialize_transaction(&transaction)?,
+                recent_blockhash: recent_blockhash.to_string(),
+                last_valid_block_height,
+                compute_budget,
+            },
+        }),
+    ))
+}
+
+fn has_body(headers: &HeaderMap) -> bool {
+    headers.contains_key(header::TRANSFER_ENCODING) ||
+        headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok())
+            .map_or(false, |length| length.trim() != "0")
+}
+
+// @route POST /api/badges/:address/mint-tx
+// @desc Build a transaction minting a badge to the user's wallet, optionally fee-sponsored
+// @access Private
+pub async fn mint_badge_tx(
+    Path(address): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    headers: HeaderMap,
+    payload: Result<Json<MintBadgePayload>, JsonRejection>
+) -> Result<(StatusCode, Json<MintBadgeTransaction>), Error> {
+    // Only a request without a body defaults to an unsponsored mint; a body that does not
+    // parse is refused rather than silently ignored
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(JsonRejection::MissingJsonContentType(_)) if !has_body(&headers) => {
+            MintBadgePayload::default()
+        }
+        Err(rejection) => {
+            return Err(Error::MintBadgeError(rejection.body_text()));
+        }
+    };
+    let badge_address = Pubkey::from_str(&address).map_err(|_|
+        Error::GetAccountError("Badge address is invalid.".to_string())
+    )?;
+
+    let transaction = build_mint_transaction(
+        &app_state,
+        &user,
+        &badge_address,
+        payload.sponsored.unwrap_or(false),
+        None
+    ).await?;
+
+    Ok((StatusCode::OK, Json(transaction)))
+}
+
 // // Replace with the public key of the wallet you want to check
 // let wallet_pubkey = "2g9K42Pt5y58cejTHFLhqoQWKDUcB3s3AnGESmV9ySBW".to_string();
 // let pubkey = Pubkey::from_str(&wallet_pubkey).unwrap();
@@ -0,0 +1,102 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_claim::{
+    AllowlistEntry,
+    CampaignCodes,
+    ClaimCampaign,
+    ClaimRedemption,
+    CodeBatch,
+    CreateCampaignPayload,
+    RedeemClaimPayload,
+};
+use crate::models::model_user::User;
+use crate::services::service_claim::{
+    add_allowlist,
+    add_codes,
+    fetch_badge_campaigns,
+    insert_campaign,
+    redeem_claim,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+use axum::{ extract::{ Path, State }, http::StatusCode, Extension, Json };
+use solana_sdk::pubkey::Pubkey;
+
+fn parse_badge(address: &str) -> Result<Pubkey, Error> {
+    Pubkey::from_str(address).map_err(|_|
+        Error::GetAccountError("Badge address is invalid.".to_string())
+    )
+}
+
+// @route POST /api/badges/:address/campaigns
+// @desc Create a claim campaign for a badge the user owns. Generated codes are only returned here.
+// @access Private
+pub async fn create_campaign(
+    Path(address): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Json(payload): Json<CreateCampaignPayload>
+) -> Result<(StatusCode, Json<CampaignCodes>), Error> {
+    let badge_address = parse_badge(&address)?;
+    let campaign = insert_campaign(&app_state, &user, &badge_address, payload).await?;
+
+    Ok((StatusCode::CREATED, Json(campaign)))
+}
+
+// @route GET /api/badges/:address/campaigns
+// @desc Get the user's claim campaigns for a badge
+// @access Private
+pub async fn get_badge_campaigns(
+    Path(address): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>
+) -> Result<(StatusCode, Json<Vec<ClaimCampaign>>), Error> {
+    let badge_address = parse_badge(&address)?;
+    let campaigns = fetch_badge_campaigns(&badge_address, &user, &app_state.db).await?;
+
+    Ok((StatusCode::OK, Json(campaigns)))
+}
+
+// @route POST /api/campaigns/:id/codes
+// @desc Generate more claim codes for a code campaign
+// @access Private
+pub async fn create_campaign_codes(
+    Path(id): Path<Uuid>,
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Json(batch): Json<CodeBatch>
+) -> Result<(StatusCode, Json<CampaignCodes>), Error> {
+    let codes = add_codes(id, &user, batch, &app_state.db).await?;
+
+    Ok((StatusCode::CREATED, Json(codes)))
+}
+
+// @route POST /api/campaigns/:id/allowlist
+// @desc Add wallets to an allowlist campaign, or change their per-wallet caps
+// @access Private
+pub async fn update_campaign_allowlist(
+    Path(id): Path<Uuid>,
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Json(entries): Json<Vec<AllowlistEntry>>
+) -> Result<StatusCode, Error> {
+    add_allowlist(id, &user, entries, &app_state.db).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// @route POST /api/campaigns/:id/redeem
+// @desc Redeem a claim code or allowlist spot for a fee-sponsored mint transaction, discounted by a signed voucher when the campaign has a price
+// @access Private
+pub async fn redeem_campaign(
+    Path(id): Path<Uuid>,
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    payload: Option<Json<RedeemClaimPayload>>
+) -> Result<(StatusCode, Json<ClaimRedemption>), Error> {
+    let Json(payload) = payload.unwrap_or_default();
+    let redemption = redeem_claim(&app_state, &user, id, payload).await?;
+
+    Ok((StatusCode::OK, Json(redemption)))
+}
@@ -0,0 +1,83 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_collection::{
+    CollectionWithBadges,
+    CreateCollectionPayload,
+    Storefront,
+    UpdateCollectionPayload,
+};
+use crate::models::model_user::User;
+use crate::services::service_collection::{
+    fetch_collection,
+    fetch_storefront,
+    insert_collection,
+    remove_collection,
+    save_collection,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+use axum::{ extract::{ Path, State }, http::StatusCode, Extension, Json };
+
+// @route POST /api/collections
+// @desc Create a collection of badges the user owns
+// @access Private
+pub async fn create_collection(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Json(payload): Json<CreateCollectionPayload>
+) -> Result<(StatusCode, Json<CollectionWithBadges>), Error> {
+    let collection = insert_collection(&app_state, &user, payload).await?;
+
+    Ok((StatusCode::CREATED, Json(collection)))
+}
+
+// @route GET /api/collections/:id
+// @desc Get a collection with its badges in display order
+// @access Public
+pub async fn get_collection(
+    Path(id): Path<Uuid>,
+    State(app_state): State<Arc<AppState>>
+) -> Result<(StatusCode, Json<CollectionWithBadges>), Error> {
+    let collection = fetch_collection(id, &app_state.db).await?;
+
+    Ok((StatusCode::OK, Json(collection)))
+}
+
+// @route PUT /api/collections/:id
+// @desc Update a collection. A `badges` list replaces the current one and its order.
+// @access Private
+pub async fn update_collection(
+    Path(id): Path<Uuid>,
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Json(payload): Json<UpdateCollectionPayload>
+) -> Result<(StatusCode, Json<CollectionWithBadges>), Error> {
+    let collection = save_collection(&app_state, &user, id, payload).await?;
+
+    Ok((StatusCode::OK, Json(collection)))
+}
+
+// @route DELETE /api/collections/:id
+// @desc Delete a collection
+// @access Private
+pub async fn delete_collection(
+    Path(id): Path<Uuid>,
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>
+) -> Result<StatusCode, Error> {
+    remove_collection(id, &user, &app_state.db).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// @route GET /api/creators/:id/storefront
+// @desc Get a creator's profile, collections, live and sold-out badges and total sales
+// @access Public
+pub async fn get_storefront(
+    Path(id): Path<Uuid>,
+    State(app_state): State<Arc<AppState>>
+) -> Result<(StatusCode, Json<Storefront>), Error> {
+    let storefront = fetch_storefront(app_state, id).await?;
+
+    Ok((StatusCode::OK, Json(storefront)))
+}
@@ -0,0 +1,90 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_holding::{ BadgeAccess, BadgeHolding, HoldingsQuery, OwnershipProof };
+use crate::models::model_user::User;
+use crate::services::service_holding::{
+    fetch_badge_holders,
+    fetch_wallet_holdings,
+    verify_ownership,
+};
+use crate::services::service_user::fetch_user_by_id;
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+use axum::{ extract::{ Path, Query, State }, http::StatusCode, Extension, Json };
+use solana_sdk::pubkey::Pubkey;
+
+fn parse_address(address: &str, what: &str) -> Result<Pubkey, Error> {
+    Pubkey::from_str(address).map_err(|_|
+        Error::GetAccountError(format!("{} address is invalid.", what))
+    )
+}
+
+// @route GET /api/badges/:address/holders
+// @desc Get the indexed holders of a badge
+// @access Public
+pub async fn get_badge_holders(
+    Path(address): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<HoldingsQuery>
+) -> Result<(StatusCode, Json<Vec<BadgeHolding>>), Error> {
+    let badge = parse_address(&address, "Badge")?;
+    let holders = fetch_badge_holders(
+        &badge.to_string(),
+        query.limit,
+        query.offset,
+        &app_state.db
+    ).await?;
+
+    Ok((StatusCode::OK, Json(holders)))
+}
+
+// @route GET /api/users/:id/badges
+// @desc Get the indexed badges held by a user's wallet
+// @access Public
+pub async fn get_user_badges(
+    Path(id): Path<Uuid>,
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<HoldingsQuery>
+) -> Result<(StatusCode, Json<Vec<BadgeHolding>>), Error> {
+    let user = fetch_user_by_id(id, &app_state.db).await?;
+    let holdings = fetch_wallet_holdings(
+        &user.wallet_address,
+        query.limit,
+        query.offset,
+        &app_state.db
+    ).await?;
+
+    Ok((StatusCode::OK, Json(holdings)))
+}
+
+// @route GET /api/wallets/:address/owns/:badge
+// @desc Check on-chain whether a wallet holds a badge, with the slot it was read at
+// @access Public
+pub async fn get_wallet_owns_badge(
+    Path((address, badge)): Path<(String, String)>,
+    State(app_state): State<Arc<AppState>>
+) -> Result<(StatusCode, Json<OwnershipProof>), Error> {
+    let wallet = parse_address(&address, "Wallet")?;
+    let badge = parse_address(&badge, "Badge")?;
+    let proof = verify_ownership(&app_state, &wallet, &badge).await?;
+
+    Ok((StatusCode::OK, Json(proof)))
+}
+
+// @route GET /api/badges/:address/access
+// @desc Confirm the user holds a badge, for token-gated content. Refused by the badge gate otherwise.
+// @access Private
+pub async fn get_badge_access(
+    Path(address): Path<String>,
+    Extension(user): Extension<User>
+) -> Result<(StatusCode, Json<BadgeAccess>), Error> {
+    Ok((
+        StatusCode::OK,
+        Json(BadgeAccess {
+            badge: address,
+            wallet: user.wallet_address,
+            access: true,
+        }),
+    ))
+}
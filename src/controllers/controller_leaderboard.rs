@@ -0,0 +1,35 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_leaderboard::{
+    CollectorScore,
+    LeaderboardQuery,
+    TrendingBadge,
+    TrendingQuery,
+};
+use crate::services::service_leaderboard::{ fetch_collectors, fetch_trending };
+use std::sync::Arc;
+use axum::{ extract::{ Query, State }, http::StatusCode, Json };
+
+// @route GET /api/badges/trending?window=24h
+// @desc Get the badges minting fastest over a rolling window, from the latest snapshot
+// @access Public
+pub async fn get_trending_badges(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<TrendingQuery>
+) -> Result<(StatusCode, Json<Vec<TrendingBadge>>), Error> {
+    let badges = fetch_trending(query.window.as_deref(), query.limit, &app_state.db).await?;
+
+    Ok((StatusCode::OK, Json(badges)))
+}
+
+// @route GET /api/leaderboards/collectors
+// @desc Get collectors ranked by rarity-weighted holdings, from the latest snapshot
+// @access Public
+pub async fn get_top_collectors(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<LeaderboardQuery>
+) -> Result<(StatusCode, Json<Vec<CollectorScore>>), Error> {
+    let collectors = fetch_collectors(query.limit, query.offset, &app_state.db).await?;
+
+    Ok((StatusCode::OK, Json(collectors)))
+}
@@ -0,0 +1,15 @@
+use crate::database::db::AppState;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::{ extract::State, http::StatusCode, Json };
+
+// @route GET /api/metrics
+// @desc Get in-process counters
+// @access Public
+pub async fn get_metrics(State(app_state): State<Arc<AppState>>) -> (
+    StatusCode,
+    Json<HashMap<String, u64>>,
+) {
+    (StatusCode::OK, Json(app_state.metrics.snapshot()))
+}
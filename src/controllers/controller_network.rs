@@ -0,0 +1,48 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_network::{ NetworkFees, NetworkFeesQuery };
+use crate::services::service_program::config_pda;
+use std::str::FromStr;
+use std::sync::Arc;
+use axum::{ extract::{ Query, State }, http::StatusCode, Json };
+use solana_sdk::pubkey::Pubkey;
+
+// @route GET /api/network/fees
+// @desc Get current priority fee estimates for writes to the given accounts
+// @access Public
+pub async fn get_network_fees(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<NetworkFeesQuery>
+) -> Result<(StatusCode, Json<NetworkFees>), Error> {
+    let mut accounts = vec![];
+
+    for account in query.accounts.unwrap_or_default().split(',') {
+        let account = account.trim();
+        if account.is_empty() {
+            continue;
+        }
+
+        accounts.push(
+            Pubkey::from_str(account).map_err(|_|
+                Error::GetAccountError(format!("Account address {} is invalid.", account))
+            )?
+        );
+    }
+
+    if accounts.is_empty() {
+        accounts.push(config_pda(&app_state.program_id).0);
+    }
+
+    let priority_fees = app_state.fees.estimate(&app_state.rpc, &accounts).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(NetworkFees {
+            accounts: accounts
+                .iter()
+                .map(|account| account.to_string())
+                .collect(),
+            priority_fees,
+        }),
+    ))
+}
@@ -0,0 +1,18 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_search::{ SearchQuery, SearchResults };
+use crate::services::service_search::search;
+use std::sync::Arc;
+use axum::{ extract::{ Query, State }, http::StatusCode, Json };
+
+// @route GET /api/search?q=
+// @desc Search badges by name, symbol, description and creator, with facets, plus matching users
+// @access Public
+pub async fn get_search(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<SearchQuery>
+) -> Result<(StatusCode, Json<SearchResults>), Error> {
+    let results = search(query, &app_state.db).await?;
+
+    Ok((StatusCode::OK, Json(results)))
+}
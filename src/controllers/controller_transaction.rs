@@ -0,0 +1,65 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_transaction::{ SubmitTransactionPayload, TransactionRecord };
+use crate::models::model_user::User;
+use crate::services::service_program::deserialize_transaction;
+use crate::services::service_transaction::{ fetch_transaction, insert_transaction };
+use std::sync::Arc;
+use axum::{ extract::{ Path, State }, http::StatusCode, Extension, Json };
+
+// @route POST /api/transactions
+// @desc Verify, simulate and submit a signed transaction
+// @access Private
+pub async fn submit_transaction(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Json(payload): Json<SubmitTransactionPayload>
+) -> Result<(StatusCode, Json<TransactionRecord>), Error> {
+    let transaction = deserialize_transaction(&payload.transaction)?;
+
+    if !transaction.is_signed() {
+        return Err(Error::SubmitTransactionError("Transaction is not fully signed.".to_string()));
+    }
+
+    transaction
+        .verify()
+        .map_err(|_| Error::SubmitTransactionError("Signature verification failed.".to_string()))?;
+
+    // Only relay transactions that invoke the Pebble program
+    let invokes_program = transaction.message.instructions
+        .iter()
+        .any(|instruction| {
+            transaction.message.account_keys.get(instruction.program_id_index as usize) ==
+                Some(&app_state.program_id)
+        });
+
+    if !invokes_program {
+        return Err(
+            Error::SubmitTransactionError("Transaction does not invoke the Pebble program.".to_string())
+        );
+    }
+
+    let simulation = app_state.rpc.simulate_transaction(&transaction).await?;
+
+    if let Some(err) = simulation.err {
+        let logs = simulation.logs.unwrap_or_default().join("\n");
+        return Err(Error::SubmitTransactionError(format!("Simulation failed: {}\n{}", err, logs)));
+    }
+
+    let signature = app_state.rpc.send_transaction(&transaction).await?;
+    let record = insert_transaction(&signature, &transaction, user.id, &app_state.db).await?;
+
+    Ok((StatusCode::ACCEPTED, Json(record)))
+}
+
+// @route GET /api/transactions/:signature
+// @desc Get the status of a relayed transaction
+// @access Public
+pub async fn get_transaction_by_signature(
+    Path(signature): Path<String>,
+    State(app_state): State<Arc<AppState>>
+) -> Result<(StatusCode, Json<TransactionRecord>), Error> {
+    let record = fetch_transaction(&signature, &app_state.db).await?;
+
+    Ok((StatusCode::OK, Json(record)))
+}
@@ -0,0 +1,97 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_upload::{ ImageUpload, MetadataUpload };
+use crate::models::model_user::User;
+use crate::services::service_upload::{
+    build_metadata,
+    metadata_document,
+    store_image,
+    store_metadata,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use axum::{ extract::{ Multipart, State }, http::StatusCode, Extension, Json };
+
+// Collects text fields and the `image` file from a multipart body
+async fn read_form(
+    mut multipart: Multipart
+) -> Result<(HashMap<String, String>, Option<Vec<u8>>), Error> {
+    let mut fields = HashMap::new();
+    let mut image = None;
+
+    while
+        let Some(field) = multipart
+            .next_field().await
+            .map_err(|err| Error::UploadError(err.body_text()))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+
+        if name == "image" {
+            let bytes = field.bytes().await.map_err(|err| Error::UploadError(err.body_text()))?;
+            image = Some(bytes.to_vec());
+        } else {
+            let value = field.text().await.map_err(|err| Error::UploadError(err.body_text()))?;
+            fields.insert(name, value);
+        }
+    }
+
+    Ok((fields, image))
+}
+
+// @route POST /api/uploads/image
+// @desc Upload a badge image and generate its thumbnail
+// @access Private
+pub async fn upload_image(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    multipart: Multipart
+) -> Result<(StatusCode, Json<ImageUpload>), Error> {
+    let (_, image) = read_form(multipart).await?;
+    let image = image.ok_or_else(|| Error::UploadError("An image file is required.".to_string()))?;
+
+    let upload = store_image(app_state.storage.as_ref(), &app_state.upload_limits, image).await?;
+    println!("User {} uploaded image {}", user.id, upload.image);
+
+    Ok((StatusCode::CREATED, Json(upload)))
+}
+
+// @route POST /api/uploads/metadata
+// @desc Upload a badge image with its name, symbol, description and attributes, returning the metadata URI
+// @access Private
+pub async fn upload_metadata(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    multipart: Multipart
+) -> Result<(StatusCode, Json<MetadataUpload>), Error> {
+    let (mut fields, image) = read_form(multipart).await?;
+    let image = image.ok_or_else(|| Error::UploadError("An image file is required.".to_string()))?;
+
+    let name = fields.remove("name").unwrap_or_default().trim().to_string();
+    let symbol = fields.remove("symbol").unwrap_or_default().trim().to_string();
+    let description = fields.remove("description").unwrap_or_default();
+    let external_url = fields.remove("external_url").filter(|url| !url.trim().is_empty());
+
+    // Validate the text fields before spending time on the image
+    let mut metadata = build_metadata(
+        name,
+        symbol,
+        description,
+        external_url,
+        fields.get("attributes").map(|attributes| attributes.as_str())
+    )?;
+
+    let upload = store_image(app_state.storage.as_ref(), &app_state.upload_limits, image).await?;
+    metadata.image = upload.image.clone();
+    let document = metadata_document(&metadata, &upload)?;
+    let uri = store_metadata(app_state.storage.as_ref(), &document).await?;
+    println!("User {} uploaded metadata {}", user.id, uri);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MetadataUpload {
+            uri,
+            image: upload,
+            metadata,
+        }),
+    ))
+}
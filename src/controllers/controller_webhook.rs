@@ -0,0 +1,107 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_user::User;
+use crate::models::model_webhook::{
+    CreateWebhookPayload,
+    CreatedWebhook,
+    DeliveriesQuery,
+    WebhookAttempt,
+    WebhookDelivery,
+    WebhookSubscription,
+};
+use crate::services::service_webhook::{
+    fetch_attempts,
+    fetch_deliveries,
+    fetch_webhooks,
+    insert_webhook,
+    remove_webhook,
+    replay_delivery,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+use axum::{ extract::{ Path, Query, State }, http::StatusCode, Extension, Json };
+
+// @route POST /api/webhooks
+// @desc Subscribe a URL to badge events. The signing secret is only returned here.
+// @access Private
+pub async fn create_webhook(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Json(payload): Json<CreateWebhookPayload>
+) -> Result<(StatusCode, Json<CreatedWebhook>), Error> {
+    let webhook = insert_webhook(&app_state, &user, payload).await?;
+
+    Ok((StatusCode::CREATED, Json(webhook)))
+}
+
+// @route GET /api/webhooks
+// @desc Get the user's webhooks
+// @access Private
+pub async fn get_webhooks(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>
+) -> Result<(StatusCode, Json<Vec<WebhookSubscription>>), Error> {
+    let webhooks = fetch_webhooks(&user, &app_state.db).await?;
+
+    Ok((StatusCode::OK, Json(webhooks)))
+}
+
+// @route DELETE /api/webhooks/:id
+// @desc Delete a webhook and its delivery log
+// @access Private
+pub async fn delete_webhook(
+    Path(id): Path<Uuid>,
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>
+) -> Result<StatusCode, Error> {
+    remove_webhook(id, &user, &app_state.db).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// @route GET /api/webhooks/:id/deliveries
+// @desc Get a webhook's deliveries, optionally by status
+// @access Private
+pub async fn get_deliveries(
+    Path(id): Path<Uuid>,
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Query(query): Query<DeliveriesQuery>
+) -> Result<(StatusCode, Json<Vec<WebhookDelivery>>), Error> {
+    let deliveries = fetch_deliveries(
+        id,
+        &user,
+        query.status.as_deref(),
+        query.limit,
+        query.offset,
+        &app_state.db
+    ).await?;
+
+    Ok((StatusCode::OK, Json(deliveries)))
+}
+
+// @route GET /api/webhooks/:id/deliveries/:delivery_id/attempts
+// @desc Get every HTTP attempt of a delivery
+// @access Private
+pub async fn get_attempts(
+    Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>
+) -> Result<(StatusCode, Json<Vec<WebhookAttempt>>), Error> {
+    let attempts = fetch_attempts(id, delivery_id, &user, &app_state.db).await?;
+
+    Ok((StatusCode::OK, Json(attempts)))
+}
+
+// @route POST /api/webhooks/:id/deliveries/:delivery_id/replay
+// @desc Send a delivery again, whatever its status
+// @access Private
+pub async fn replay_webhook_delivery(
+    Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<User>
+) -> Result<(StatusCode, Json<WebhookDelivery>), Error> {
+    let delivery = replay_delivery(id, delivery_id, &user, &app_state.db).await?;
+
+    Ok((StatusCode::ACCEPTED, Json(delivery)))
+}
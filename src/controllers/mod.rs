@@ -1,3 +1,16 @@
 pub mod controller_user;
 pub mod controller_auth;
 pub mod controller_badge;
+pub mod controller_metrics;
+pub mod controller_account;
+pub mod controller_transaction;
+pub mod controller_network;
+pub mod controller_holding;
+pub mod controller_activity;
+pub mod controller_upload;
+pub mod controller_collection;
+pub mod controller_claim;
+pub mod controller_search;
+pub mod controller_leaderboard;
+pub mod controller_analytics;
+pub mod controller_webhook;
@@ -1,8 +1,39 @@
+use crate::services::service_collection::StorefrontCache;
+use crate::services::service_events::EventBus;
+use crate::services::service_fees::FeeEstimator;
+use crate::services::service_gate::OwnershipCache;
+use crate::services::service_idl::Idl;
+use crate::services::service_keystore::Keystore;
+use crate::services::service_metadata::MetadataResolver;
+use crate::services::service_metrics::Metrics;
+use crate::services::service_rpc::RpcPool;
+use crate::services::service_sponsor::FeeSponsor;
+use crate::services::service_storage::Storage;
+use crate::services::service_upload::UploadLimits;
+use crate::services::service_webhook::WebhookDispatcher;
 use dotenv::dotenv;
+use socketioxide::SocketIo;
+use solana_sdk::pubkey::Pubkey;
 use sqlx::{ postgres::PgPoolOptions, Pool, Postgres };
+use std::sync::Arc;
 
 pub struct AppState {
     pub db: Pool<Postgres>,
+    pub metrics: Arc<Metrics>,
+    pub idl: Option<Idl>,
+    pub rpc: RpcPool,
+    pub program_id: Pubkey,
+    pub io: SocketIo,
+    pub events: EventBus,
+    pub keystore: Keystore,
+    pub sponsor: Option<FeeSponsor>,
+    pub fees: FeeEstimator,
+    pub ownership_cache: OwnershipCache,
+    pub storefronts: StorefrontCache,
+    pub metadata: MetadataResolver,
+    pub storage: Arc<dyn Storage>,
+    pub upload_limits: UploadLimits,
+    pub webhooks: WebhookDispatcher,
 }
 
 pub async fn connect() -> Pool<Postgres> {
@@ -8,6 +8,28 @@ pub enum Error {
     LoginError(String),
     UpdateUserError(String),
     Unauthorized(String),
+    GetAccountError(String),
+    CreateBadgeError(String),
+    MintBadgeError(String),
+    BadgeSoldOut,
+    BadgeRequired(String),
+    SponsorshipError(String),
+    SubmitTransactionError(String),
+    UploadError(String),
+    CollectionError(String),
+    GetCollectionError(String),
+    Forbidden(String),
+    ClaimError(String),
+    GetCampaignError(String),
+    SearchError(String),
+    LeaderboardError(String),
+    AnalyticsError(String),
+    WebhookError(String),
+    GetWebhookError(String),
+    PayloadTooLarge(String),
+    GetTransactionError(String),
+    RpcError(String),
+    ServiceUnavailable(String),
     InvalidToken,
     InternalServerError,
 }
@@ -24,6 +46,56 @@ impl IntoResponse for Error {
                 (StatusCode::BAD_REQUEST, message).into_response()
             }
             Error::Unauthorized(message) => { (StatusCode::UNAUTHORIZED, message).into_response() }
+            Error::GetAccountError(message) => {
+                (StatusCode::NOT_FOUND, message).into_response()
+            }
+            Error::CreateBadgeError(message) => {
+                (StatusCode::BAD_REQUEST, message).into_response()
+            }
+            Error::MintBadgeError(message) => {
+                (StatusCode::BAD_REQUEST, message).into_response()
+            }
+            Error::BadgeSoldOut => { (StatusCode::CONFLICT, "Badge is sold out").into_response() }
+            Error::BadgeRequired(message) => { (StatusCode::FORBIDDEN, message).into_response() }
+            Error::SponsorshipError(message) => {
+                (StatusCode::FORBIDDEN, message).into_response()
+            }
+            Error::SubmitTransactionError(message) => {
+                (StatusCode::BAD_REQUEST, message).into_response()
+            }
+            Error::UploadError(message) => { (StatusCode::BAD_REQUEST, message).into_response() }
+            Error::CollectionError(message) => {
+                (StatusCode::BAD_REQUEST, message).into_response()
+            }
+            Error::GetCollectionError(message) => {
+                (StatusCode::NOT_FOUND, message).into_response()
+            }
+            Error::Forbidden(message) => { (StatusCode::FORBIDDEN, message).into_response() }
+            Error::ClaimError(message) => { (StatusCode::BAD_REQUEST, message).into_response() }
+            Error::GetCampaignError(message) => {
+                (StatusCode::NOT_FOUND, message).into_response()
+            }
+            Error::SearchError(message) => { (StatusCode::BAD_REQUEST, message).into_response() }
+            Error::LeaderboardError(message) => {
+                (StatusCode::BAD_REQUEST, message).into_response()
+            }
+            Error::AnalyticsError(message) => {
+                (StatusCode::BAD_REQUEST, message).into_response()
+            }
+            Error::WebhookError(message) => { (StatusCode::BAD_REQUEST, message).into_response() }
+            Error::GetWebhookError(message) => {
+                (StatusCode::NOT_FOUND, message).into_response()
+            }
+            Error::PayloadTooLarge(message) => {
+                (StatusCode::PAYLOAD_TOO_LARGE, message).into_response()
+            }
+            Error::GetTransactionError(message) => {
+                (StatusCode::NOT_FOUND, message).into_response()
+            }
+            Error::RpcError(message) => { (StatusCode::BAD_GATEWAY, message).into_response() }
+            Error::ServiceUnavailable(message) => {
+                (StatusCode::SERVICE_UNAVAILABLE, message).into_response()
+            }
             Error::InvalidToken => { (StatusCode::UNAUTHORIZED, "Invalid token").into_response() }
             Error::InternalServerError => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error").into_response()
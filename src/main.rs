@@ -7,36 +7,127 @@ mod services;
 
 use axum::{ Router, serve };
 use database::db;
-use socketioxide::{ extract::SocketRef, SocketIo };
+use models::model_socket::SocketAuth;
+use services::{
+    service_activity,
+    service_collection::StorefrontCache,
+    service_events::{ self, EventBus },
+    service_fees::FeeEstimator,
+    service_gate::OwnershipCache,
+    service_holding,
+    service_idl,
+    service_keystore,
+    service_leaderboard,
+    service_metadata::MetadataResolver,
+    service_rpc,
+    service_search,
+    service_socket,
+    service_transaction,
+    service_metrics::Metrics,
+    service_sponsor::{ self, FeeSponsor },
+    service_storage,
+    service_upload::UploadLimits,
+    service_webhook::{ self, WebhookDispatcher },
+};
+use socketioxide::{ extract::{ SocketRef, TryData }, SocketIo };
 use std::sync::Arc;
 use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
+use tower_http::services::ServeDir;
 use tracing_subscriber::FmtSubscriber;
 
-async fn on_connect(socket: SocketRef) {
-    println!("Socket connected: {:?}", socket.id);
-}
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::subscriber::set_global_default(FmtSubscriber::default())?;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|arg| arg.as_str()) == Some("keystore") {
+        dotenv::dotenv().ok();
+        if let Err(err) = service_keystore::run_cli(&args[1..]) {
+            println!("{}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let pool = db::connect().await;
     // db::migrate(&pool).await;
-    let app_state = Arc::new(db::AppState { db: pool.clone() });
+    let idl = service_idl::load_from_env();
+    let keystore = service_keystore::Keystore::from_env();
+    let sponsor = FeeSponsor::from_keystore(&keystore);
+    let metrics = Arc::new(Metrics::default());
+    let (io_layer, io) = SocketIo::new_layer();
+
+    let app_state = Arc::new(db::AppState {
+        db: pool.clone(),
+        metrics: metrics.clone(),
+        idl,
+        rpc: service_rpc::RpcPool::connect(metrics.clone()),
+        program_id: service_rpc::program_id(),
+        io: io.clone(),
+        events: EventBus::new(io, pool.clone(), metrics),
+        keystore,
+        sponsor,
+        fees: FeeEstimator::from_env(),
+        ownership_cache: OwnershipCache::from_env(),
+        storefronts: StorefrontCache::from_env(),
+        metadata: MetadataResolver::from_env(),
+        storage: service_storage::from_env(),
+        upload_limits: UploadLimits::from_env(),
+        webhooks: WebhookDispatcher::from_env(),
+    });
+
+    let socket_state = app_state.clone();
+    app_state.io.ns("/", move |socket: SocketRef, auth: TryData<SocketAuth>| {
+        service_socket::on_connect(socket, auth, socket_state.clone())
+    });
+
+    let health_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(health_state.rpc.health_interval);
+        loop {
+            interval.tick().await;
+            health_state.rpc.check_health().await;
+        }
+    });
+    tokio::spawn(service_transaction::track_transactions(app_state.clone()));
+    tokio::spawn(service_sponsor::track_sponsored_fees(app_state.clone()));
+    tokio::spawn(service_holding::track_holdings(app_state.clone()));
+    tokio::spawn(service_activity::track_activity(app_state.clone()));
+    tokio::spawn(service_search::track_search(app_state.clone()));
+    tokio::spawn(service_leaderboard::track_leaderboards(app_state.clone()));
+    tokio::spawn(service_webhook::track_webhooks(app_state.clone()));
+    tokio::spawn(service_socket::track_socket_sessions(app_state.clone()));
+    tokio::spawn(service_events::track_event_fanout(app_state.clone()));
 
     let port = std::env::var("PORT").unwrap_or("5000".to_string());
     let address = "0.0.0.0:";
     let cors = CorsLayer::permissive();
     let listener = tokio::net::TcpListener::bind(format!("{}{}", address, port)).await.unwrap();
-    let (io_layer, io) = SocketIo::new_layer();
 
-    io.ns("/", on_connect);
-
-    let app_routes = Router::new()
+    let mut app_routes = Router::new()
         .merge(routes::route_user::user_route(app_state.clone()))
         .merge(routes::route_auth::auth_route(app_state.clone()))
         .merge(routes::route_badge::badge_route(app_state.clone()))
-        .layer(ServiceBuilder::new().layer(cors).layer(io_layer));
+        .merge(routes::route_transaction::transaction_route(app_state.clone()))
+        .merge(routes::route_account::account_route(app_state.clone()))
+        .merge(routes::route_metrics::metrics_route(app_state.clone()))
+        .merge(routes::route_network::network_route(app_state.clone()))
+        .merge(routes::route_holding::holding_route(app_state.clone()))
+        .merge(routes::route_activity::activity_route(app_state.clone()))
+        .merge(routes::route_upload::upload_route(app_state.clone()))
+        .merge(routes::route_collection::collection_route(app_state.clone()))
+        .merge(routes::route_claim::claim_route(app_state.clone()))
+        .merge(routes::route_search::search_route(app_state.clone()))
+        .merge(routes::route_leaderboard::leaderboard_route(app_state.clone()))
+        .merge(routes::route_analytics::analytics_route(app_state.clone()))
+        .merge(routes::route_webhook::webhook_route(app_state.clone()));
+
+    if let Some(dir) = app_state.storage.local_dir() {
+        app_routes = app_routes.nest_service("/uploads", ServeDir::new(dir));
+    }
+
+    let app_routes = app_routes.layer(ServiceBuilder::new().layer(cors).layer(io_layer));
 
     println!("Listening on http://{}", listener.local_addr().unwrap());
     serve(listener, app_routes).await.unwrap();
@@ -1,2 +1,16 @@
 pub mod model_user;
 pub mod model_badge;
+pub mod model_account;
+pub mod model_transaction;
+pub mod model_network;
+pub mod model_holding;
+pub mod model_activity;
+pub mod model_metadata;
+pub mod model_upload;
+pub mod model_collection;
+pub mod model_claim;
+pub mod model_search;
+pub mod model_leaderboard;
+pub mod model_analytics;
+pub mod model_webhook;
+pub mod model_socket;
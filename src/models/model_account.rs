@@ -0,0 +1,125 @@
+use crate::models::model_badge::{ BadgeAccount, BadgeAccountV2, VersionedBadgeAccount };
+use borsh::BorshDeserialize;
+use serde::{ Deserialize, Serialize };
+use solana_sdk::hash::hash;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+#[derive(Serialize, Deserialize, BorshDeserialize, Debug)]
+pub struct MintReceiptAccount {
+    pub badge: Pubkey,
+    pub holder: Pubkey,
+    pub number: u64,
+    pub price_paid: u64,
+    pub minted_at: i64,
+}
+
+#[derive(Serialize, Deserialize, BorshDeserialize, Debug)]
+pub struct ConfigAccount {
+    pub authority: Pubkey,
+    pub treasury: Pubkey,
+    pub fee_basis_points: u16,
+    pub badge_count: u64,
+}
+
+// A program account decoded through the IDL
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DecodedAccount {
+    pub address: String,
+    pub account_type: String,
+    pub data: serde_json::Value,
+}
+
+// Every account type the Pebble program owns, keyed by its Anchor discriminator
+#[derive(Debug)]
+pub enum PebbleAccount {
+    Badge(VersionedBadgeAccount),
+    MintReceipt(MintReceiptAccount),
+    Config(ConfigAccount),
+}
+
+#[derive(Debug)]
+pub enum AccountDecodeError {
+    TooShort(usize),
+    UnknownDiscriminator([u8; DISCRIMINATOR_LEN]),
+    InvalidData(&'static str, String),
+}
+
+impl fmt::Display for AccountDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountDecodeError::TooShort(len) => {
+                write!(f, "account data is {} bytes, shorter than the discriminator", len)
+            }
+            AccountDecodeError::UnknownDiscriminator(discriminator) => {
+                write!(f, "unknown account discriminator {:?}", discriminator)
+            }
+            AccountDecodeError::InvalidData(account_type, err) => {
+                write!(f, "failed to decode {}: {}", account_type, err)
+            }
+        }
+    }
+}
+
+// Anchor account discriminator: the first 8 bytes of sha256("account:<Name>")
+pub fn account_discriminator(name: &str) -> [u8; DISCRIMINATOR_LEN] {
+    let mut discriminator = [0u8; DISCRIMINATOR_LEN];
+    discriminator.copy_from_slice(
+        &hash(format!("account:{}", name).as_bytes()).to_bytes()[..DISCRIMINATOR_LEN]
+    );
+    discriminator
+}
+
+fn decode_body<T: BorshDeserialize>(
+    account_type: &'static str,
+    mut body: &[u8]
+) -> Result<T, AccountDecodeError> {
+    T::deserialize(&mut body).map_err(|err|
+        AccountDecodeError::InvalidData(account_type, err.to_string())
+    )
+}
+
+impl PebbleAccount {
+    pub fn decode(data: &[u8]) -> Result<Self, AccountDecodeError> {
+        if data.len() < DISCRIMINATOR_LEN {
+            return Err(AccountDecodeError::TooShort(data.len()));
+        }
+
+        let (discriminator, body) = data.split_at(DISCRIMINATOR_LEN);
+
+        if discriminator == account_discriminator("BadgeAccount") {
+            return decode_body::<BadgeAccount>("BadgeAccount", body).map(|account|
+                PebbleAccount::Badge(VersionedBadgeAccount::V1(account))
+            );
+        }
+
+        if discriminator == account_discriminator("BadgeAccountV2") {
+            return decode_body::<BadgeAccountV2>("BadgeAccountV2", body).map(|account|
+                PebbleAccount::Badge(VersionedBadgeAccount::V2(account))
+            );
+        }
+
+        if discriminator == account_discriminator("MintReceiptAccount") {
+            return decode_body("MintReceiptAccount", body).map(PebbleAccount::MintReceipt);
+        }
+
+        if discriminator == account_discriminator("ConfigAccount") {
+            return decode_body("ConfigAccount", body).map(PebbleAccount::Config);
+        }
+
+        let mut unknown = [0u8; DISCRIMINATOR_LEN];
+        unknown.copy_from_slice(discriminator);
+        Err(AccountDecodeError::UnknownDiscriminator(unknown))
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            PebbleAccount::Badge(VersionedBadgeAccount::V1(_)) => "BadgeAccount",
+            PebbleAccount::Badge(VersionedBadgeAccount::V2(_)) => "BadgeAccountV2",
+            PebbleAccount::MintReceipt(_) => "MintReceiptAccount",
+            PebbleAccount::Config(_) => "ConfigAccount",
+        }
+    }
+}
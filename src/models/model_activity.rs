@@ -0,0 +1,162 @@
+use crate::models::model_account::DISCRIMINATOR_LEN;
+use borsh::BorshDeserialize;
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use serde_json::json;
+use solana_sdk::hash::hash;
+use solana_sdk::pubkey::Pubkey;
+use sqlx::FromRow;
+
+// Event "BadgeCreated"
+#[derive(BorshDeserialize, Debug)]
+pub struct BadgeCreatedEvent {
+    pub badge: Pubkey,
+    pub owner: Pubkey,
+    pub price: u64,
+    pub max_supply: u64,
+    pub created_at: i64,
+}
+
+// Event "BadgeMinted"
+#[derive(BorshDeserialize, Debug)]
+pub struct BadgeMintedEvent {
+    pub badge: Pubkey,
+    pub receipt: Pubkey,
+    pub holder: Pubkey,
+    pub number: u64,
+    pub price_paid: u64,
+    pub minted_at: i64,
+}
+
+// Event "PriceChanged"
+#[derive(BorshDeserialize, Debug)]
+pub struct PriceChangedEvent {
+    pub badge: Pubkey,
+    pub owner: Pubkey,
+    pub old_price: u64,
+    pub new_price: u64,
+    pub changed_at: i64,
+}
+
+// An Anchor `emit!` payload from a "Program data:" log line
+#[derive(Debug)]
+pub enum ProgramEvent {
+    BadgeCreated(BadgeCreatedEvent),
+    BadgeMinted(BadgeMintedEvent),
+    PriceChanged(PriceChangedEvent),
+}
+
+// Anchor event discriminator: the first 8 bytes of sha256("event:<Name>")
+pub fn event_discriminator(name: &str) -> [u8; DISCRIMINATOR_LEN] {
+    let mut discriminator = [0u8; DISCRIMINATOR_LEN];
+    discriminator.copy_from_slice(
+        &hash(format!("event:{}", name).as_bytes()).to_bytes()[..DISCRIMINATOR_LEN]
+    );
+    discriminator
+}
+
+impl ProgramEvent {
+    // Returns None for events this server does not track
+    pub fn decode(data: &[u8]) -> Result<Option<Self>, String> {
+        if data.len() < DISCRIMINATOR_LEN {
+            return Err(format!("event data is {} bytes, shorter than the discriminator", data.len()));
+        }
+
+        let (discriminator, mut body) = data.split_at(DISCRIMINATOR_LEN);
+
+        let event = if discriminator == event_discriminator("BadgeCreated") {
+            ProgramEvent::BadgeCreated(
+                BadgeCreatedEvent::deserialize(&mut body).map_err(|err| err.to_string())?
+            )
+        } else if discriminator == event_discriminator("BadgeMinted") {
+            ProgramEvent::BadgeMinted(
+                BadgeMintedEvent::deserialize(&mut body).map_err(|err| err.to_string())?
+            )
+        } else if discriminator == event_discriminator("PriceChanged") {
+            ProgramEvent::PriceChanged(
+                PriceChangedEvent::deserialize(&mut body).map_err(|err| err.to_string())?
+            )
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(event))
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ProgramEvent::BadgeCreated(_) => "badge_created",
+            ProgramEvent::BadgeMinted(_) => "badge_minted",
+            ProgramEvent::PriceChanged(_) => "price_changed",
+        }
+    }
+
+    pub fn badge(&self) -> Pubkey {
+        match self {
+            ProgramEvent::BadgeCreated(event) => event.badge,
+            ProgramEvent::BadgeMinted(event) => event.badge,
+            ProgramEvent::PriceChanged(event) => event.badge,
+        }
+    }
+
+    // The wallet the event is about: the creator, the minter or the owner changing the price
+    pub fn wallet(&self) -> Pubkey {
+        match self {
+            ProgramEvent::BadgeCreated(event) => event.owner,
+            ProgramEvent::BadgeMinted(event) => event.holder,
+            ProgramEvent::PriceChanged(event) => event.owner,
+        }
+    }
+
+    pub fn data(&self) -> serde_json::Value {
+        match self {
+            ProgramEvent::BadgeCreated(event) =>
+                json!({
+                    "badge": event.badge.to_string(),
+                    "owner": event.owner.to_string(),
+                    "price": event.price,
+                    "max_supply": event.max_supply,
+                    "created_at": event.created_at,
+                }),
+            ProgramEvent::BadgeMinted(event) =>
+                json!({
+                    "badge": event.badge.to_string(),
+                    "receipt": event.receipt.to_string(),
+                    "holder": event.holder.to_string(),
+                    "number": event.number,
+                    "price_paid": event.price_paid,
+                    "minted_at": event.minted_at,
+                }),
+            ProgramEvent::PriceChanged(event) =>
+                json!({
+                    "badge": event.badge.to_string(),
+                    "owner": event.owner.to_string(),
+                    "old_price": event.old_price,
+                    "new_price": event.new_price,
+                    "changed_at": event.changed_at,
+                }),
+        }
+    }
+}
+
+// Row in the `activity` table
+#[derive(FromRow, Debug, Serialize, Deserialize, Clone)]
+pub struct ActivityRecord {
+    pub id: i64,
+    pub signature: String,
+    pub event_index: i32,
+    pub slot: i64,
+    pub block_time: Option<i64>,
+    pub kind: String,
+    pub badge_address: String,
+    pub wallet_address: String,
+    pub data: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ActivityQuery {
+    pub kind: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
@@ -0,0 +1,72 @@
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::FromRow;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnalyticsQuery {
+    // `day` or `hour`
+    pub bucket: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub badge: Option<String>,
+    // `json` or `csv`
+    pub format: Option<String>,
+}
+
+// Mints of one badge in one time bucket. A repeat buyer had already bought one of the
+// creator's badges before the bucket started.
+#[derive(FromRow, Debug, Serialize, Deserialize)]
+pub struct AnalyticsBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub badge_address: String,
+    pub name: Option<String>,
+    pub mints: i64,
+    // In the badge's base units, lamports for badges priced in SOL
+    pub revenue_lamports: i64,
+    // Only for badges priced in SOL (9 decimals)
+    #[sqlx(default)]
+    pub revenue_sol: Option<f64>,
+    pub unique_buyers: i64,
+    pub repeat_buyers: i64,
+    // All mints of the badge up to the end of the bucket
+    pub cumulative_mints: i64,
+    #[sqlx(default)]
+    pub sell_through_percent: Option<f64>,
+    #[serde(skip)]
+    pub decimals: i16,
+    #[serde(skip)]
+    pub max_supply: Option<i64>,
+}
+
+// Totals for one badge over the requested range. Repeat buyers had bought from the creator
+// before the range started.
+#[derive(FromRow, Debug, Serialize, Deserialize)]
+pub struct BadgeAnalytics {
+    pub badge_address: String,
+    pub name: Option<String>,
+    pub mints: i64,
+    // In the badge's base units, lamports for badges priced in SOL
+    pub revenue_lamports: i64,
+    // Only for badges priced in SOL (9 decimals)
+    #[sqlx(default)]
+    pub revenue_sol: Option<f64>,
+    pub unique_buyers: i64,
+    pub repeat_buyers: i64,
+    pub supply: Option<i64>,
+    pub max_supply: Option<i64>,
+    #[sqlx(default)]
+    pub sell_through_percent: Option<f64>,
+    #[serde(skip)]
+    pub decimals: i16,
+    #[serde(skip)]
+    pub total_mints: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatorAnalytics {
+    pub bucket: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub badges: Vec<BadgeAnalytics>,
+    pub series: Vec<AnalyticsBucket>,
+}
@@ -1,14 +1,17 @@
 use crate::errors::error::Error;
+use crate::models::model_metadata::BadgeMetadata;
 use serde::{ Deserialize, Serialize };
 use sqlx::{ FromRow, Postgres, Transaction };
 use uuid::Uuid;
 use borsh::BorshDeserialize;
 use solana_sdk::pubkey::Pubkey;
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+// Domain model every on-chain layout version is decoded into
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct Badge {
-    pub id: Uuid,
-    pub owner: Pubkey,
+    pub address: String,
+    pub version: u8,
+    pub owner: String,
     pub supply: u64,
     pub max_supply: u64,
     pub price: u64,
@@ -17,8 +20,14 @@ pub struct Badge {
     pub symbol: String,
     pub uri: String,
     pub created_at: i64,
+    pub royalty_basis_points: u16,
+    pub paused: bool,
+    // Resolved from `uri`, absent until it has been fetched and validated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<BadgeMetadata>,
 }
 
+// Layout v1, discriminator "account:BadgeAccount"
 #[derive(Serialize, Deserialize, BorshDeserialize, Debug)]
 pub struct BadgeAccount {
     pub owner: Pubkey,
@@ -31,3 +40,242 @@ pub struct BadgeAccount {
     pub uri: String,
     pub created_at: i64,
 }
+
+// Layout v2, discriminator "account:BadgeAccountV2". Appends royalties and a pause flag to v1.
+#[derive(Serialize, Deserialize, BorshDeserialize, Debug)]
+pub struct BadgeAccountV2 {
+    pub owner: Pubkey,
+    pub supply: u64,
+    pub max_supply: u64,
+    pub price: u64,
+    pub decimals: u8,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub created_at: i64,
+    pub royalty_basis_points: u16,
+    pub paused: bool,
+}
+
+// A badge account in whichever layout it was written with.
+// Fields appended to a layout without a new discriminator are tolerated since borsh
+// ignores trailing bytes; renaming the account type is how the program bumps the version.
+#[derive(Debug)]
+pub enum VersionedBadgeAccount {
+    V1(BadgeAccount),
+    V2(BadgeAccountV2),
+}
+
+impl VersionedBadgeAccount {
+    pub fn version(&self) -> u8 {
+        match self {
+            VersionedBadgeAccount::V1(_) => 1,
+            VersionedBadgeAccount::V2(_) => 2,
+        }
+    }
+
+    pub fn into_badge(self, address: &Pubkey) -> Badge {
+        let version = self.version();
+
+        match self {
+            VersionedBadgeAccount::V1(account) =>
+                Badge {
+                    address: address.to_string(),
+                    version,
+                    owner: account.owner.to_string(),
+                    supply: account.supply,
+                    max_supply: account.max_supply,
+                    price: account.price,
+                    decimals: account.decimals,
+                    name: account.name,
+                    symbol: account.symbol,
+                    uri: account.uri,
+                    created_at: account.created_at,
+                    royalty_basis_points: 0,
+                    paused: false,
+                    metadata: None,
+                },
+            VersionedBadgeAccount::V2(account) =>
+                Badge {
+                    address: address.to_string(),
+                    version,
+                    owner: account.owner.to_string(),
+                    supply: account.supply,
+                    max_supply: account.max_supply,
+                    price: account.price,
+                    decimals: account.decimals,
+                    name: account.name,
+                    symbol: account.symbol,
+                    uri: account.uri,
+                    created_at: account.created_at,
+                    royalty_basis_points: account.royalty_basis_points,
+                    paused: account.paused,
+                    metadata: None,
+                },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateBadgePayload {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub price: u64,
+    pub max_supply: u64,
+    pub decimals: Option<u8>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+}
+
+// Row in the `badges` table, written as `pending` when its create transaction is built
+#[derive(FromRow, Debug, Serialize, Deserialize, Clone)]
+pub struct BadgeRecord {
+    pub id: Uuid,
+    pub creator_id: Uuid,
+    pub badge_address: String,
+    pub badge_name: String,
+    pub badge_symbol: String,
+    pub badge_max_supply: i32,
+    pub badge_description: String,
+    pub badge_image: String,
+    pub badge_uri: String,
+    pub badge_price: i64,
+    pub badge_decimals: i16,
+    pub status: String,
+}
+
+impl BadgeRecord {
+    pub fn new(creator_id: Uuid, badge_address: String, payload: &CreateBadgePayload) -> Self {
+        BadgeRecord {
+            id: Uuid::new_v4(),
+            creator_id,
+            badge_address,
+            badge_name: payload.name.trim().to_string(),
+            badge_symbol: payload.symbol.clone(),
+            badge_max_supply: payload.max_supply as i32,
+            badge_description: payload.description.clone().unwrap_or_default(),
+            badge_image: payload.image.clone().unwrap_or_default(),
+            badge_uri: payload.uri.clone(),
+            badge_price: payload.price as i64,
+            badge_decimals: payload.decimals.unwrap_or(9) as i16,
+            status: "pending".to_string(),
+        }
+    }
+
+    // Inserts the badge, or refreshes it while it is still pending so a creator can rebuild the transaction
+    pub async fn save(&self, session: &mut Transaction<'_, Postgres>) -> Result<(), Error> {
+        let result = sqlx
+            ::query(
+                "INSERT INTO badges (id, creator_id, badge_address, badge_name, badge_symbol, badge_max_supply, badge_description, badge_image, badge_uri, badge_price, badge_decimals, status) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) \
+                 ON CONFLICT (badge_address) DO UPDATE SET \
+                 badge_name = EXCLUDED.badge_name, badge_max_supply = EXCLUDED.badge_max_supply, \
+                 badge_description = EXCLUDED.badge_description, badge_image = EXCLUDED.badge_image, \
+                 badge_uri = EXCLUDED.badge_uri, badge_price = EXCLUDED.badge_price, \
+                 badge_decimals = EXCLUDED.badge_decimals, updated_at = NOW() \
+                 WHERE badges.status = 'pending' AND badges.creator_id = EXCLUDED.creator_id"
+            )
+            .bind(&self.id)
+            .bind(&self.creator_id)
+            .bind(&self.badge_address)
+            .bind(&self.badge_name)
+            .bind(&self.badge_symbol)
+            .bind(&self.badge_max_supply)
+            .bind(&self.badge_description)
+            .bind(&self.badge_image)
+            .bind(&self.badge_uri)
+            .bind(&self.badge_price)
+            .bind(&self.badge_decimals)
+            .bind(&self.status)
+            .execute(session).await
+            .map_err(|err| {
+                let error_message = format!("Database insert failed: {}", err);
+                println!("{}", error_message);
+
+                let unique_violation = err
+                    .as_database_error()
+                    .and_then(|err| err.code())
+                    .is_some_and(|code| code == "23505");
+
+                if unique_violation {
+                    Error::CreateBadgeError("A badge with this name or symbol already exists.".to_string())
+                } else {
+                    Error::InternalServerError
+                }
+            })?;
+
+        if result.rows_affected() == 0 {
+            return Err(Error::CreateBadgeError("Badge has already been created.".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::model_account::PebbleAccount;
+
+    const OWNER: [u8; 32] = [
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32,
+    ];
+
+    fn decode_fixture(data: &[u8]) -> Badge {
+        match PebbleAccount::decode(data).expect("fixture should decode") {
+            PebbleAccount::Badge(account) => account.into_badge(&Pubkey::default()),
+            other => panic!("expected a badge, got {}", other.type_name()),
+        }
+    }
+
+    fn assert_common_fields(badge: &Badge) {
+        assert_eq!(badge.owner, Pubkey::new_from_array(OWNER).to_string());
+        assert_eq!(badge.supply, 12);
+        assert_eq!(badge.max_supply, 100);
+        assert_eq!(badge.price, 250_000_000);
+        assert_eq!(badge.decimals, 9);
+        assert_eq!(badge.name, "Pebble Genesis");
+        assert_eq!(badge.symbol, "PBLG");
+        assert_eq!(badge.uri, "https://arweave.net/pebble-genesis.json");
+        assert_eq!(badge.created_at, 1717200000);
+    }
+
+    #[test]
+    fn decodes_v1_fixture() {
+        let badge = decode_fixture(include_bytes!("../../fixtures/accounts/badge_account_v1.bin"));
+
+        assert_eq!(badge.version, 1);
+        assert_common_fields(&badge);
+        assert_eq!(badge.royalty_basis_points, 0);
+        assert!(!badge.paused);
+    }
+
+    #[test]
+    fn decodes_padded_v1_fixture() {
+        let badge = decode_fixture(
+            include_bytes!("../../fixtures/accounts/badge_account_v1_padded.bin")
+        );
+
+        assert_eq!(badge.version, 1);
+        assert_common_fields(&badge);
+    }
+
+    #[test]
+    fn decodes_v2_fixture() {
+        let badge = decode_fixture(include_bytes!("../../fixtures/accounts/badge_account_v2.bin"));
+
+        assert_eq!(badge.version, 2);
+        assert_common_fields(&badge);
+        assert_eq!(badge.royalty_basis_points, 500);
+        assert!(badge.paused);
+    }
+
+    #[test]
+    fn rejects_truncated_v2_fixture() {
+        let data = include_bytes!("../../fixtures/accounts/badge_account_v2.bin");
+
+        assert!(PebbleAccount::decode(&data[..data.len() - 3]).is_err());
+    }
+}
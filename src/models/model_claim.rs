@@ -0,0 +1,85 @@
+use crate::models::model_transaction::MintBadgeTransaction;
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::FromRow;
+use uuid::Uuid;
+
+// Row in the `claim_campaigns` table. `mode` is `code` or `allowlist`. A claim hands out a
+// fee-sponsored mint, at `price` through a server-signed voucher when set, else at the badge price.
+#[derive(FromRow, Debug, Serialize, Deserialize, Clone)]
+pub struct ClaimCampaign {
+    pub id: Uuid,
+    pub badge_address: String,
+    pub creator_id: Uuid,
+    pub name: String,
+    pub mode: String,
+    pub price: Option<i64>,
+    pub per_wallet_limit: i32,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CodeBatch {
+    pub count: u32,
+    // Redemptions each code allows, 1 for single-use codes
+    pub max_uses: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllowlistEntry {
+    pub wallet_address: String,
+    pub max_claims: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCampaignPayload {
+    pub name: String,
+    pub mode: String,
+    // Discounted price in the badge's base units, 0 for free mints
+    pub price: Option<u64>,
+    pub per_wallet_limit: Option<i32>,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub codes: Option<CodeBatch>,
+    pub allowlist: Option<Vec<AllowlistEntry>>,
+}
+
+// Plain codes are only ever returned here, right after they are generated
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CampaignCodes {
+    pub campaign: ClaimCampaign,
+    pub codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RedeemClaimPayload {
+    pub code: Option<String>,
+}
+
+// Signed by the claim signer key over `message`, which the program checks through the
+// ed25519 precompile before minting at `price`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClaimVoucher {
+    pub badge_address: String,
+    pub wallet_address: String,
+    pub price: u64,
+    pub nonce: String,
+    pub expires_at: i64,
+    pub message: String,
+    pub signature: String,
+    pub signer: String,
+}
+
+// The mint stays pending, and counts against the caps, until it lands or its blockhash expires
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClaimRedemption {
+    pub id: Uuid,
+    pub campaign_id: Uuid,
+    pub transaction: MintBadgeTransaction,
+    // Already carried by the transaction, returned for clients that show the claim terms
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voucher: Option<ClaimVoucher>,
+}
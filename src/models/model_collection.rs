@@ -0,0 +1,54 @@
+use crate::models::model_badge::Badge;
+use crate::models::model_user::User;
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::FromRow;
+use uuid::Uuid;
+
+// Row in the `collections` table
+#[derive(FromRow, Debug, Serialize, Deserialize, Clone)]
+pub struct Collection {
+    pub id: Uuid,
+    pub creator_id: Uuid,
+    pub name: String,
+    pub description: String,
+    pub cover_image: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// A collection with its badge addresses in display order
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionWithBadges {
+    #[serde(flatten)]
+    pub collection: Collection,
+    pub badges: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCollectionPayload {
+    pub name: String,
+    pub description: Option<String>,
+    pub cover_image: Option<String>,
+    pub badges: Option<Vec<String>>,
+}
+
+// Omitted fields are left unchanged; `badges` replaces the whole ordered list
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateCollectionPayload {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub cover_image: Option<String>,
+    pub badges: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Storefront {
+    pub creator: User,
+    pub collections: Vec<CollectionWithBadges>,
+    pub live_badges: Vec<Badge>,
+    pub sold_out_badges: Vec<Badge>,
+    pub total_sales: u64,
+    // Sales of badges priced in SOL only, since other tokens' base units do not add up with lamports
+    pub total_sales_lamports: u64,
+}
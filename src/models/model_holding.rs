@@ -0,0 +1,42 @@
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::FromRow;
+
+// Row in the `badge_holdings` table, indexed from a mint receipt account
+#[derive(FromRow, Debug, Serialize, Deserialize, Clone)]
+pub struct BadgeHolding {
+    pub receipt_address: String,
+    pub badge_address: String,
+    pub holder: String,
+    pub number: i64,
+    pub price_paid: i64,
+    pub minted_at: i64,
+    // Slot the receipt was last seen at
+    pub slot: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct HoldingsQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+// Read straight from the chain so partners do not have to trust the index.
+// Each receipt can be checked against the program at `slot`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OwnershipProof {
+    pub wallet: String,
+    pub badge: String,
+    pub owns: bool,
+    pub receipts: Vec<String>,
+    pub slot: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BadgeAccess {
+    pub badge: String,
+    pub wallet: String,
+    pub access: bool,
+}
@@ -0,0 +1,49 @@
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::FromRow;
+use uuid::Uuid;
+
+// A row of `trending_snapshots` with the badge's indexed name and image
+#[derive(FromRow, Debug, Serialize, Deserialize)]
+pub struct TrendingBadge {
+    pub rank: i64,
+    pub badge_address: String,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub image: Option<String>,
+    // Mints in the window and in the window before it
+    pub mints: i64,
+    pub previous_mints: i64,
+    // Distinct wallets that minted in the window
+    pub unique_minters: i64,
+    // Mints per hour over the window
+    pub velocity: f64,
+    pub score: f64,
+    pub computed_at: DateTime<Utc>,
+}
+
+// Row in the `collector_snapshots` table
+#[derive(FromRow, Debug, Serialize, Deserialize)]
+pub struct CollectorScore {
+    pub rank: i64,
+    pub holder: String,
+    pub user_id: Option<Uuid>,
+    pub username: Option<String>,
+    pub badges_held: i64,
+    pub unique_badges: i64,
+    pub score: f64,
+    pub computed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrendingQuery {
+    // `1h`, `24h`, `7d` or `30d`
+    pub window: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LeaderboardQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
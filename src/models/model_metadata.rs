@@ -0,0 +1,38 @@
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::FromRow;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct MetadataAttribute {
+    pub trait_type: String,
+    // A string or a number
+    pub value: serde_json::Value,
+}
+
+// Metaplex-style token metadata, the subset Pebble serves with badges
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct BadgeMetadata {
+    pub name: String,
+    #[serde(default)]
+    pub symbol: String,
+    #[serde(default)]
+    pub description: String,
+    pub image: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_url: Option<String>,
+    #[serde(default)]
+    pub attributes: Vec<MetadataAttribute>,
+}
+
+// Row in the `badge_metadata` table. `content` keeps the last valid document
+// when a later refresh fails.
+#[derive(FromRow, Debug, Clone)]
+pub struct MetadataRecord {
+    pub uri: String,
+    pub etag: Option<String>,
+    pub content: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+}
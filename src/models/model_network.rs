@@ -0,0 +1,27 @@
+use serde::{ Deserialize, Serialize };
+
+// Micro-lamport per compute unit prices paid over the last ~150 slots
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PriorityFeeEstimate {
+    pub samples: usize,
+    pub min: u64,
+    pub low: u64,
+    pub medium: u64,
+    pub high: u64,
+    pub very_high: u64,
+    pub max: u64,
+    // The configured percentile, clamped to the configured bounds
+    pub recommended: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NetworkFees {
+    pub accounts: Vec<String>,
+    pub priority_fees: PriorityFeeEstimate,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct NetworkFeesQuery {
+    // Comma separated accounts to price writes against, defaults to the program config
+    pub accounts: Option<String>,
+}
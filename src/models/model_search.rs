@@ -0,0 +1,63 @@
+use crate::models::model_user::User;
+use serde::{ Deserialize, Serialize };
+use sqlx::FromRow;
+use uuid::Uuid;
+
+// A badge matching a search, with the facet values it falls under
+#[derive(FromRow, Debug, Serialize, Deserialize)]
+pub struct BadgeSearchResult {
+    pub badge_address: String,
+    pub owner: String,
+    pub creator_id: Option<Uuid>,
+    pub creator_username: Option<String>,
+    pub name: String,
+    pub symbol: String,
+    pub description: String,
+    pub image: String,
+    pub price: i64,
+    pub decimals: i16,
+    pub supply: i64,
+    pub max_supply: i64,
+    pub paused: bool,
+    pub price_bucket: String,
+    pub availability: String,
+    pub rank: f32,
+    // Matches across all pages, read from the first row
+    #[serde(skip)]
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    // `free`, `under_1`, `1_to_10` or `over_10`, in display units of the badge currency
+    pub price: Option<String>,
+    // `available`, `sold_out` or `paused`
+    pub availability: Option<String>,
+    // Creator username, or owner wallet for badges without an account
+    pub creator: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
+// Counts over every text match, before facet filters are applied
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct SearchFacets {
+    pub price: Vec<FacetCount>,
+    pub availability: Vec<FacetCount>,
+    pub creator: Vec<FacetCount>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub total: i64,
+    pub badges: Vec<BadgeSearchResult>,
+    pub users: Vec<User>,
+    pub facets: SearchFacets,
+}
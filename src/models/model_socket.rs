@@ -0,0 +1,186 @@
+use crate::models::model_user::User;
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use uuid::Uuid;
+
+// Socket.IO handshake `auth` payload, for clients that cannot send cookies or headers
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SocketAuth {
+    pub token: Option<String>,
+}
+
+// Session a socket was authenticated with, kept in the socket's extensions. Sockets opened with
+// the refresh token cookie are tied to it, others only live until their access token expires.
+#[derive(Debug, Clone)]
+pub struct SocketSession {
+    pub user: User,
+    pub refresh_token: Option<String>,
+    pub expires_at: i64,
+}
+
+// `room:join` and `room:leave` payload, e.g. `{ "room": "badge:<address>" }`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoomPayload {
+    pub room: String,
+}
+
+// Acknowledgement of `room:join` and `room:leave`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoomResponse {
+    pub ok: bool,
+    pub room: Option<String>,
+    pub error: Option<String>,
+}
+
+// `badge:minted`, sent to `badge:<address>`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BadgeMintedMessage {
+    pub badge: String,
+    pub receipt: String,
+    pub holder: String,
+    pub number: u64,
+    pub price_paid: u64,
+    pub minted_at: i64,
+    pub signature: String,
+    pub slot: u64,
+}
+
+// `badge:updated`, sent to `badge:<address>` when a badge is created or repriced
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BadgeUpdatedMessage {
+    pub badge: String,
+    pub owner: String,
+    // "created" or "price_changed"
+    pub change: String,
+    pub price: u64,
+    pub old_price: Option<u64>,
+    pub max_supply: Option<u64>,
+    pub signature: String,
+    pub slot: u64,
+}
+
+// `badge:sold_out`, sent to `badge:<address>` after the last number in supply is minted
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BadgeSoldOutMessage {
+    pub badge: String,
+    pub supply: u64,
+    pub signature: String,
+    pub slot: u64,
+}
+
+// `notification:new`, sent to `user:<id>`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationMessage {
+    pub id: Uuid,
+    // e.g. "transaction_status", "badge_minted", "badge_sold" or "badge_sold_out"
+    pub kind: String,
+    pub message: String,
+    pub data: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+// Event broadcast to a Socket.IO room
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "event", content = "payload")]
+pub enum SocketEvent {
+    #[serde(rename = "badge:minted")]
+    BadgeMinted(BadgeMintedMessage),
+    #[serde(rename = "badge:updated")]
+    BadgeUpdated(BadgeUpdatedMessage),
+    #[serde(rename = "badge:sold_out")]
+    BadgeSoldOut(BadgeSoldOutMessage),
+    #[serde(rename = "notification:new")]
+    Notification(NotificationMessage),
+}
+
+impl SocketEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SocketEvent::BadgeMinted(_) => "badge:minted",
+            SocketEvent::BadgeUpdated(_) => "badge:updated",
+            SocketEvent::BadgeSoldOut(_) => "badge:sold_out",
+            SocketEvent::Notification(_) => "notification:new",
+        }
+    }
+
+    pub fn payload(&self) -> serde_json::Value {
+        let payload = match self {
+            SocketEvent::BadgeMinted(message) => serde_json::to_value(message),
+            SocketEvent::BadgeUpdated(message) => serde_json::to_value(message),
+            SocketEvent::BadgeSoldOut(message) => serde_json::to_value(message),
+            SocketEvent::Notification(message) => serde_json::to_value(message),
+        };
+
+        payload.unwrap_or_default()
+    }
+}
+
+// NOTIFY payload replicating a room broadcast to the other instances. Events too large for
+// NOTIFY are stored in `socket_events` and sent by id.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    pub origin: Uuid,
+    pub room: String,
+    pub event: Option<SocketEvent>,
+    pub stored_id: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sold_out() -> SocketEvent {
+        SocketEvent::BadgeSoldOut(BadgeSoldOutMessage {
+            badge: "badge".to_string(),
+            supply: 100,
+            signature: "signature".to_string(),
+            slot: 42,
+        })
+    }
+
+    #[test]
+    fn encodes_the_event_name_next_to_its_payload() {
+        let encoded = serde_json::to_value(sold_out()).unwrap();
+
+        assert_eq!(
+            encoded,
+            json!({
+                "event": "badge:sold_out",
+                "payload": { "badge": "badge", "supply": 100, "signature": "signature", "slot": 42 },
+            })
+        );
+        assert_eq!(encoded["event"], sold_out().name());
+        assert_eq!(encoded["payload"], sold_out().payload());
+    }
+
+    #[test]
+    fn decodes_events_by_name() {
+        let decoded: SocketEvent = serde_json::from_value(
+            json!({
+                "event": "notification:new",
+                "payload": {
+                    "id": Uuid::nil(),
+                    "kind": "badge_sold",
+                    "message": "Your badge sold.",
+                    "data": {},
+                    "created_at": "2024-01-01T00:00:00Z",
+                },
+            })
+        ).unwrap();
+
+        assert!(
+            matches!(decoded, SocketEvent::Notification(ref message) if message.kind == "badge_sold")
+        );
+        assert_eq!(decoded.name(), "notification:new");
+    }
+
+    #[test]
+    fn rejects_unknown_events() {
+        let decoded = serde_json::from_value::<SocketEvent>(
+            json!({ "event": "badge:burned", "payload": {} })
+        );
+
+        assert!(decoded.is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::FromRow;
+use uuid::Uuid;
+
+// An unsigned, base64 encoded transaction handed back to a wallet for signing
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BuiltTransaction {
+    pub transaction: String,
+    pub recent_blockhash: String,
+    pub last_valid_block_height: u64,
+    #[serde(flatten)]
+    pub compute_budget: ComputeBudget,
+}
+
+// Compute unit limit and micro-lamport unit price injected into a built transaction
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ComputeBudget {
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateBadgeTransaction {
+    pub badge_address: String,
+    #[serde(flatten)]
+    pub built: BuiltTransaction,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct MintBadgePayload {
+    // Ask the server fee payer to cover the network fee
+    pub sponsored: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MintQuote {
+    pub sponsored: bool,
+    pub price_lamports: u64,
+    pub price_sol: f64,
+    pub network_fee_lamports: u64,
+    pub network_fee_sol: f64,
+    // Price plus the fee the buyer pays, only when the badge is priced in SOL (9 decimals)
+    pub total_sol: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MintBadgeTransaction {
+    pub badge_address: String,
+    pub receipt_address: String,
+    pub number: u64,
+    pub quote: MintQuote,
+    // Known up front only for sponsored mints, whose fee payer signs first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(flatten)]
+    pub built: BuiltTransaction,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitTransactionPayload {
+    pub transaction: String,
+}
+
+// Row in the `transactions` table. Status moves from `submitted` through `processed` and
+// `confirmed` to `finalized`, or ends as `failed` or `expired`.
+#[derive(FromRow, Debug, Serialize, Deserialize, Clone)]
+pub struct TransactionRecord {
+    pub signature: String,
+    pub user_id: Option<Uuid>,
+    pub recent_blockhash: String,
+    pub status: String,
+    pub error: Option<String>,
+    pub slot: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,20 @@
+use crate::models::model_metadata::BadgeMetadata;
+use serde::{ Deserialize, Serialize };
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageUpload {
+    pub image: String,
+    pub thumbnail: String,
+    pub content_type: String,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: usize,
+}
+
+// `uri` is what goes into the create-badge transaction
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetadataUpload {
+    pub uri: String,
+    pub image: ImageUpload,
+    pub metadata: BadgeMetadata,
+}
@@ -0,0 +1,68 @@
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::FromRow;
+use uuid::Uuid;
+
+// Row in the `webhook_subscriptions` table. The secret is only returned when it is created.
+#[derive(FromRow, Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookSubscription {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub url: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub event_types: Vec<String>,
+    pub badge_addresses: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub subscription: WebhookSubscription,
+    // Key for the HMAC-SHA256 `Pebble-Signature` header
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateWebhookPayload {
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub badges: Option<Vec<String>>,
+}
+
+// Row in the `webhook_deliveries` table
+#[derive(FromRow, Debug, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub subscription_id: Uuid,
+    pub outbox_id: i64,
+    pub event_type: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Row in the `webhook_attempts` table
+#[derive(FromRow, Debug, Serialize, Deserialize)]
+pub struct WebhookAttempt {
+    pub id: i64,
+    pub delivery_id: Uuid,
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeliveriesQuery {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
@@ -1,3 +1,16 @@
 pub mod route_user;
 pub mod route_auth;
 pub mod route_badge;
+pub mod route_metrics;
+pub mod route_account;
+pub mod route_transaction;
+pub mod route_network;
+pub mod route_holding;
+pub mod route_activity;
+pub mod route_upload;
+pub mod route_collection;
+pub mod route_claim;
+pub mod route_search;
+pub mod route_leaderboard;
+pub mod route_analytics;
+pub mod route_webhook;
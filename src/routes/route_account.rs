@@ -0,0 +1,12 @@
+use crate::database::db::AppState;
+use crate::controllers::controller_account::{ get_all_accounts, get_account_by_address };
+
+use std::sync::Arc;
+use axum::routing::{ get, Router };
+
+pub fn account_route(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/api/accounts", get(get_all_accounts))
+        .route("/api/accounts/:address", get(get_account_by_address))
+        .with_state(app_state)
+}
@@ -0,0 +1,12 @@
+use crate::database::db::AppState;
+use crate::controllers::controller_activity::{ get_badge_activity, get_wallet_activity };
+
+use std::sync::Arc;
+use axum::routing::{ get, Router };
+
+pub fn activity_route(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/api/badges/:address/activity", get(get_badge_activity))
+        .route("/api/wallets/:address/activity", get(get_wallet_activity))
+        .with_state(app_state)
+}
@@ -0,0 +1,17 @@
+use crate::database::db::AppState;
+use crate::controllers::controller_analytics::get_creator_analytics;
+use crate::services::service_auth::auth;
+
+use std::sync::Arc;
+use axum::{ routing::{ get, Router }, middleware };
+
+pub fn analytics_route(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(
+            "/api/creators/me/analytics",
+            get(get_creator_analytics).route_layer(
+                middleware::from_fn_with_state(app_state.clone(), auth)
+            )
+        )
+        .with_state(app_state)
+}
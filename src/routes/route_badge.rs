@@ -1,5 +1,10 @@
 use crate::database::db::AppState;
-use crate::controllers::controller_badge::{ get_all_badges };
+use crate::controllers::controller_badge::{
+    get_all_badges,
+    get_badge,
+    create_badge_tx,
+    mint_badge_tx,
+};
 use crate::services::service_auth::auth;
 
 use std::sync::Arc;
@@ -8,6 +13,19 @@ use axum::{ routing::{ get, post, put, Router }, middleware };
 pub fn badge_route(app_state: Arc<AppState>) -> Router {
     Router::new()
         .route("/api/badges", get(get_all_badges))
+        .route("/api/badges/:address", get(get_badge))
+        .route(
+            "/api/badges/create-tx",
+            post(create_badge_tx).route_layer(
+                middleware::from_fn_with_state(app_state.clone(), auth)
+            )
+        )
+        .route(
+            "/api/badges/:address/mint-tx",
+            post(mint_badge_tx).route_layer(
+                middleware::from_fn_with_state(app_state.clone(), auth)
+            )
+        )
         // .route(
         //     "/api/badge/:id",
         //     get(get_user_by_id).route_layer(middleware::from_fn_with_state(app_state.clone(), auth))
@@ -0,0 +1,22 @@
+use crate::database::db::AppState;
+use crate::controllers::controller_claim::{
+    create_campaign,
+    create_campaign_codes,
+    get_badge_campaigns,
+    redeem_campaign,
+    update_campaign_allowlist,
+};
+use crate::services::service_auth::auth;
+
+use std::sync::Arc;
+use axum::{ routing::{ get, post, Router }, middleware };
+
+pub fn claim_route(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/api/badges/:address/campaigns", get(get_badge_campaigns).post(create_campaign))
+        .route("/api/campaigns/:id/codes", post(create_campaign_codes))
+        .route("/api/campaigns/:id/allowlist", post(update_campaign_allowlist))
+        .route("/api/campaigns/:id/redeem", post(redeem_campaign))
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), auth))
+        .with_state(app_state)
+}
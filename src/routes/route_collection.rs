@@ -0,0 +1,32 @@
+use crate::database::db::AppState;
+use crate::controllers::controller_collection::{
+    create_collection,
+    delete_collection,
+    get_collection,
+    get_storefront,
+    update_collection,
+};
+use crate::services::service_auth::auth;
+
+use std::sync::Arc;
+use axum::{ routing::{ delete, get, post, put, Router }, middleware };
+
+pub fn collection_route(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(
+            "/api/collections",
+            post(create_collection).route_layer(
+                middleware::from_fn_with_state(app_state.clone(), auth)
+            )
+        )
+        .route(
+            "/api/collections/:id",
+            get(get_collection).merge(
+                put(update_collection)
+                    .delete(delete_collection)
+                    .route_layer(middleware::from_fn_with_state(app_state.clone(), auth))
+            )
+        )
+        .route("/api/creators/:id/storefront", get(get_storefront))
+        .with_state(app_state)
+}
@@ -0,0 +1,31 @@
+use crate::database::db::AppState;
+use crate::controllers::controller_holding::{
+    get_badge_access,
+    get_badge_holders,
+    get_user_badges,
+    get_wallet_owns_badge,
+};
+use crate::services::service_auth::auth;
+use crate::services::service_gate::{ badge_gate, BadgeGate };
+
+use std::sync::Arc;
+use axum::{ routing::{ get, Router }, middleware };
+
+pub fn holding_route(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/api/badges/:address/holders", get(get_badge_holders))
+        .route(
+            "/api/badges/:address/access",
+            get(get_badge_access)
+                .route_layer(
+                    middleware::from_fn_with_state(
+                        (app_state.clone(), Arc::new(BadgeGate::from_path("address"))),
+                        badge_gate
+                    )
+                )
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth))
+        )
+        .route("/api/users/:id/badges", get(get_user_badges))
+        .route("/api/wallets/:address/owns/:badge", get(get_wallet_owns_badge))
+        .with_state(app_state)
+}
@@ -0,0 +1,12 @@
+use crate::database::db::AppState;
+use crate::controllers::controller_leaderboard::{ get_top_collectors, get_trending_badges };
+
+use std::sync::Arc;
+use axum::routing::{ get, Router };
+
+pub fn leaderboard_route(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/api/badges/trending", get(get_trending_badges))
+        .route("/api/leaderboards/collectors", get(get_top_collectors))
+        .with_state(app_state)
+}
@@ -0,0 +1,9 @@
+use crate::database::db::AppState;
+use crate::controllers::controller_metrics::get_metrics;
+
+use std::sync::Arc;
+use axum::routing::{ get, Router };
+
+pub fn metrics_route(app_state: Arc<AppState>) -> Router {
+    Router::new().route("/api/metrics", get(get_metrics)).with_state(app_state)
+}
@@ -0,0 +1,9 @@
+use crate::database::db::AppState;
+use crate::controllers::controller_network::get_network_fees;
+
+use std::sync::Arc;
+use axum::routing::{ get, Router };
+
+pub fn network_route(app_state: Arc<AppState>) -> Router {
+    Router::new().route("/api/network/fees", get(get_network_fees)).with_state(app_state)
+}
@@ -0,0 +1,9 @@
+use crate::database::db::AppState;
+use crate::controllers::controller_search::get_search;
+
+use std::sync::Arc;
+use axum::routing::{ get, Router };
+
+pub fn search_route(app_state: Arc<AppState>) -> Router {
+    Router::new().route("/api/search", get(get_search)).with_state(app_state)
+}
@@ -0,0 +1,21 @@
+use crate::database::db::AppState;
+use crate::controllers::controller_transaction::{
+    submit_transaction,
+    get_transaction_by_signature,
+};
+use crate::services::service_auth::auth;
+
+use std::sync::Arc;
+use axum::{ routing::{ get, post, Router }, middleware };
+
+pub fn transaction_route(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(
+            "/api/transactions",
+            post(submit_transaction).route_layer(
+                middleware::from_fn_with_state(app_state.clone(), auth)
+            )
+        )
+        .route("/api/transactions/:signature", get(get_transaction_by_signature))
+        .with_state(app_state)
+}
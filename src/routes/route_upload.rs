@@ -0,0 +1,30 @@
+use crate::database::db::AppState;
+use crate::controllers::controller_upload::{ upload_image, upload_metadata };
+use crate::services::service_auth::auth;
+
+use std::sync::Arc;
+use axum::{ extract::DefaultBodyLimit, routing::{ post, Router }, middleware };
+
+// Room for the multipart framing and text fields on top of the image
+const FORM_OVERHEAD_BYTES: usize = 64 * 1024;
+
+pub fn upload_route(app_state: Arc<AppState>) -> Router {
+    let body_limit = DefaultBodyLimit::max(
+        app_state.upload_limits.max_image_bytes + FORM_OVERHEAD_BYTES
+    );
+
+    Router::new()
+        .route(
+            "/api/uploads/image",
+            post(upload_image)
+                .layer(body_limit)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth))
+        )
+        .route(
+            "/api/uploads/metadata",
+            post(upload_metadata)
+                .layer(body_limit)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth))
+        )
+        .with_state(app_state)
+}
@@ -0,0 +1,24 @@
+use crate::database::db::AppState;
+use crate::controllers::controller_webhook::{
+    create_webhook,
+    delete_webhook,
+    get_attempts,
+    get_deliveries,
+    get_webhooks,
+    replay_webhook_delivery,
+};
+use crate::services::service_auth::auth;
+
+use std::sync::Arc;
+use axum::{ routing::{ delete, get, post, Router }, middleware };
+
+pub fn webhook_route(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/api/webhooks", post(create_webhook).get(get_webhooks))
+        .route("/api/webhooks/:id", delete(delete_webhook))
+        .route("/api/webhooks/:id/deliveries", get(get_deliveries))
+        .route("/api/webhooks/:id/deliveries/:delivery_id/attempts", get(get_attempts))
+        .route("/api/webhooks/:id/deliveries/:delivery_id/replay", post(replay_webhook_delivery))
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), auth))
+        .with_state(app_state)
+}
@@ -1,2 +1,25 @@
 pub mod service_user;
 pub mod service_auth;
+pub mod service_metrics;
+pub mod service_idl;
+pub mod service_rpc;
+pub mod service_program;
+pub mod service_badge;
+pub mod service_transaction;
+pub mod service_sponsor;
+pub mod service_keystore;
+pub mod service_fees;
+pub mod service_holding;
+pub mod service_gate;
+pub mod service_activity;
+pub mod service_metadata;
+pub mod service_storage;
+pub mod service_upload;
+pub mod service_collection;
+pub mod service_claim;
+pub mod service_search;
+pub mod service_leaderboard;
+pub mod service_analytics;
+pub mod service_webhook;
+pub mod service_socket;
+pub mod service_events;
@@ -0,0 +1,369 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_activity::{ ActivityRecord, BadgeMintedEvent, ProgramEvent };
+use crate::services::service_events::publish_program_event;
+use crate::services::service_rpc::env_or;
+use crate::services::service_webhook::enqueue_program_event;
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use sqlx::{ Postgres, Pool, Transaction };
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+const CURSOR_NAME: &str = "activity";
+// getSignaturesForAddress returns at most 1000 signatures per call
+const SIGNATURE_PAGE: usize = 1000;
+const MAX_ACTIVITY_PAGE: i64 = 500;
+
+// Base64 "Program data:" payloads logged while `program_id` is the innermost running program.
+// Invocations are tracked as a stack so data logged by CPI callees is not attributed to it.
+pub fn parse_program_data(logs: &[String], program_id: &Pubkey) -> Vec<Vec<u8>> {
+    let program = program_id.to_string();
+    let mut stack: Vec<&str> = vec![];
+    let mut payloads = vec![];
+
+    for log in logs {
+        let Some(rest) = log.strip_prefix("Program ") else {
+            continue;
+        };
+
+        if let Some(data) = rest.strip_prefix("data: ") {
+            if stack.last() == Some(&program.as_str()) {
+                if let Ok(bytes) = STANDARD.decode(data.trim()) {
+                    payloads.push(bytes);
+                }
+            }
+            continue;
+        }
+
+        let mut parts = rest.split_whitespace();
+        match (parts.next(), parts.next()) {
+            (Some(id), Some("invoke")) => stack.push(id),
+            (Some(_), Some("success")) | (Some(_), Some("failed:")) => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    payloads
+}
+
+pub enum ActivityFeed {
+    Badge,
+    Wallet,
+}
+
+// Gets the activity feed of a badge or a wallet, newest first
+pub async fn fetch_activity(
+    feed: ActivityFeed,
+    address: &str,
+    kind: Option<&str>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    db: &Pool<Postgres>
+) -> Result<Vec<ActivityRecord>, Error> {
+    let column = match feed {
+        ActivityFeed::Badge => "badge_address",
+        ActivityFeed::Wallet => "wallet_address",
+    };
+
+    sqlx
+        ::query_as::<_, ActivityRecord>(
+            &format!(
+                "SELECT * FROM activity WHERE {} = $1 AND ($2::VARCHAR IS NULL OR kind = $2) \
+                 ORDER BY slot DESC, event_index DESC LIMIT $3 OFFSET $4",
+                column
+            )
+        )
+        .bind(address)
+        .bind(kind)
+        .bind(limit.unwrap_or(50).clamp(1, MAX_ACTIVITY_PAGE))
+        .bind(offset.unwrap_or(0).max(0))
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })
+}
+
+async fn fetch_cursor(db: &Pool<Postgres>) -> Result<Option<Signature>, Error> {
+    let cursor = sqlx
+        ::query_as::<_, (String,)>("SELECT signature FROM indexer_cursors WHERE name = $1")
+        .bind(CURSOR_NAME)
+        .fetch_optional(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    Ok(cursor.and_then(|(signature,)| Signature::from_str(&signature).ok()))
+}
+
+// Whether a mint took the last number in supply, going by the search index or the badge's
+// creation event
+async fn sells_out(
+    minted: &BadgeMintedEvent,
+    session: &mut Transaction<'_, Postgres>
+) -> Result<bool, Error> {
+    let (max_supply,) = sqlx
+        ::query_as::<_, (Option<i64>,)>(
+            "SELECT COALESCE((SELECT max_supply FROM badge_search WHERE badge_address = $1), \
+             (SELECT MAX((data->>'max_supply')::BIGINT) FROM activity \
+             WHERE kind = 'badge_created' AND badge_address = $1))"
+        )
+        .bind(minted.badge.to_string())
+        .fetch_one(&mut *session).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    Ok(max_supply.is_some_and(|max_supply| (minted.number as i64) >= max_supply))
+}
+
+// Stores the events of one transaction and advances the cursor past it
+async fn save_transaction_events(
+    app_state: &AppState,
+    signature: &str,
+    slot: u64,
+    block_time: Option<i64>,
+    events: Vec<ProgramEvent>
+) -> Result<(), Error> {
+    let mut session = app_state.db.begin().await.map_err(|_| Error::InternalServerError)?;
+    let mut stored = vec![];
+
+    for (index, event) in events.iter().enumerate() {
+        let inserted = sqlx
+            ::query(
+                "INSERT INTO activity (signature, event_index, slot, block_time, kind, badge_address, wallet_address, data) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT (signature, event_index) DO NOTHING"
+            )
+            .bind(signature)
+            .bind(index as i32)
+            .bind(slot as i64)
+            .bind(block_time)
+            .bind(event.kind())
+            .bind(event.badge().to_string())
+            .bind(event.wallet().to_string())
+            .bind(event.data())
+            .execute(&mut session).await
+            .map_err(|err| {
+                println!("Database insert failed: {}", err);
+                Error::InternalServerError
+            })?;
+
+        // Webhooks are queued and sockets told once, when the event is first stored
+        if inserted.rows_affected() > 0 {
+            let sold_out = match event {
+                ProgramEvent::BadgeMinted(minted) => sells_out(minted, &mut session).await?,
+                _ => false,
+            };
+            enqueue_program_event(event, sold_out, signature, slot, block_time, &mut session).await?;
+            stored.push((event, sold_out));
+        }
+    }
+
+    sqlx
+        ::query(
+            "INSERT INTO indexer_cursors (name, signature, slot) VALUES ($1, $2, $3) \
+             ON CONFLICT (name) DO UPDATE SET signature = EXCLUDED.signature, slot = EXCLUDED.slot, updated_at = NOW()"
+        )
+        .bind(CURSOR_NAME)
+        .bind(signature)
+        .bind(slot as i64)
+        .execute(&mut session).await
+        .map_err(|err| {
+            println!("Database update failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    session.commit().await.map_err(|_| Error::InternalServerError)?;
+
+    for (event, sold_out) in stored {
+        if let Err(err) = publish_program_event(app_state, event, signature, slot, sold_out).await {
+            println!("Failed to publish {} event: {:?}", event.kind(), err);
+        }
+    }
+
+    Ok(())
+}
+
+// Walks program signatures back to the cursor, then parses them oldest first.
+// Without a cursor only the latest ACTIVITY_BACKFILL_LIMIT signatures are indexed.
+async fn index_activity(app_state: &AppState) -> Result<(), Error> {
+    let cursor = fetch_cursor(&app_state.db).await?;
+    let backfill_limit: usize = env_or("ACTIVITY_BACKFILL_LIMIT", 10_000);
+    let mut signatures = vec![];
+    let mut before = None;
+
+    loop {
+        let page = app_state.rpc.get_signatures_for_address(
+            &app_state.program_id,
+            before,
+            cursor,
+            SIGNATURE_PAGE
+        ).await?;
+        let page_len = page.len();
+
+        before = page.last().and_then(|status| Signature::from_str(&status.signature).ok());
+        signatures.extend(page);
+
+        if page_len < SIGNATURE_PAGE || before.is_none() {
+            break;
+        }
+
+        if cursor.is_none() && signatures.len() >= backfill_limit {
+            println!("Activity backfill stopped at {} signatures.", signatures.len());
+            break;
+        }
+    }
+
+    for status in signatures.iter().rev() {
+        let mut events = vec![];
+
+        // Failed transactions roll back their events, but still move the cursor
+        if status.err.is_none() {
+            let signature = Signature::from_str(&status.signature).map_err(
+                |_| Error::InternalServerError
+            )?;
+            let transaction = app_state.rpc.get_transaction(&signature).await?;
+            let logs: Vec<String> = transaction.transaction.meta
+                .and_then(|meta| Option::<Vec<String>>::from(meta.log_messages))
+                .unwrap_or_default();
+
+            for payload in parse_program_data(&logs, &app_state.program_id) {
+                match ProgramEvent::decode(&payload) {
+                    Ok(Some(event)) => events.push(event),
+                    Ok(None) => {}
+                    Err(err) => {
+                        println!("Failed to decode event in {}: {}", status.signature, err);
+                        app_state.metrics.incr("program_event_decode_failures");
+                    }
+                }
+            }
+        }
+
+        app_state.metrics.incr_by("activity_events_indexed", events.len() as u64);
+        save_transaction_events(
+            app_state,
+            &status.signature,
+            status.slot,
+            status.block_time,
+            events
+        ).await?;
+    }
+
+    Ok(())
+}
+
+// Background task indexing program events every ACTIVITY_INDEX_INTERVAL_SECS
+pub async fn track_activity(app_state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(
+        Duration::from_secs(env_or("ACTIVITY_INDEX_INTERVAL_SECS", 10))
+    );
+
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = index_activity(&app_state).await {
+            println!("Activity indexer failed: {:?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(bytes: &[u8]) -> String {
+        format!("Program data: {}", STANDARD.encode(bytes))
+    }
+
+    #[test]
+    fn top_level_program_data_is_decoded() {
+        let program = Pubkey::new_unique();
+
+        let payloads = parse_program_data(
+            &[
+                format!("Program {} invoke [1]", program),
+                "Program log: Instruction: MintBadge".to_string(),
+                data(&[1, 2, 3]),
+                data(&[4]),
+                format!("Program {} consumed 5000 of 200000 compute units", program),
+                format!("Program {} success", program),
+            ],
+            &program
+        );
+
+        assert_eq!(payloads, vec![vec![1, 2, 3], vec![4]]);
+    }
+
+    #[test]
+    fn data_logged_by_cpi_callees_is_skipped() {
+        let program = Pubkey::new_unique();
+        let callee = Pubkey::new_unique();
+
+        let payloads = parse_program_data(
+            &[
+                format!("Program {} invoke [1]", program),
+                format!("Program {} invoke [2]", callee),
+                data(&[9, 9]),
+                format!("Program {} success", callee),
+                data(&[1]),
+                format!("Program {} invoke [2]", callee),
+                data(&[8]),
+                format!("Program {} failed: custom program error: 0x1", callee),
+                data(&[2]),
+                format!("Program {} success", program),
+            ],
+            &program
+        );
+
+        assert_eq!(payloads, vec![vec![1], vec![2]]);
+    }
+
+    #[test]
+    fn data_is_attributed_when_the_program_is_invoked_by_cpi() {
+        let program = Pubkey::new_unique();
+        let caller = Pubkey::new_unique();
+
+        let payloads = parse_program_data(
+            &[
+                format!("Program {} invoke [1]", caller),
+                data(&[7]),
+                format!("Program {} invoke [2]", program),
+                data(&[1, 1]),
+                format!("Program {} success", program),
+                data(&[7, 7]),
+                format!("Program {} success", caller),
+            ],
+            &program
+        );
+
+        assert_eq!(payloads, vec![vec![1, 1]]);
+    }
+
+    #[test]
+    fn malformed_or_unattributed_data_is_ignored() {
+        let program = Pubkey::new_unique();
+
+        let payloads = parse_program_data(
+            &[
+                data(&[5]),
+                format!("Program {} invoke [1]", program),
+                "Program data: not base64!".to_string(),
+                "Program log: data: AQI=".to_string(),
+                format!("Program return: {} AQI=", program),
+                data(&[3]),
+                format!("Program {} success", program),
+                data(&[6]),
+            ],
+            &program
+        );
+
+        assert_eq!(payloads, vec![vec![3]]);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::errors::error::Error;
+use crate::models::model_analytics::{ AnalyticsBucket, BadgeAnalytics, CreatorAnalytics };
+use crate::services::service_badge::to_display_amount;
+use chrono::{ DateTime, Duration, Utc };
+use sqlx::{ Postgres, Pool };
+
+// Badges the wallet created (from its creation events or the search index), and every
+// indexed mint of them. Binds the creator wallet as $1.
+const CREATOR_MINTS: &str =
+    "WITH creator_badges AS (\
+     SELECT badge_address, MAX((data->>'max_supply')::BIGINT) AS created_max_supply FROM (\
+     SELECT badge_address, data FROM activity WHERE kind = 'badge_created' AND wallet_address = $1 \
+     UNION ALL SELECT badge_address, NULL FROM badge_search WHERE owner = $1) AS found \
+     GROUP BY badge_address), \
+     mints AS (SELECT a.badge_address, a.wallet_address AS buyer, \
+     (a.data->>'price_paid')::BIGINT AS price_paid, \
+     to_timestamp(COALESCE(a.block_time, (a.data->>'minted_at')::BIGINT)) AS minted_at \
+     FROM activity a JOIN creator_badges c ON c.badge_address = a.badge_address \
+     WHERE a.kind = 'badge_minted'), \
+     first_purchase AS (SELECT buyer, MIN(minted_at) AS first_at FROM mints GROUP BY buyer) ";
+
+// Revenue in SOL, for badges priced in SOL only
+fn revenue_sol(revenue_lamports: i64, decimals: i16) -> Option<f64> {
+    (decimals == 9).then(|| to_display_amount(revenue_lamports.max(0) as u64, 9))
+}
+
+fn sell_through(sold: i64, max_supply: Option<i64>) -> Option<f64> {
+    max_supply
+        .filter(|max_supply| *max_supply > 0)
+        .map(|max_supply| ((sold as f64) / (max_supply as f64)) * 100.0)
+}
+
+// Checks the bucket size and range, defaulting to the last 30 days by day or 48 hours by hour
+pub fn analytics_range(
+    bucket: Option<&str>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>
+) -> Result<(String, DateTime<Utc>, DateTime<Utc>), Error> {
+    let (bucket, default_span, max_span) = match bucket.unwrap_or("day") {
+        "day" => ("day", Duration::days(30), Duration::days(366)),
+        "hour" => ("hour", Duration::hours(48), Duration::days(31)),
+        _ => {
+            return Err(Error::AnalyticsError("Bucket must be \"day\" or \"hour\".".to_string()));
+        }
+    };
+
+    let to = to.unwrap_or_else(Utc::now);
+    let from = from.unwrap_or(to - default_span);
+
+    if from >= to {
+        return Err(Error::AnalyticsError("Range must end after it starts.".to_string()));
+    }
+
+    if to - from > max_span {
+        return Err(
+            Error::AnalyticsError(
+                format!("{} buckets cover at most {} days.", bucket, max_span.num_days())
+            )
+        );
+    }
+
+    Ok((bucket.to_string(), from, to))
+}
+
+// Mint and revenue statistics for a creator's badges, per badge and per time bucket
+pub async fn fetch_creator_analytics(
+    wallet: &str,
+    bucket: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    badge: Option<&str>,
+    db: &Pool<Postgres>
+) -> Result<CreatorAnalytics, Error> {
+    let mut badges = sqlx
+        ::query_as::<_, BadgeAnalytics>(
+            &format!(
+                "{}SELECT c.badge_address, s.name, COALESCE(s.decimals, 9)::SMALLINT AS decimals, \
+                 s.supply, COALESCE(s.max_supply, c.created_max_supply) AS max_supply, \
+                 COUNT(m.buyer) AS mints, COALESCE(SUM(m.price_paid), 0)::BIGINT AS revenue_lamports, \
+                 COUNT(DISTINCT m.buyer) AS unique_buyers, \
+                 COUNT(DISTINCT m.buyer) FILTER (WHERE f.first_at < $2) AS repeat_buyers, \
+                 (SELECT COUNT(*) FROM mints a WHERE a.badge_address = c.badge_address AND a.minted_at < $3) \
+                 AS total_mints \
+                 FROM creator_badges c \
+                 LEFT JOIN mints m ON m.badge_address = c.badge_address AND m.minted_at >= $2 AND m.minted_at < $3 \
+                 LEFT JOIN first_purchase f ON f.buyer = m.buyer \
+                 LEFT JOIN badge_search s ON s.badge_address = c.badge_address \
+                 WHERE ($4::VARCHAR IS NULL OR c.badge_address = $4) \
+                 GROUP BY c.badge_address, c.created_max_supply, s.name, s.decimals, s.supply, s.max_supply \
+                 ORDER BY revenue_lamports DESC, mints DESC, c.badge_address",
+                CREATOR_MINTS
+            )
+        )
+        .bind(wallet)
+        .bind(from)
+        .bind(to)
+        .bind(badge)
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    for badge in &mut badges {
+        badge.revenue_sol = revenue_sol(badge.revenue_lamports, badge.decimals);
+        badge.sell_through_percent = sell_through(
+            badge.supply.unwrap_or(badge.total_mints),
+            badge.max_supply
+        );
+    }
+
+    // Cumulative mints run over every bucket before filtering to the range
+    let mut series = sqlx
+        ::query_as::<_, AnalyticsBucket>(
+            &format!(
+                "{}SELECT * FROM (SELECT b.bucket_start, b.badge_address, s.name, \
+                 COALESCE(s.decimals, 9)::SMALLINT AS decimals, \
+                 COALESCE(s.max_supply, c.created_max_supply) AS max_supply, \
+                 b.mints, b.revenue_lamports, b.unique_buyers, b.repeat_buyers, \
+                 SUM(b.mints) OVER (PARTITION BY b.badge_address ORDER BY b.bucket_start)::BIGINT \
+                 AS cumulative_mints \
+                 FROM (SELECT date_trunc($5, m.minted_at) AS bucket_start, m.badge_address, \
+                 COUNT(*) AS mints, COALESCE(SUM(m.price_paid), 0)::BIGINT AS revenue_lamports, \
+                 COUNT(DISTINCT m.buyer) AS unique_buyers, \
+                 COUNT(DISTINCT m.buyer) FILTER (WHERE f.first_at < date_trunc($5, m.minted_at)) \
+                 AS repeat_buyers \
+                 FROM mints m JOIN first_purchase f ON f.buyer = m.buyer \
+                 WHERE m.minted_at < $3 GROUP BY 1, 2) AS b \
+                 JOIN creator_badges c ON c.badge_address = b.badge_address \
+                 LEFT JOIN badge_search s ON s.badge_address = b.badge_address) AS series \
+                 WHERE bucket_start >= date_trunc($5, $2) AND ($4::VARCHAR IS NULL OR badge_address = $4) \
+                 ORDER BY bucket_start, badge_address",
+                CREATOR_MINTS
+            )
+        )
+        .bind(wallet)
+        .bind(from)
+        .bind(to)
+        .bind(badge)
+        .bind(bucket)
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    for row in &mut series {
+        row.revenue_sol = revenue_sol(row.revenue_lamports, row.decimals);
+        row.sell_through_percent = sell_through(row.cumulative_mints, row.max_supply);
+    }
+
+    Ok(CreatorAnalytics {
+        bucket: bucket.to_string(),
+        from,
+        to,
+        badges,
+        series,
+    })
+}
+
+// Quotes a field if needed. Spreadsheets run fields starting with `=`, `+`, `-` or `@` as
+// formulas, so those are prefixed with a quote to be read as text.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+// The time series as CSV, one row per badge and bucket
+pub fn analytics_csv(analytics: &CreatorAnalytics) -> String {
+    let mut csv = String::from(
+        "bucket_start,badge_address,name,mints,revenue_lamports,revenue_sol,unique_buyers,\
+         repeat_buyers,cumulative_mints,sell_through_percent\n"
+    );
+
+    for row in &analytics.series {
+        csv.push_str(
+            &format!(
+                "{},{},{},{},{},{},{},{},{},{}\n",
+                row.bucket_start.to_rfc3339(),
+                row.badge_address,
+                csv_field(row.name.as_deref().unwrap_or("")),
+                row.mints,
+                row.revenue_lamports,
+                row.revenue_sol.map_or(String::new(), |revenue| revenue.to_string()),
+                row.unique_buyers,
+                row.repeat_buyers,
+                row.cumulative_mints,
+                row.sell_through_percent.map_or(String::new(), |percent| format!("{:.2}", percent))
+            )
+        );
+    }
+
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_to_the_last_30_days_by_day() {
+        let to = Utc::now();
+        let (bucket, from, range_to) = analytics_range(None, None, Some(to)).unwrap();
+
+        assert_eq!(bucket, "day");
+        assert_eq!(range_to, to);
+        assert_eq!(to - from, Duration::days(30));
+    }
+
+    #[test]
+    fn defaults_to_the_last_48_hours_by_hour() {
+        let to = Utc::now();
+        let (bucket, from, _) = analytics_range(Some("hour"), None, Some(to)).unwrap();
+
+        assert_eq!(bucket, "hour");
+        assert_eq!(to - from, Duration::hours(48));
+    }
+
+    #[test]
+    fn rejects_bad_ranges() {
+        let to = Utc::now();
+
+        assert!(analytics_range(Some("week"), None, None).is_err());
+        assert!(analytics_range(None, Some(to), Some(to)).is_err());
+        assert!(analytics_range(None, Some(to - Duration::days(367)), Some(to)).is_err());
+        assert!(analytics_range(Some("hour"), Some(to - Duration::days(32)), Some(to)).is_err());
+        assert!(analytics_range(Some("hour"), Some(to - Duration::days(31)), Some(to)).is_ok());
+    }
+
+    #[test]
+    fn computes_sell_through_against_capped_supply() {
+        assert_eq!(sell_through(25, Some(100)), Some(25.0));
+        assert_eq!(sell_through(3, Some(3)), Some(100.0));
+        assert_eq!(sell_through(5, Some(0)), None);
+        assert_eq!(sell_through(5, None), None);
+    }
+
+    #[test]
+    fn reports_revenue_in_sol_only_for_sol_badges() {
+        assert_eq!(revenue_sol(1_500_000_000, 9), Some(1.5));
+        assert_eq!(revenue_sol(1_500_000, 6), None);
+    }
+
+    #[test]
+    fn quotes_fields_with_separators() {
+        assert_eq!(csv_field("Gold Pass"), "Gold Pass");
+        assert_eq!(csv_field("Gold, Pass"), "\"Gold, Pass\"");
+        assert_eq!(csv_field("The \"Gold\" Pass"), "\"The \"\"Gold\"\" Pass\"");
+        assert_eq!(csv_field("Gold\nPass"), "\"Gold\nPass\"");
+    }
+
+    #[test]
+    fn neutralizes_formulas() {
+        assert_eq!(csv_field("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(csv_field("+1"), "'+1");
+        assert_eq!(csv_field("-1"), "'-1");
+        assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(csv_field("Gold = Pass"), "Gold = Pass");
+    }
+}
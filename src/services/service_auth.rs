@@ -1,7 +1,9 @@
 use crate::database::db::AppState;
 use crate::errors::error::Error;
+use crate::models::model_socket::SocketSession;
 use crate::services::service_user::fetch_user_by_id;
 use serde::{ Deserialize, Serialize };
+use sqlx::{ Postgres, Pool };
 use chrono::{ Utc, Duration };
 use std::sync::Arc;
 use uuid::Uuid;
@@ -171,6 +173,89 @@ pub fn parse_cookies_from_request(req: &Request<Body>) -> Result<JwtTokens, Erro
     })
 }
 
+fn cookie_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+// Checks a refresh token against the app's pool, for callers that already hold one
+async fn check_refresh_token(token: &str, db: &Pool<Postgres>) -> Result<Claims, Error> {
+    let token_data = verify_access_token(token)?;
+
+    let stored = sqlx
+        ::query_as::<_, (Uuid,)>("SELECT user_id FROM refresh_tokens WHERE token = $1")
+        .bind(token)
+        .fetch_optional(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    match stored {
+        Some((user_id,)) if user_id == token_data.sub => Ok(token_data),
+        _ => Err(Error::InvalidToken),
+    }
+}
+
+// The user, refresh token and expiry of a socket session. A refresh token of the same user
+// keeps the socket alive past the access token; one of another user is ignored.
+fn session_claims<'a>(
+    access: Option<Claims>,
+    refresh: Option<(&'a str, Claims)>
+) -> Option<(Uuid, Option<&'a str>, i64)> {
+    match (access, refresh) {
+        (Some(access), Some((token, refresh))) if refresh.sub == access.sub => {
+            Some((refresh.sub, Some(token), refresh.exp as i64))
+        }
+        (Some(access), _) => Some((access.sub, None, access.exp as i64)),
+        (None, Some((token, refresh))) => Some((refresh.sub, Some(token), refresh.exp as i64)),
+        (None, None) => None,
+    }
+}
+
+// Authenticate a Socket.IO handshake with the same tokens as `auth`: the access and refresh
+// token cookies, or an access token sent as a bearer header or in the handshake payload
+pub async fn authenticate_socket(
+    headers: &HeaderMap,
+    auth_token: Option<&str>,
+    db: &Pool<Postgres>
+) -> Result<SocketSession, Error> {
+    let access = cookie_value(headers, "access_token")
+        .or_else(|| bearer_token(headers))
+        .or(auth_token)
+        .and_then(|token| verify_access_token(token).ok());
+
+    let refresh = match cookie_value(headers, "refresh_token") {
+        Some(token) => check_refresh_token(token, db).await.ok().map(|claims| (token, claims)),
+        None => None,
+    };
+
+    let (user_id, refresh_token, expires_at) = session_claims(access, refresh).ok_or_else(||
+        Error::Unauthorized("No valid access or refresh token provided".to_string())
+    )?;
+
+    Ok(SocketSession {
+        user: fetch_user_by_id(user_id, db).await?,
+        refresh_token: refresh_token.map(str::to_string),
+        expires_at,
+    })
+}
+
 // Middleware to authenticate user on each protected route call
 pub async fn auth(
     State(app_state): State<Arc<AppState>>,
@@ -224,3 +309,86 @@ pub async fn auth(
 
     Err(Error::Unauthorized("Token expired".to_string()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(sub: Uuid, exp: usize) -> Claims {
+        Claims { sub, exp, iat: 0 }
+    }
+
+    fn headers(pairs: &[(header::HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(name.clone(), value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn finds_cookies_across_headers() {
+        let headers = headers(
+            &[
+                (header::COOKIE, "theme=dark; access_token=abc"),
+                (header::COOKIE, "refresh_token=def=="),
+            ]
+        );
+
+        assert_eq!(cookie_value(&headers, "access_token"), Some("abc"));
+        assert_eq!(cookie_value(&headers, "refresh_token"), Some("def=="));
+        assert_eq!(cookie_value(&headers, "token"), None);
+        assert_eq!(cookie_value(&HeaderMap::new(), "access_token"), None);
+    }
+
+    #[test]
+    fn matches_whole_cookie_names() {
+        let headers = headers(&[(header::COOKIE, "old_access_token=abc;access_token=xyz")]);
+
+        assert_eq!(cookie_value(&headers, "access_token"), Some("xyz"));
+    }
+
+    #[test]
+    fn reads_bearer_tokens_only() {
+        assert_eq!(
+            bearer_token(&headers(&[(header::AUTHORIZATION, "Bearer abc ")])),
+            Some("abc")
+        );
+        assert_eq!(bearer_token(&headers(&[(header::AUTHORIZATION, "Basic abc")])), None);
+        assert_eq!(bearer_token(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn refresh_token_of_the_same_user_extends_the_session() {
+        let user_id = Uuid::new_v4();
+        let session = session_claims(
+            Some(claims(user_id, 100)),
+            Some(("refresh", claims(user_id, 1000)))
+        );
+
+        assert_eq!(session, Some((user_id, Some("refresh"), 1000)));
+    }
+
+    #[test]
+    fn refresh_token_of_another_user_is_ignored() {
+        let user_id = Uuid::new_v4();
+        let session = session_claims(
+            Some(claims(user_id, 100)),
+            Some(("refresh", claims(Uuid::new_v4(), 1000)))
+        );
+
+        assert_eq!(session, Some((user_id, None, 100)));
+    }
+
+    #[test]
+    fn either_token_alone_opens_a_session() {
+        let user_id = Uuid::new_v4();
+
+        assert_eq!(session_claims(Some(claims(user_id, 100)), None), Some((user_id, None, 100)));
+        assert_eq!(
+            session_claims(None, Some(("refresh", claims(user_id, 1000)))),
+            Some((user_id, Some("refresh"), 1000))
+        );
+        assert_eq!(session_claims(None, None), None);
+    }
+}
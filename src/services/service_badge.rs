@@ -0,0 +1,239 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_account::{ account_discriminator, PebbleAccount };
+use crate::models::model_badge::{ Badge, CreateBadgePayload };
+use crate::models::model_transaction::{ BuiltTransaction, MintBadgeTransaction, MintQuote };
+use crate::models::model_user::User;
+use crate::services::service_program::{
+    build_transaction,
+    claim_badge_instructions,
+    mint_badge_instruction,
+    mint_receipt_pda,
+    serialize_transaction,
+    MintBadgeArgs,
+    SignedVoucher,
+};
+use solana_client::rpc_filter::{ Memcmp, RpcFilterType };
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+const MAX_NAME_LEN: usize = 32;
+const MAX_SYMBOL_LEN: usize = 10;
+const MAX_URI_LEN: usize = 200;
+
+// Validates the badge parameters before a create transaction is built
+pub fn validate_create_badge(payload: &CreateBadgePayload) -> Result<(), Error> {
+    let name = payload.name.trim();
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(
+            Error::CreateBadgeError(format!("Name must be 1 to {} bytes.", MAX_NAME_LEN))
+        );
+    }
+
+    if
+        payload.symbol.is_empty() ||
+        payload.symbol.len() > MAX_SYMBOL_LEN ||
+        !payload.symbol.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+    {
+        return Err(
+            Error::CreateBadgeError(
+                format!("Symbol must be 1 to {} uppercase letters or digits.", MAX_SYMBOL_LEN)
+            )
+        );
+    }
+
+    if payload.uri.len() > MAX_URI_LEN {
+        return Err(Error::CreateBadgeError(format!("URI must be at most {} bytes.", MAX_URI_LEN)));
+    }
+
+    if !["https://", "ipfs://", "ar://"].iter().any(|scheme| payload.uri.starts_with(scheme)) {
+        return Err(Error::CreateBadgeError("URI must use https, ipfs or ar.".to_string()));
+    }
+
+    if payload.max_supply == 0 || payload.max_supply > (i32::MAX as u64) {
+        return Err(Error::CreateBadgeError("Max supply is out of range.".to_string()));
+    }
+
+    if payload.price > (i64::MAX as u64) {
+        return Err(Error::CreateBadgeError("Price is out of range.".to_string()));
+    }
+
+    if payload.decimals.map_or(false, |decimals| decimals > 9) {
+        return Err(Error::CreateBadgeError("Decimals must be at most 9.".to_string()));
+    }
+
+    Ok(())
+}
+
+// Reads a badge account straight from chain
+pub async fn fetch_badge(app_state: &AppState, address: &Pubkey) -> Result<Badge, Error> {
+    let account = app_state.rpc
+        .get_account(address).await?
+        .ok_or_else(|| Error::GetAccountError("Badge not found.".to_string()))?;
+
+    if account.owner != app_state.program_id {
+        return Err(Error::GetAccountError("Badge not found.".to_string()));
+    }
+
+    match PebbleAccount::decode(&account.data) {
+        Ok(PebbleAccount::Badge(badge)) => Ok(badge.into_badge(address)),
+        Ok(other) => {
+            Err(Error::GetAccountError(format!("Account is a {}, not a badge.", other.type_name())))
+        }
+        Err(err) => {
+            println!("Failed to decode program account {}: {}", address, err);
+            app_state.metrics.incr("program_account_decode_failures");
+            Err(Error::GetAccountError("Badge could not be decoded.".to_string()))
+        }
+    }
+}
+
+// Badges of every layout version owned by `owner`, newest first. The owner follows the
+// discriminator in each layout.
+pub async fn fetch_badges_by_owner(app_state: &AppState, owner: &Pubkey) -> Result<Vec<Badge>, Error> {
+    let mut badges = vec![];
+
+    for account_type in ["BadgeAccount", "BadgeAccountV2"] {
+        let (_, accounts) = app_state.rpc.get_filtered_program_accounts(
+            &app_state.program_id,
+            vec![
+                RpcFilterType::Memcmp(
+                    Memcmp::new_base58_encoded(0, &account_discriminator(account_type))
+                ),
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(8, owner.as_ref()))
+            ]
+        ).await?;
+
+        for (address, account) in accounts {
+            match PebbleAccount::decode(&account.data) {
+                Ok(PebbleAccount::Badge(badge)) => badges.push(badge.into_badge(&address)),
+                Ok(_) => {}
+                Err(err) => {
+                    println!("Failed to decode program account {}: {}", address, err);
+                    app_state.metrics.incr("program_account_decode_failures");
+                }
+            }
+        }
+    }
+
+    badges.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(badges)
+}
+
+// Builds a transaction minting the next number of a badge to the user's wallet. With `sponsored`
+// the server fee payer signs and covers the network fee.
+pub async fn build_mint_transaction(
+    app_state: &AppState,
+    user: &User,
+    badge_address: &Pubkey,
+    sponsored: bool,
+    voucher: Option<&SignedVoucher>
+) -> Result<MintBadgeTransaction, Error> {
+    let buyer = Pubkey::from_str(&user.wallet_address).map_err(|_|
+        Error::MintBadgeError("Wallet address is invalid.".to_string())
+    )?;
+
+    let badge = fetch_badge(app_state, badge_address).await?;
+
+    if badge.paused {
+        return Err(Error::MintBadgeError("Minting is paused for this badge.".to_string()));
+    }
+
+    if badge.supply >= badge.max_supply {
+        return Err(Error::BadgeSoldOut);
+    }
+
+    let badge_owner = Pubkey::from_str(&badge.owner).map_err(|_| Error::InternalServerError)?;
+    let number = badge.supply + 1;
+    let (receipt_address, _) = mint_receipt_pda(&app_state.program_id, badge_address, number);
+
+    // A voucher mints at its own price, unless the badge has since become cheaper
+    let (program_instructions, price) = match voucher {
+        Some(voucher) =>
+            (
+                claim_badge_instructions(
+                    &app_state.program_id,
+                    badge_address,
+                    &badge_owner,
+                    &buyer,
+                    number,
+                    voucher
+                )?,
+                voucher.args.price.min(badge.price),
+            ),
+        None =>
+            (
+                vec![
+                    mint_badge_instruction(
+                        &app_state.program_id,
+                        badge_address,
+                        &badge_owner,
+                        &buyer,
+                        number,
+                        &(MintBadgeArgs { expected_price: badge.price })
+                    )?
+                ],
+                badge.price,
+            ),
+    };
+
+    let sponsor = if sponsored {
+        Some(
+            app_state.sponsor
+                .as_ref()
+                .ok_or_else(|| {
+                    Error::SponsorshipError("Fee sponsorship is not available.".to_string())
+                })?
+        )
+    } else {
+        None
+    };
+    let fee_payer = sponsor.map_or(buyer, |sponsor| sponsor.pubkey());
+
+    let (recent_blockhash, last_valid_block_height) = app_state.rpc.get_latest_blockhash().await?;
+    let (instructions, compute_budget) = app_state.fees.with_compute_budget(
+        &app_state.rpc,
+        &fee_payer,
+        program_instructions,
+        recent_blockhash
+    ).await?;
+    let mut transaction = build_transaction(&fee_payer, &instructions, recent_blockhash);
+    let network_fee = app_state.rpc.get_fee_for_message(&transaction.message).await?;
+
+    if let Some(sponsor) = sponsor {
+        sponsor.check_allowlist(&transaction, &app_state.program_id)?;
+        sponsor.sign(&mut transaction)?;
+        sponsor.reserve(user.id, badge_address, &transaction, network_fee, &app_state.db).await?;
+    }
+
+    let price_sol = to_display_amount(price, badge.decimals);
+    let network_fee_sol = to_display_amount(network_fee, 9);
+    let buyer_fee_sol = if sponsor.is_some() { 0.0 } else { network_fee_sol };
+    let total_sol = (badge.decimals == 9).then(|| price_sol + buyer_fee_sol);
+
+    Ok(MintBadgeTransaction {
+        badge_address: badge_address.to_string(),
+        receipt_address: receipt_address.to_string(),
+        number,
+        signature: sponsor.map(|_| transaction.signatures[0].to_string()),
+        quote: MintQuote {
+            sponsored: sponsor.is_some(),
+            price_lamports: price,
+            price_sol,
+            network_fee_lamports: network_fee,
+            network_fee_sol,
+            total_sol,
+        },
+        built: BuiltTransaction {
+            transaction: serialize_transaction(&transaction)?,
+            recent_blockhash: recent_blockhash.to_string(),
+            last_valid_block_height,
+            compute_budget,
+        },
+    })
+}
+
+// Converts a base-unit amount into display units, e.g. lamports into SOL for 9 decimals
+pub fn to_display_amount(amount: u64, decimals: u8) -> f64 {
+    (amount as f64) / (10f64).powi(decimals as i32)
+}
@@ -0,0 +1,837 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_claim::{
+    AllowlistEntry,
+    CampaignCodes,
+    ClaimCampaign,
+    ClaimRedemption,
+    ClaimVoucher,
+    CodeBatch,
+    CreateCampaignPayload,
+    RedeemClaimPayload,
+};
+use crate::models::model_user::User;
+use crate::services::service_badge::{ build_mint_transaction, fetch_badge };
+use crate::services::service_keystore::TransactionSigner;
+use crate::services::service_program::{ ClaimBadgeArgs, SignedVoucher };
+use crate::services::service_rpc::env_or;
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use borsh::BorshSerialize;
+use chacha20poly1305::aead::{ rand_core::RngCore, OsRng };
+use chrono::{ DateTime, Utc };
+use solana_sdk::hash::hash;
+use solana_sdk::pubkey::Pubkey;
+use sqlx::{ PgConnection, Postgres, Pool, Transaction };
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const MAX_NAME_LEN: usize = 64;
+const MAX_PER_WALLET_LIMIT: i32 = 100;
+const MAX_CODE_BATCH: u32 = 1000;
+const MAX_CODE_USES: i32 = 100_000;
+const MAX_ALLOWLIST_BATCH: usize = 10_000;
+const CODE_BYTES: usize = 10;
+// Prefixed to every voucher message so the signature cannot be replayed in another context
+const VOUCHER_DOMAIN: &[u8] = b"pebble:claim-voucher:v1";
+
+// Layout the program deserializes after VOUCHER_DOMAIN
+#[derive(BorshSerialize)]
+struct VoucherMessage {
+    program_id: Pubkey,
+    badge: Pubkey,
+    wallet: Pubkey,
+    price: u64,
+    nonce: [u8; 16],
+    expires_at: i64,
+}
+
+impl VoucherMessage {
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut message = VOUCHER_DOMAIN.to_vec();
+        self.serialize(&mut message).map_err(|_| Error::InternalServerError)?;
+        Ok(message)
+    }
+}
+
+// Key used to sign vouchers, resolved on every use so a rotation takes effect immediately.
+// It must be the program's config authority for the program to accept its vouchers.
+fn claim_signer(app_state: &AppState) -> Option<Arc<dyn TransactionSigner>> {
+    let name = std::env::var("CLAIM_SIGNER_KEY").unwrap_or("claim-signer".to_string());
+    app_state.keystore.signer(&name)
+}
+
+fn hash_code(code: &str) -> String {
+    hash(code.trim().as_bytes()).to_string()
+}
+
+fn generate_code() -> String {
+    let mut bytes = [0u8; CODE_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    solana_sdk::bs58::encode(bytes).into_string()
+}
+
+fn parse_wallet(address: &str) -> Result<Pubkey, Error> {
+    Pubkey::from_str(address.trim()).map_err(|_|
+        Error::ClaimError(format!("Wallet address {} is invalid.", address))
+    )
+}
+
+async fn fetch_campaign(id: Uuid, db: &Pool<Postgres>) -> Result<ClaimCampaign, Error> {
+    sqlx
+        ::query_as::<_, ClaimCampaign>("SELECT * FROM claim_campaigns WHERE id = $1")
+        .bind(id)
+        .fetch_optional(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?
+        .ok_or(Error::GetCampaignError("Campaign not found.".to_string()))
+}
+
+// Loads a campaign the user created, refusing anyone else
+async fn fetch_owned_campaign(
+    id: Uuid,
+    user: &User,
+    db: &Pool<Postgres>
+) -> Result<ClaimCampaign, Error> {
+    let campaign = fetch_campaign(id, db).await?;
+
+    if campaign.creator_id != user.id {
+        return Err(Error::Forbidden("Only the creator can manage this campaign.".to_string()));
+    }
+
+    Ok(campaign)
+}
+
+async fn insert_codes(
+    campaign_id: Uuid,
+    batch: &CodeBatch,
+    session: &mut Transaction<'_, Postgres>
+) -> Result<Vec<String>, Error> {
+    if batch.count == 0 || batch.count > MAX_CODE_BATCH {
+        return Err(
+            Error::ClaimError(
+                format!("Generate between 1 and {} codes at a time.", MAX_CODE_BATCH)
+            )
+        );
+    }
+
+    let max_uses = batch.max_uses.unwrap_or(1);
+    if !(1..=MAX_CODE_USES).contains(&max_uses) {
+        return Err(
+            Error::ClaimError(format!("Codes allow between 1 and {} uses.", MAX_CODE_USES))
+        );
+    }
+
+    let mut codes = vec![];
+
+    for _ in 0..batch.count {
+        let code = generate_code();
+
+        sqlx
+            ::query("INSERT INTO claim_codes (campaign_id, code_hash, max_uses) VALUES ($1, $2, $3)")
+            .bind(campaign_id)
+            .bind(hash_code(&code))
+            .bind(max_uses)
+            .execute(&mut *session).await
+            .map_err(|err| {
+                println!("Database insert failed: {}", err);
+                Error::InternalServerError
+            })?;
+
+        codes.push(code);
+    }
+
+    Ok(codes)
+}
+
+// Adds wallets to the allowlist, or updates the cap of wallets already on it
+async fn upsert_allowlist(
+    campaign: &ClaimCampaign,
+    entries: &[AllowlistEntry],
+    session: &mut Transaction<'_, Postgres>
+) -> Result<(), Error> {
+    if entries.len() > MAX_ALLOWLIST_BATCH {
+        return Err(
+            Error::ClaimError(
+                format!("Add at most {} wallets at a time.", MAX_ALLOWLIST_BATCH)
+            )
+        );
+    }
+
+    for entry in entries {
+        let wallet = parse_wallet(&entry.wallet_address)?;
+        let max_claims = entry.max_claims.unwrap_or(campaign.per_wallet_limit);
+
+        if !(1..=MAX_PER_WALLET_LIMIT).contains(&max_claims) {
+            return Err(
+                Error::ClaimError(
+                    format!("Wallets may claim between 1 and {} times.", MAX_PER_WALLET_LIMIT)
+                )
+            );
+        }
+
+        sqlx
+            ::query(
+                "INSERT INTO claim_allowlist (campaign_id, wallet_address, max_claims) VALUES ($1, $2, $3) \
+                 ON CONFLICT (campaign_id, wallet_address) DO UPDATE SET max_claims = EXCLUDED.max_claims"
+            )
+            .bind(campaign.id)
+            .bind(wallet.to_string())
+            .bind(max_claims)
+            .execute(&mut *session).await
+            .map_err(|err| {
+                println!("Database insert failed: {}", err);
+                Error::InternalServerError
+            })?;
+    }
+
+    Ok(())
+}
+
+fn validate_campaign(payload: &CreateCampaignPayload, badge_price: u64) -> Result<(), Error> {
+    if payload.name.trim().is_empty() || payload.name.chars().count() > MAX_NAME_LEN {
+        return Err(Error::ClaimError(format!("Name must be 1 to {} characters.", MAX_NAME_LEN)));
+    }
+
+    match payload.mode.as_str() {
+        "code" if payload.allowlist.is_some() => {
+            return Err(Error::ClaimError("Code campaigns do not take an allowlist.".to_string()));
+        }
+        "allowlist" if payload.codes.is_some() => {
+            return Err(Error::ClaimError("Allowlist campaigns do not take codes.".to_string()));
+        }
+        "code" | "allowlist" => {}
+        _ => {
+            return Err(Error::ClaimError("Mode must be \"code\" or \"allowlist\".".to_string()));
+        }
+    }
+
+    let per_wallet_limit = payload.per_wallet_limit.unwrap_or(1);
+    if !(1..=MAX_PER_WALLET_LIMIT).contains(&per_wallet_limit) {
+        return Err(
+            Error::ClaimError(
+                format!("Per-wallet limit must be between 1 and {}.", MAX_PER_WALLET_LIMIT)
+            )
+        );
+    }
+
+    if let (Some(starts_at), Some(ends_at)) = (payload.starts_at, payload.ends_at) {
+        if ends_at <= starts_at {
+            return Err(Error::ClaimError("Campaign must end after it starts.".to_string()));
+        }
+    }
+
+    if payload.price.is_some_and(|price| price >= badge_price || price > (i64::MAX as u64)) {
+        return Err(
+            Error::ClaimError(
+                "Claim price must be below the badge price, leave it out to mint at the badge price.".to_string()
+            )
+        );
+    }
+
+    Ok(())
+}
+
+// Creates a campaign for a badge the user owns on chain, with its first codes or allowlist
+pub async fn insert_campaign(
+    app_state: &AppState,
+    user: &User,
+    badge_address: &Pubkey,
+    payload: CreateCampaignPayload
+) -> Result<CampaignCodes, Error> {
+    let badge = fetch_badge(app_state, badge_address).await?;
+
+    if badge.owner != user.wallet_address {
+        return Err(Error::Forbidden("Only the badge owner can create campaigns.".to_string()));
+    }
+
+    validate_campaign(&payload, badge.price)?;
+
+    // Claims hand out fee-sponsored mints, discounted ones carrying a signed voucher
+    if app_state.sponsor.is_none() {
+        return Err(Error::ClaimError("Fee sponsorship is not available on this server.".to_string()));
+    }
+
+    if payload.price.is_some() && claim_signer(app_state).is_none() {
+        return Err(Error::ClaimError("Voucher signing is not available on this server.".to_string()));
+    }
+
+    let mut session = app_state.db.begin().await.map_err(|_| Error::InternalServerError)?;
+
+    let campaign = sqlx
+        ::query_as::<_, ClaimCampaign>(
+            "INSERT INTO claim_campaigns (badge_address, creator_id, name, mode, price, per_wallet_limit, starts_at, ends_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING *"
+        )
+        .bind(badge_address.to_string())
+        .bind(user.id)
+        .bind(payload.name.trim())
+        .bind(&payload.mode)
+        .bind(payload.price.map(|price| price as i64))
+        .bind(payload.per_wallet_limit.unwrap_or(1))
+        .bind(payload.starts_at)
+        .bind(payload.ends_at)
+        .fetch_one(&mut session).await
+        .map_err(|err| {
+            println!("Database insert failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    let codes = match &payload.codes {
+        Some(batch) => insert_codes(campaign.id, batch, &mut session).await?,
+        None => vec![],
+    };
+
+    if let Some(entries) = &payload.allowlist {
+        upsert_allowlist(&campaign, entries, &mut session).await?;
+    }
+
+    session.commit().await.map_err(|_| Error::InternalServerError)?;
+
+    Ok(CampaignCodes { campaign, codes })
+}
+
+// Campaigns the user created for a badge, newest first
+pub async fn fetch_badge_campaigns(
+    badge_address: &Pubkey,
+    user: &User,
+    db: &Pool<Postgres>
+) -> Result<Vec<ClaimCampaign>, Error> {
+    sqlx
+        ::query_as::<_, ClaimCampaign>(
+            "SELECT * FROM claim_campaigns WHERE badge_address = $1 AND creator_id = $2 \
+             ORDER BY created_at DESC"
+        )
+        .bind(badge_address.to_string())
+        .bind(user.id)
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })
+}
+
+pub async fn add_codes(
+    id: Uuid,
+    user: &User,
+    batch: CodeBatch,
+    db: &Pool<Postgres>
+) -> Result<CampaignCodes, Error> {
+    let campaign = fetch_owned_campaign(id, user, db).await?;
+
+    if campaign.mode != "code" {
+        return Err(Error::ClaimError("Allowlist campaigns do not take codes.".to_string()));
+    }
+
+    let mut session = db.begin().await.map_err(|_| Error::InternalServerError)?;
+    let codes = insert_codes(campaign.id, &batch, &mut session).await?;
+    session.commit().await.map_err(|_| Error::InternalServerError)?;
+
+    Ok(CampaignCodes { campaign, codes })
+}
+
+pub async fn add_allowlist(
+    id: Uuid,
+    user: &User,
+    entries: Vec<AllowlistEntry>,
+    db: &Pool<Postgres>
+) -> Result<(), Error> {
+    let campaign = fetch_owned_campaign(id, user, db).await?;
+
+    if campaign.mode != "allowlist" {
+        return Err(Error::ClaimError("Code campaigns do not take an allowlist.".to_string()));
+    }
+
+    let mut session = db.begin().await.map_err(|_| Error::InternalServerError)?;
+    upsert_allowlist(&campaign, &entries, &mut session).await?;
+    session.commit().await.map_err(|_| Error::InternalServerError)
+}
+
+// Refuses claims outside the campaign's window
+fn check_window(campaign: &ClaimCampaign, now: DateTime<Utc>) -> Result<(), Error> {
+    if campaign.starts_at.is_some_and(|starts_at| now < starts_at) {
+        return Err(Error::ClaimError("This campaign has not started yet.".to_string()));
+    }
+
+    if campaign.ends_at.is_some_and(|ends_at| now >= ends_at) {
+        return Err(Error::ClaimError("This campaign has ended.".to_string()));
+    }
+
+    Ok(())
+}
+
+// What a claim is checked against. Pending and landed redemptions count, released ones do not.
+#[derive(Debug, PartialEq)]
+struct ClaimCounts {
+    // The code's id, its redemptions and the redemptions it allows
+    code: Option<(Uuid, i64, i32)>,
+    // The wallet's redemptions in the campaign and how many it may make
+    wallet_claims: i64,
+    wallet_limit: i32,
+}
+
+// The code the claim uses, if any, once every cap has room left
+fn check_caps(counts: &ClaimCounts) -> Result<Option<Uuid>, Error> {
+    if let Some((_, uses, max_uses)) = counts.code {
+        if uses >= (max_uses as i64) {
+            return Err(Error::ClaimError("Claim code has already been used.".to_string()));
+        }
+    }
+
+    if counts.wallet_claims >= (counts.wallet_limit as i64) {
+        return Err(Error::ClaimError("You have already claimed this badge.".to_string()));
+    }
+
+    Ok(counts.code.map(|(code_id, _, _)| code_id))
+}
+
+// Reads the code and wallet counts a claim is checked against
+async fn fetch_counts(
+    campaign: &ClaimCampaign,
+    wallet: &Pubkey,
+    code: Option<&str>,
+    conn: &mut PgConnection
+) -> Result<ClaimCounts, Error> {
+    let code = if campaign.mode == "code" {
+        let code = code
+            .filter(|code| !code.trim().is_empty())
+            .ok_or_else(|| Error::ClaimError("A claim code is required.".to_string()))?;
+
+        let code = sqlx
+            ::query_as::<_, (Uuid, i64, i32)>(
+                "SELECT c.id, \
+                 (SELECT COUNT(*) FROM claim_redemptions r WHERE r.code_id = c.id AND r.status <> 'released'), \
+                 c.max_uses FROM claim_codes c WHERE c.campaign_id = $1 AND c.code_hash = $2"
+            )
+            .bind(campaign.id)
+            .bind(hash_code(code))
+            .fetch_optional(&mut *conn).await
+            .map_err(|err| {
+                println!("Database query failed: {}", err);
+                Error::InternalServerError
+            })?
+            .ok_or_else(|| Error::ClaimError("Claim code is invalid.".to_string()))?;
+
+        Some(code)
+    } else {
+        None
+    };
+
+    let wallet_limit = if campaign.mode == "allowlist" {
+        let (max_claims,) = sqlx
+            ::query_as::<_, (i32,)>(
+                "SELECT max_claims FROM claim_allowlist WHERE campaign_id = $1 AND wallet_address = $2"
+            )
+            .bind(campaign.id)
+            .bind(wallet.to_string())
+            .fetch_optional(&mut *conn).await
+            .map_err(|err| {
+                println!("Database query failed: {}", err);
+                Error::InternalServerError
+            })?
+            .ok_or_else(|| Error::Forbidden("Your wallet is not on the allowlist.".to_string()))?;
+
+        max_claims
+    } else {
+        campaign.per_wallet_limit
+    };
+
+    let (wallet_claims,) = sqlx
+        ::query_as::<_, (i64,)>(
+            "SELECT COUNT(*) FROM claim_redemptions \
+             WHERE campaign_id = $1 AND wallet_address = $2 AND status <> 'released'"
+        )
+        .bind(campaign.id)
+        .bind(wallet.to_string())
+        .fetch_one(&mut *conn).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    Ok(ClaimCounts { code, wallet_claims, wallet_limit })
+}
+
+// Records a pending redemption if the caps still have room. The advisory lock serializes
+// redemptions per campaign and is only held around the count and the insert.
+async fn record_redemption(
+    app_state: &AppState,
+    id: Uuid,
+    campaign: &ClaimCampaign,
+    user: &User,
+    wallet: &Pubkey,
+    code: Option<&str>,
+    signature: &str
+) -> Result<(), Error> {
+    let mut session = app_state.db.begin().await.map_err(|_| Error::InternalServerError)?;
+
+    sqlx
+        ::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind(format!("claim_campaign:{}", campaign.id))
+        .execute(&mut session).await
+        .map_err(|_| Error::InternalServerError)?;
+
+    let counts = fetch_counts(campaign, wallet, code, &mut session).await?;
+    let code_id = check_caps(&counts)?;
+
+    sqlx
+        ::query(
+            "INSERT INTO claim_redemptions (id, campaign_id, code_id, user_id, wallet_address, signature, status) \
+             VALUES ($1, $2, $3, $4, $5, $6, 'pending')"
+        )
+        .bind(id)
+        .bind(campaign.id)
+        .bind(code_id)
+        .bind(user.id)
+        .bind(wallet.to_string())
+        .bind(signature)
+        .execute(&mut session).await
+        .map_err(|err| {
+            println!("Database insert failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    session.commit().await.map_err(|_| Error::InternalServerError)
+}
+
+// Signs a voucher letting `wallet` mint the badge at `price` until it expires. The
+// redemption id is the nonce, so the program can refuse a voucher used twice.
+fn sign_voucher(
+    app_state: &AppState,
+    badge_address: &Pubkey,
+    wallet: &Pubkey,
+    price: u64,
+    nonce: Uuid
+) -> Result<(SignedVoucher, ClaimVoucher), Error> {
+    let signer = claim_signer(app_state).ok_or_else(|| {
+        Error::ServiceUnavailable("Voucher signing is not available.".to_string())
+    })?;
+
+    let ttl: i64 = env_or("CLAIM_VOUCHER_TTL_SECS", 600);
+    let expires_at = Utc::now().timestamp() + ttl;
+    let message = (VoucherMessage {
+        program_id: app_state.program_id,
+        badge: *badge_address,
+        wallet: *wallet,
+        price,
+        nonce: *nonce.as_bytes(),
+        expires_at,
+    }).to_bytes()?;
+    let signature = signer.sign_message(&message);
+
+    let voucher = ClaimVoucher {
+        badge_address: badge_address.to_string(),
+        wallet_address: wallet.to_string(),
+        price,
+        nonce: nonce.to_string(),
+        expires_at,
+        message: STANDARD.encode(&message),
+        signature: signature.to_string(),
+        signer: signer.pubkey().to_string(),
+    };
+    let signed = SignedVoucher {
+        signer: signer.pubkey(),
+        signature,
+        message,
+        args: ClaimBadgeArgs { price, nonce: *nonce.as_bytes(), expires_at },
+    };
+
+    Ok((signed, voucher))
+}
+
+// Validates a code or allowlist entry and hands back a fee-sponsored mint, carrying a signed
+// voucher for the campaign price when it has one, else at the badge price. Caps are checked before the mint is built, so ineligible claims cost no RPC calls or sponsor
+// budget, and again under the campaign lock once it is built. The redemption stays pending
+// until the mint lands, and is released with the sponsored fee if its blockhash expires unused.
+pub async fn redeem_claim(
+    app_state: &AppState,
+    user: &User,
+    id: Uuid,
+    payload: RedeemClaimPayload
+) -> Result<ClaimRedemption, Error> {
+    let campaign = fetch_campaign(id, &app_state.db).await?;
+    check_window(&campaign, Utc::now())?;
+
+    let badge_address = Pubkey::from_str(&campaign.badge_address).map_err(
+        |_| Error::InternalServerError
+    )?;
+    let wallet = Pubkey::from_str(&user.wallet_address).map_err(|_|
+        Error::ClaimError("Wallet address is invalid.".to_string())
+    )?;
+    let code = payload.code.as_deref();
+
+    {
+        let mut conn = app_state.db.acquire().await.map_err(|_| Error::InternalServerError)?;
+        check_caps(&fetch_counts(&campaign, &wallet, code, &mut conn).await?)?;
+    }
+
+    let redemption_id = Uuid::new_v4();
+    let voucher = match campaign.price {
+        Some(price) =>
+            Some(sign_voucher(app_state, &badge_address, &wallet, price as u64, redemption_id)?),
+        None => None,
+    };
+
+    let transaction = build_mint_transaction(
+        app_state,
+        user,
+        &badge_address,
+        true,
+        voucher.as_ref().map(|(signed, _)| signed)
+    ).await?;
+    let signature = transaction.signature.clone().ok_or(Error::InternalServerError)?;
+
+    let recorded = record_redemption(
+        app_state,
+        redemption_id,
+        &campaign,
+        user,
+        &wallet,
+        code,
+        &signature
+    ).await;
+
+    if let Err(err) = recorded {
+        // Lost a race for the last claim; the mint is never handed out
+        if let Some(sponsor) = &app_state.sponsor {
+            sponsor.release(&signature, &app_state.db).await?;
+        }
+        return Err(err);
+    }
+
+    app_state.metrics.incr("claims_redeemed");
+
+    Ok(ClaimRedemption {
+        id: redemption_id,
+        campaign_id: campaign.id,
+        transaction,
+        voucher: voucher.map(|(_, voucher)| voucher),
+    })
+}
+
+// Finalizes the redemptions of settled sponsored mints
+pub async fn settle_redemptions(
+    landed: &[String],
+    released: &[String],
+    db: &Pool<Postgres>
+) -> Result<(), Error> {
+    for (signatures, status) in [(landed, "landed"), (released, "released")] {
+        if signatures.is_empty() {
+            continue;
+        }
+
+        sqlx
+            ::query(
+                "UPDATE claim_redemptions SET status = $1 WHERE signature = ANY($2) AND status = 'pending'"
+            )
+            .bind(status)
+            .bind(signatures)
+            .execute(db).await
+            .map_err(|err| {
+                println!("Database update failed: {}", err);
+                Error::InternalServerError
+            })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn campaign(starts_at: Option<DateTime<Utc>>, ends_at: Option<DateTime<Utc>>) -> ClaimCampaign {
+        ClaimCampaign {
+            id: Uuid::new_v4(),
+            badge_address: Pubkey::new_unique().to_string(),
+            creator_id: Uuid::new_v4(),
+            name: "Launch".to_string(),
+            mode: "code".to_string(),
+            price: None,
+            per_wallet_limit: 1,
+            starts_at,
+            ends_at,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn payload(mode: &str) -> CreateCampaignPayload {
+        CreateCampaignPayload {
+            name: "Launch".to_string(),
+            mode: mode.to_string(),
+            price: None,
+            per_wallet_limit: None,
+            starts_at: None,
+            ends_at: None,
+            codes: None,
+            allowlist: None,
+        }
+    }
+
+    #[test]
+    fn hashes_codes_ignoring_surrounding_whitespace() {
+        assert_eq!(hash_code("  abc123\n"), hash_code("abc123"));
+        assert_eq!(hash_code("abc123"), hash_code("abc123"));
+        assert_ne!(hash_code("abc123"), hash_code("abc124"));
+        assert_ne!(hash_code("abc123"), "abc123");
+    }
+
+    #[test]
+    fn generated_codes_are_unique() {
+        assert_ne!(generate_code(), generate_code());
+    }
+
+    #[test]
+    fn open_campaigns_accept_claims() {
+        let now = Utc::now();
+
+        assert!(check_window(&campaign(None, None), now).is_ok());
+        assert!(check_window(&campaign(Some(now), Some(now + Duration::hours(1))), now).is_ok());
+    }
+
+    #[test]
+    fn refuses_claims_outside_the_window() {
+        let now = Utc::now();
+
+        assert!(
+            matches!(
+                check_window(&campaign(Some(now + Duration::seconds(1)), None), now),
+                Err(Error::ClaimError(message)) if message.contains("not started")
+            )
+        );
+        assert!(
+            matches!(
+                check_window(&campaign(None, Some(now)), now),
+                Err(Error::ClaimError(message)) if message.contains("ended")
+            )
+        );
+    }
+
+    #[test]
+    fn returns_the_code_while_caps_have_room() {
+        let code_id = Uuid::new_v4();
+        let counts = ClaimCounts { code: Some((code_id, 2, 3)), wallet_claims: 0, wallet_limit: 1 };
+
+        assert_eq!(check_caps(&counts).ok(), Some(Some(code_id)));
+
+        let counts = ClaimCounts { code: None, wallet_claims: 1, wallet_limit: 2 };
+
+        assert_eq!(check_caps(&counts).ok(), Some(None));
+    }
+
+    #[test]
+    fn refuses_used_up_codes() {
+        let counts = ClaimCounts {
+            code: Some((Uuid::new_v4(), 3, 3)),
+            wallet_claims: 0,
+            wallet_limit: 1,
+        };
+
+        assert!(
+            matches!(
+                check_caps(&counts),
+                Err(Error::ClaimError(message)) if message.contains("already been used")
+            )
+        );
+    }
+
+    #[test]
+    fn refuses_wallets_at_their_limit() {
+        let counts = ClaimCounts {
+            code: Some((Uuid::new_v4(), 0, 10)),
+            wallet_claims: 2,
+            wallet_limit: 2,
+        };
+
+        assert!(
+            matches!(
+                check_caps(&counts),
+                Err(Error::ClaimError(message)) if message.contains("already claimed")
+            )
+        );
+    }
+
+    #[test]
+    fn validates_campaign_settings() {
+        assert!(validate_campaign(&payload("code"), 100).is_ok());
+        assert!(validate_campaign(&payload("allowlist"), 100).is_ok());
+        assert!(validate_campaign(&payload("raffle"), 100).is_err());
+        assert!(
+            validate_campaign(
+                &(CreateCampaignPayload { name: "  ".to_string(), ..payload("code") }),
+                100
+            ).is_err()
+        );
+        assert!(
+            validate_campaign(
+                &(CreateCampaignPayload { per_wallet_limit: Some(0), ..payload("code") }),
+                100
+            ).is_err()
+        );
+        assert!(
+            validate_campaign(
+                &(CreateCampaignPayload {
+                    allowlist: Some(vec![]),
+                    ..payload("code")
+                }),
+                100
+            ).is_err()
+        );
+        assert!(
+            validate_campaign(
+                &(CreateCampaignPayload {
+                    codes: Some(CodeBatch { count: 1, max_uses: None }),
+                    ..payload("allowlist")
+                }),
+                100
+            ).is_err()
+        );
+
+        let now = Utc::now();
+        assert!(
+            validate_campaign(
+                &(CreateCampaignPayload {
+                    starts_at: Some(now),
+                    ends_at: Some(now),
+                    ..payload("code")
+                }),
+                100
+            ).is_err()
+        );
+    }
+
+    #[test]
+    fn claim_prices_must_undercut_the_badge() {
+        let priced = |price| CreateCampaignPayload { price: Some(price), ..payload("code") };
+
+        assert!(validate_campaign(&priced(0), 100).is_ok());
+        assert!(validate_campaign(&priced(99), 100).is_ok());
+        assert!(validate_campaign(&priced(100), 100).is_err());
+        assert!(validate_campaign(&priced(0), 0).is_err());
+        assert!(validate_campaign(&priced(u64::MAX), u64::MAX).is_err());
+    }
+
+    #[test]
+    fn voucher_messages_are_domain_separated() {
+        let nonce = Uuid::new_v4();
+        let message = (VoucherMessage {
+            program_id: Pubkey::new_unique(),
+            badge: Pubkey::new_unique(),
+            wallet: Pubkey::new_unique(),
+            price: 5,
+            nonce: *nonce.as_bytes(),
+            expires_at: 1717200000,
+        })
+            .to_bytes()
+            .unwrap();
+
+        assert!(message.starts_with(VOUCHER_DOMAIN));
+        assert_eq!(message.len(), VOUCHER_DOMAIN.len() + 3 * 32 + 8 + 16 + 8);
+        assert_eq!(&message[message.len() - 24..message.len() - 8], nonce.as_bytes());
+        assert_eq!(&message[message.len() - 8..], &(1717200000i64).to_le_bytes());
+    }
+}
@@ -0,0 +1,510 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_collection::{
+    Collection,
+    CollectionWithBadges,
+    CreateCollectionPayload,
+    Storefront,
+    UpdateCollectionPayload,
+};
+use crate::models::model_badge::Badge;
+use crate::models::model_user::User;
+use crate::services::service_badge::{ fetch_badge, fetch_badges_by_owner };
+use crate::services::service_metadata::refresh_in_background;
+use crate::services::service_rpc::env_or;
+use crate::services::service_user::fetch_user_by_id;
+use solana_sdk::pubkey::Pubkey;
+use sqlx::{ Postgres, Pool, Transaction };
+use std::collections::{ HashMap, HashSet };
+use std::str::FromStr;
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
+use uuid::Uuid;
+
+const MAX_NAME_LEN: usize = 64;
+const MAX_DESCRIPTION_LEN: usize = 1000;
+const MAX_COVER_IMAGE_LEN: usize = 512;
+const MAX_COLLECTION_BADGES: usize = 100;
+
+// Names are stored trimmed, so that is what the length limit applies to
+fn validate_fields(name: &str, description: &str, cover_image: &str) -> Result<(), Error> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return Err(
+            Error::CollectionError(format!("Name must be 1 to {} characters.", MAX_NAME_LEN))
+        );
+    }
+
+    if description.chars().count() > MAX_DESCRIPTION_LEN {
+        return Err(
+            Error::CollectionError(
+                format!("Description must be at most {} characters.", MAX_DESCRIPTION_LEN)
+            )
+        );
+    }
+
+    if
+        !cover_image.is_empty() &&
+        (cover_image.len() > MAX_COVER_IMAGE_LEN ||
+            !(cover_image.starts_with("https://") || cover_image.starts_with("http://")))
+    {
+        return Err(
+            Error::CollectionError(
+                format!(
+                    "Cover image must be an http(s) URL of at most {} characters.",
+                    MAX_COVER_IMAGE_LEN
+                )
+            )
+        );
+    }
+
+    Ok(())
+}
+
+// Parses the ordered badge list and checks on chain that the creator owns every badge
+async fn check_badges(
+    app_state: &AppState,
+    user: &User,
+    badges: &[String]
+) -> Result<Vec<String>, Error> {
+    if badges.len() > MAX_COLLECTION_BADGES {
+        return Err(
+            Error::CollectionError(
+                format!("A collection may hold at most {} badges.", MAX_COLLECTION_BADGES)
+            )
+        );
+    }
+
+    let mut seen = HashSet::new();
+    let mut addresses = vec![];
+
+    for badge in badges {
+        let address = Pubkey::from_str(badge).map_err(|_|
+            Error::CollectionError(format!("Badge address {} is invalid.", badge))
+        )?;
+
+        if !seen.insert(address) {
+            return Err(
+                Error::CollectionError(format!("Badge {} is listed more than once.", badge))
+            );
+        }
+
+        let account = fetch_badge(app_state, &address).await?;
+        if account.owner != user.wallet_address {
+            return Err(Error::Forbidden(format!("Badge {} is not owned by you.", badge)));
+        }
+
+        addresses.push(address.to_string());
+    }
+
+    Ok(addresses)
+}
+
+async fn replace_badges(
+    collection_id: Uuid,
+    badges: &[String],
+    session: &mut Transaction<'_, Postgres>
+) -> Result<(), Error> {
+    sqlx
+        ::query("DELETE FROM collection_badges WHERE collection_id = $1")
+        .bind(collection_id)
+        .execute(&mut *session).await
+        .map_err(|err| {
+            println!("Database delete failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    for (position, badge) in badges.iter().enumerate() {
+        sqlx
+            ::query(
+                "INSERT INTO collection_badges (collection_id, badge_address, position) VALUES ($1, $2, $3)"
+            )
+            .bind(collection_id)
+            .bind(badge)
+            .bind(position as i32)
+            .execute(&mut *session).await
+            .map_err(|err| {
+                println!("Database insert failed: {}", err);
+                Error::InternalServerError
+            })?;
+    }
+
+    Ok(())
+}
+
+// Attaches the ordered badge addresses to each collection
+async fn with_badges(
+    collections: Vec<Collection>,
+    db: &Pool<Postgres>
+) -> Result<Vec<CollectionWithBadges>, Error> {
+    let ids: Vec<Uuid> = collections
+        .iter()
+        .map(|collection| collection.id)
+        .collect();
+
+    let rows = sqlx
+        ::query_as::<_, (Uuid, String)>(
+            "SELECT collection_id, badge_address FROM collection_badges \
+             WHERE collection_id = ANY($1) ORDER BY collection_id, position"
+        )
+        .bind(&ids)
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    Ok(
+        collections
+            .into_iter()
+            .map(|collection| {
+                let badges = rows
+                    .iter()
+                    .filter(|(id, _)| *id == collection.id)
+                    .map(|(_, badge)| badge.clone())
+                    .collect();
+                CollectionWithBadges { collection, badges }
+            })
+            .collect()
+    )
+}
+
+async fn fetch_collection_row(id: Uuid, db: &Pool<Postgres>) -> Result<Collection, Error> {
+    sqlx
+        ::query_as::<_, Collection>("SELECT * FROM collections WHERE id = $1")
+        .bind(id)
+        .fetch_optional(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?
+        .ok_or(Error::GetCollectionError("Collection not found.".to_string()))
+}
+
+// Loads a collection the user created, refusing anyone else
+async fn fetch_owned_collection(
+    id: Uuid,
+    user: &User,
+    db: &Pool<Postgres>
+) -> Result<Collection, Error> {
+    let collection = fetch_collection_row(id, db).await?;
+
+    if collection.creator_id != user.id {
+        return Err(Error::Forbidden("Only the creator can change this collection.".to_string()));
+    }
+
+    Ok(collection)
+}
+
+pub async fn fetch_collection(id: Uuid, db: &Pool<Postgres>) -> Result<CollectionWithBadges, Error> {
+    let collection = fetch_collection_row(id, db).await?;
+
+    Ok(with_badges(vec![collection], db).await?.remove(0))
+}
+
+pub async fn insert_collection(
+    app_state: &AppState,
+    user: &User,
+    payload: CreateCollectionPayload
+) -> Result<CollectionWithBadges, Error> {
+    let description = payload.description.unwrap_or_default();
+    let cover_image = payload.cover_image.unwrap_or_default();
+    validate_fields(&payload.name, &description, &cover_image)?;
+    let badges = check_badges(app_state, user, &payload.badges.unwrap_or_default()).await?;
+
+    let mut session = app_state.db.begin().await.map_err(|_| Error::InternalServerError)?;
+
+    let collection = sqlx
+        ::query_as::<_, Collection>(
+            "INSERT INTO collections (creator_id, name, description, cover_image) \
+             VALUES ($1, $2, $3, $4) RETURNING *"
+        )
+        .bind(user.id)
+        .bind(payload.name.trim())
+        .bind(&description)
+        .bind(&cover_image)
+        .fetch_one(&mut session).await
+        .map_err(|err| {
+            println!("Database insert failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    replace_badges(collection.id, &badges, &mut session).await?;
+    session.commit().await.map_err(|_| Error::InternalServerError)?;
+
+    Ok(CollectionWithBadges { collection, badges })
+}
+
+pub async fn save_collection(
+    app_state: &AppState,
+    user: &User,
+    id: Uuid,
+    payload: UpdateCollectionPayload
+) -> Result<CollectionWithBadges, Error> {
+    let current = fetch_owned_collection(id, user, &app_state.db).await?;
+
+    let name = payload.name.unwrap_or(current.name);
+    let description = payload.description.unwrap_or(current.description);
+    let cover_image = payload.cover_image.unwrap_or(current.cover_image);
+    validate_fields(&name, &description, &cover_image)?;
+
+    let badges = match payload.badges {
+        Some(badges) => Some(check_badges(app_state, user, &badges).await?),
+        None => None,
+    };
+
+    let mut session = app_state.db.begin().await.map_err(|_| Error::InternalServerError)?;
+
+    let collection = sqlx
+        ::query_as::<_, Collection>(
+            "UPDATE collections SET name = $1, description = $2, cover_image = $3, updated_at = NOW() \
+             WHERE id = $4 RETURNING *"
+        )
+        .bind(name.trim())
+        .bind(&description)
+        .bind(&cover_image)
+        .bind(id)
+        .fetch_one(&mut session).await
+        .map_err(|err| {
+            println!("Database update failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    if let Some(badges) = &badges {
+        replace_badges(id, badges, &mut session).await?;
+    }
+
+    session.commit().await.map_err(|_| Error::InternalServerError)?;
+
+    Ok(with_badges(vec![collection], &app_state.db).await?.remove(0))
+}
+
+pub async fn remove_collection(id: Uuid, user: &User, db: &Pool<Postgres>) -> Result<(), Error> {
+    fetch_owned_collection(id, user, db).await?;
+
+    sqlx
+        ::query("DELETE FROM collections WHERE id = $1")
+        .bind(id)
+        .execute(db).await
+        .map_err(|err| {
+            println!("Database delete failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    Ok(())
+}
+
+// A creator's on-chain badges from recent storefront requests, so public page views do not
+// each cost getProgramAccounts calls
+pub struct StorefrontCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<Pubkey, (Vec<Badge>, Instant)>>,
+}
+
+impl StorefrontCache {
+    pub fn from_env() -> StorefrontCache {
+        StorefrontCache {
+            ttl: Duration::from_secs(env_or("STOREFRONT_CACHE_TTL_SECS", 30)),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, owner: &Pubkey) -> Option<Vec<Badge>> {
+        let entries = self.entries.lock().unwrap();
+
+        match entries.get(owner) {
+            Some((badges, fetched_at)) if fetched_at.elapsed() < self.ttl => Some(badges.clone()),
+            _ => None,
+        }
+    }
+
+    fn insert(&self, owner: &Pubkey, badges: Vec<Badge>) {
+        let mut entries = self.entries.lock().unwrap();
+        let ttl = self.ttl;
+
+        entries.retain(|_, (_, fetched_at)| fetched_at.elapsed() < ttl);
+        entries.insert(*owner, (badges, Instant::now()));
+    }
+}
+
+// Mints recorded by the holdings index across the given badges: (count, lamports paid). Only
+// badges priced in SOL count towards the lamports.
+async fn fetch_sales(badges: &[Badge], db: &Pool<Postgres>) -> Result<(u64, u64), Error> {
+    let addresses: Vec<String> = badges
+        .iter()
+        .map(|badge| badge.address.clone())
+        .collect();
+    let sol_addresses: Vec<String> = badges
+        .iter()
+        .filter(|badge| badge.decimals == 9)
+        .map(|badge| badge.address.clone())
+        .collect();
+
+    let (count, total) = sqlx
+        ::query_as::<_, (i64, i64)>(
+            "SELECT COUNT(*), COALESCE(SUM(price_paid) FILTER (WHERE badge_address = ANY($2)), 0)::BIGINT \
+             FROM badge_holdings WHERE badge_address = ANY($1)"
+        )
+        .bind(&addresses)
+        .bind(&sol_addresses)
+        .fetch_one(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    Ok((count.max(0) as u64, total.max(0) as u64))
+}
+
+// Splits the badges a storefront lists into (live, sold out). Paused badges are left out
+// unless they sold out anyway.
+fn split_badges(badges: Vec<Badge>) -> (Vec<Badge>, Vec<Badge>) {
+    let (sold_out, live): (Vec<_>, Vec<_>) = badges
+        .into_iter()
+        .filter(|badge| badge.supply >= badge.max_supply || !badge.paused)
+        .partition(|badge| badge.supply >= badge.max_supply);
+
+    (live, sold_out)
+}
+
+// Everything a creator's public page shows. Badges are read from chain, cached for
+// STOREFRONT_CACHE_TTL_SECS, and sales from the index.
+pub async fn fetch_storefront(app_state: Arc<AppState>, creator_id: Uuid) -> Result<Storefront, Error> {
+    let creator = fetch_user_by_id(creator_id, &app_state.db).await?;
+    let owner = Pubkey::from_str(&creator.wallet_address).map_err(|_|
+        Error::GetAccountError("Creator wallet address is invalid.".to_string())
+    )?;
+
+    let collections = sqlx
+        ::query_as::<_, Collection>(
+            "SELECT * FROM collections WHERE creator_id = $1 ORDER BY created_at DESC"
+        )
+        .bind(creator_id)
+        .fetch_all(&app_state.db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+    let collections = with_badges(collections, &app_state.db).await?;
+
+    let mut badges = match app_state.storefronts.get(&owner) {
+        Some(badges) => badges,
+        None => {
+            let badges = fetch_badges_by_owner(&app_state, &owner).await?;
+            app_state.storefronts.insert(&owner, badges.clone());
+            badges
+        }
+    };
+    let stale = app_state.metadata.attach_cached(&mut badges, &app_state.db).await?;
+    refresh_in_background(app_state.clone(), stale);
+
+    let (total_sales, total_sales_lamports) = fetch_sales(&badges, &app_state.db).await?;
+    let (live_badges, sold_out_badges) = split_badges(badges);
+
+    Ok(Storefront {
+        creator,
+        collections,
+        live_badges,
+        sold_out_badges,
+        total_sales,
+        total_sales_lamports,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn badge(supply: u64, max_supply: u64, paused: bool) -> Badge {
+        Badge {
+            address: Pubkey::new_unique().to_string(),
+            version: 2,
+            owner: Pubkey::new_unique().to_string(),
+            supply,
+            max_supply,
+            price: 1_000_000_000,
+            decimals: 9,
+            name: "Pebble Genesis".to_string(),
+            symbol: "PBLG".to_string(),
+            uri: String::new(),
+            created_at: 1_700_000_000,
+            royalty_basis_points: 0,
+            paused,
+            metadata: None,
+        }
+    }
+
+    fn cache(ttl: Duration) -> StorefrontCache {
+        StorefrontCache { ttl, entries: Mutex::new(HashMap::new()) }
+    }
+
+    #[test]
+    fn accepts_valid_fields() {
+        assert!(validate_fields("Genesis", "", "").is_ok());
+        assert!(validate_fields("Genesis", "First drop", "https://example.com/cover.png").is_ok());
+    }
+
+    #[test]
+    fn limits_the_trimmed_name() {
+        let name = "a".repeat(MAX_NAME_LEN);
+
+        assert!(validate_fields(&format!("  {}  ", name), "", "").is_ok());
+        assert!(validate_fields(&format!("{}a", name), "", "").is_err());
+        assert!(validate_fields("   ", "", "").is_err());
+    }
+
+    #[test]
+    fn rejects_long_descriptions_and_bad_cover_images() {
+        assert!(validate_fields("Genesis", &"a".repeat(MAX_DESCRIPTION_LEN + 1), "").is_err());
+        assert!(validate_fields("Genesis", "", "ftp://example.com/cover.png").is_err());
+        assert!(validate_fields("Genesis", "", "javascript:alert(1)").is_err());
+
+        let long_url = format!("https://example.com/{}", "a".repeat(MAX_COVER_IMAGE_LEN));
+        assert!(validate_fields("Genesis", "", &long_url).is_err());
+    }
+
+    #[test]
+    fn cached_badges_are_served_until_the_ttl() {
+        let owner = Pubkey::new_unique();
+        let badges = vec![badge(1, 10, false)];
+
+        let fresh = cache(Duration::from_secs(60));
+        assert_eq!(fresh.get(&owner), None);
+        fresh.insert(&owner, badges.clone());
+        assert_eq!(fresh.get(&owner), Some(badges.clone()));
+        assert_eq!(fresh.get(&Pubkey::new_unique()), None);
+
+        let expired = cache(Duration::ZERO);
+        expired.insert(&owner, badges);
+        assert_eq!(expired.get(&owner), None);
+    }
+
+    #[test]
+    fn inserts_evict_expired_entries() {
+        let cache = cache(Duration::ZERO);
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+
+        cache.insert(&first, vec![]);
+        cache.insert(&second, vec![]);
+
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries.contains_key(&second));
+    }
+
+    #[test]
+    fn splits_live_and_sold_out_badges() {
+        let live = badge(1, 10, false);
+        let sold_out = badge(10, 10, false);
+        let paused = badge(1, 10, true);
+        let paused_sold_out = badge(10, 10, true);
+
+        let (live_badges, sold_out_badges) = split_badges(
+            vec![live.clone(), sold_out.clone(), paused, paused_sold_out.clone()]
+        );
+
+        assert_eq!(live_badges, vec![live]);
+        assert_eq!(sold_out_badges, vec![sold_out, paused_sold_out]);
+    }
+}
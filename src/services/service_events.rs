@@ -0,0 +1,453 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_activity::ProgramEvent;
+use crate::models::model_socket::{
+    BadgeMintedMessage,
+    BadgeSoldOutMessage,
+    BadgeUpdatedMessage,
+    EventEnvelope,
+    NotificationMessage,
+    SocketEvent,
+};
+use crate::services::service_metrics::Metrics;
+use crate::services::service_rpc::env_or;
+use chrono::Utc;
+use serde_json::json;
+use socketioxide::SocketIo;
+use sqlx::{ postgres::PgListener, Postgres, Pool };
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{ self, error::TrySendError };
+use uuid::Uuid;
+
+const FANOUT_CHANNEL: &str = "pebble_socket_events";
+// Postgres rejects NOTIFY payloads of 8000 bytes or more
+const MAX_NOTIFY_PAYLOAD: usize = 7900;
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+// Room for a badge's public events
+pub fn badge_room(address: &str) -> String {
+    format!("badge:{}", address)
+}
+
+// Room every socket of a signed-in user joins
+pub fn user_room(user_id: &Uuid) -> String {
+    format!("user:{}", user_id)
+}
+
+// The one place events leave the server for Socket.IO clients. Broadcasts reach this
+// instance's sockets directly and, with EVENT_FANOUT_ENABLED, the other instances' through
+// Postgres NOTIFY.
+pub struct EventBus {
+    io: SocketIo,
+    origin: Uuid,
+    fanout: Option<mpsc::Sender<(String, SocketEvent)>>,
+    metrics: Arc<Metrics>,
+}
+
+impl EventBus {
+    // Starts the task forwarding published events to NOTIFY, in publish order. At most
+    // EVENT_FANOUT_QUEUE events wait for it, so a stalled database cannot grow the queue
+    // without bound.
+    pub fn new(io: SocketIo, db: Pool<Postgres>, metrics: Arc<Metrics>) -> EventBus {
+        let origin = Uuid::new_v4();
+        let fanout = if env_or("EVENT_FANOUT_ENABLED", true) {
+            let capacity: usize = env_or("EVENT_FANOUT_QUEUE", 1024);
+            let (sender, receiver) = mpsc::channel(capacity.max(1));
+            tokio::spawn(forward_events(db, origin, receiver));
+            Some(sender)
+        } else {
+            None
+        };
+
+        EventBus { io, origin, fanout, metrics }
+    }
+
+    fn emit_local(&self, room: &str, event: &SocketEvent) {
+        if let Err(err) = self.io.to(room.to_string()).emit(event.name(), event.payload()) {
+            println!("Failed to emit {} to {}: {:?}", event.name(), room, err);
+        }
+    }
+
+    pub fn publish(&self, room: &str, event: &SocketEvent) {
+        self.emit_local(room, event);
+
+        if let Some(fanout) = &self.fanout {
+            match fanout.try_send((room.to_string(), event.clone())) {
+                Ok(()) => {}
+                // Local sockets already have the event; only the other instances miss it
+                Err(TrySendError::Full(_)) => {
+                    self.metrics.incr("event_fanout_dropped");
+                }
+                Err(TrySendError::Closed(_)) => {
+                    println!("Event fanout stopped, {} was not sent to other instances", event.name());
+                }
+            }
+        }
+    }
+
+    // Sends a `notification:new` to every socket of a user
+    pub fn notify(&self, user_id: &Uuid, kind: &str, message: String, data: serde_json::Value) {
+        let notification = NotificationMessage {
+            id: Uuid::new_v4(),
+            kind: kind.to_string(),
+            message,
+            data,
+            created_at: Utc::now(),
+        };
+
+        self.publish(&user_room(user_id), &SocketEvent::Notification(notification));
+    }
+}
+
+// Serializes an envelope for NOTIFY, or returns None when it is over the payload limit
+fn encode_envelope(envelope: &EventEnvelope) -> Result<Option<String>, Error> {
+    let payload = serde_json::to_string(envelope).map_err(|err| {
+        println!("Failed to serialize event envelope: {}", err);
+        Error::InternalServerError
+    })?;
+
+    Ok(Some(payload).filter(|payload| payload.len() <= MAX_NOTIFY_PAYLOAD))
+}
+
+// Sends one event to the other instances, storing it first when it is too large for NOTIFY
+async fn notify_event(
+    db: &Pool<Postgres>,
+    origin: Uuid,
+    room: String,
+    event: SocketEvent
+) -> Result<(), Error> {
+    let mut envelope = EventEnvelope { origin, room, event: Some(event), stored_id: None };
+
+    let payload = match encode_envelope(&envelope)? {
+        Some(payload) => payload,
+        None => {
+            let event = serde_json::to_value(&envelope.event).map_err(|err| {
+                println!("Failed to serialize socket event: {}", err);
+                Error::InternalServerError
+            })?;
+
+            // Receivers fetch stored events right away, so old ones are only kept as a margin
+            sqlx
+                ::query("DELETE FROM socket_events WHERE created_at < NOW() - INTERVAL '10 minutes'")
+                .execute(db).await
+                .map_err(|err| {
+                    println!("Database delete failed: {}", err);
+                    Error::InternalServerError
+                })?;
+
+            let (id,) = sqlx
+                ::query_as::<_, (i64,)>("INSERT INTO socket_events (event) VALUES ($1) RETURNING id")
+                .bind(event)
+                .fetch_one(db).await
+                .map_err(|err| {
+                    println!("Database insert failed: {}", err);
+                    Error::InternalServerError
+                })?;
+
+            envelope.event = None;
+            envelope.stored_id = Some(id);
+            encode_envelope(&envelope)?.ok_or(Error::InternalServerError)?
+        }
+    };
+
+    sqlx
+        ::query("SELECT pg_notify($1, $2)")
+        .bind(FANOUT_CHANNEL)
+        .bind(payload)
+        .execute(db).await
+        .map_err(|err| {
+            println!("Database notify failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    Ok(())
+}
+
+async fn forward_events(
+    db: Pool<Postgres>,
+    origin: Uuid,
+    mut receiver: mpsc::Receiver<(String, SocketEvent)>
+) {
+    while let Some((room, event)) = receiver.recv().await {
+        let name = event.name();
+
+        if let Err(err) = notify_event(&db, origin, room, event).await {
+            println!("Failed to send {} to other instances: {:?}", name, err);
+        }
+    }
+}
+
+// Emits an event another instance published to this instance's sockets
+async fn receive_event(app_state: &AppState, payload: &str) -> Result<(), Error> {
+    let envelope: EventEnvelope = serde_json::from_str(payload).map_err(|err| {
+        println!("Failed to parse event envelope: {}", err);
+        Error::InternalServerError
+    })?;
+
+    if envelope.origin == app_state.events.origin {
+        return Ok(());
+    }
+
+    let event = match (envelope.event, envelope.stored_id) {
+        (Some(event), _) => event,
+        (None, Some(id)) => {
+            let (event,) = sqlx
+                ::query_as::<_, (serde_json::Value,)>("SELECT event FROM socket_events WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&app_state.db).await
+                .map_err(|err| {
+                    println!("Database query failed: {}", err);
+                    Error::InternalServerError
+                })?
+                .ok_or(Error::InternalServerError)?;
+
+            serde_json::from_value(event).map_err(|err| {
+                println!("Failed to parse stored socket event {}: {}", id, err);
+                Error::InternalServerError
+            })?
+        }
+        (None, None) => {
+            return Ok(());
+        }
+    };
+
+    app_state.events.emit_local(&envelope.room, &event);
+    app_state.metrics.incr("event_fanout_received");
+
+    Ok(())
+}
+
+// Listens until the connection fails. `try_recv` reconnects by itself after a dropped
+// connection; notifications sent while it was down are lost.
+async fn listen_events(app_state: &AppState, retry: &mut Duration) -> Result<(), Error> {
+    let mut listener = PgListener::connect_with(&app_state.db).await.map_err(|err| {
+        println!("Event fanout connection failed: {}", err);
+        Error::InternalServerError
+    })?;
+
+    listener.listen(FANOUT_CHANNEL).await.map_err(|err| {
+        println!("Event fanout LISTEN failed: {}", err);
+        Error::InternalServerError
+    })?;
+    *retry = MIN_RECONNECT_DELAY;
+
+    loop {
+        match listener.try_recv().await {
+            Ok(Some(notification)) => {
+                if let Err(err) = receive_event(app_state, notification.payload()).await {
+                    println!("Failed to relay a fanned out event: {:?}", err);
+                }
+            }
+            Ok(None) => {
+                println!("Event fanout connection lost, reconnecting");
+                app_state.metrics.incr("event_fanout_reconnects");
+            }
+            Err(err) => {
+                println!("Event fanout receive failed: {}", err);
+                return Err(Error::InternalServerError);
+            }
+        }
+    }
+}
+
+// Background task relaying other instances' broadcasts to this instance's sockets. Reopens the
+// listener with a doubling delay, up to a minute, when it fails.
+pub async fn track_event_fanout(app_state: Arc<AppState>) {
+    if app_state.events.fanout.is_none() {
+        return;
+    }
+
+    let mut retry = MIN_RECONNECT_DELAY;
+
+    loop {
+        if let Err(err) = listen_events(&app_state, &mut retry).await {
+            println!("Event fanout listener stopped: {:?}", err);
+        }
+
+        app_state.metrics.incr("event_fanout_reconnects");
+        tokio::time::sleep(retry).await;
+        retry = (retry * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+// Signed-up users among some wallets, as (id, wallet address)
+async fn fetch_wallet_users(
+    wallets: &[String],
+    app_state: &AppState
+) -> Result<Vec<(Uuid, String)>, Error> {
+    sqlx
+        ::query_as::<_, (Uuid, String)>(
+            "SELECT id, wallet_address FROM users WHERE wallet_address = ANY($1)"
+        )
+        .bind(wallets)
+        .fetch_all(&app_state.db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })
+}
+
+// Broadcasts a newly indexed program event to its badge room. Mints also notify the minter and
+// the badge's creator, and `sold_out` marks a mint of the last number in supply.
+pub async fn publish_program_event(
+    app_state: &AppState,
+    event: &ProgramEvent,
+    signature: &str,
+    slot: u64,
+    sold_out: bool
+) -> Result<(), Error> {
+    let badge = event.badge().to_string();
+    let room = badge_room(&badge);
+
+    match event {
+        ProgramEvent::BadgeCreated(created) => {
+            let message = BadgeUpdatedMessage {
+                badge,
+                owner: created.owner.to_string(),
+                change: "created".to_string(),
+                price: created.price,
+                old_price: None,
+                max_supply: Some(created.max_supply),
+                signature: signature.to_string(),
+                slot,
+            };
+            app_state.events.publish(&room, &SocketEvent::BadgeUpdated(message));
+        }
+        ProgramEvent::PriceChanged(changed) => {
+            let message = BadgeUpdatedMessage {
+                badge,
+                owner: changed.owner.to_string(),
+                change: "price_changed".to_string(),
+                price: changed.new_price,
+                old_price: Some(changed.old_price),
+                max_supply: None,
+                signature: signature.to_string(),
+                slot,
+            };
+            app_state.events.publish(&room, &SocketEvent::BadgeUpdated(message));
+        }
+        ProgramEvent::BadgeMinted(minted) => {
+            let message = BadgeMintedMessage {
+                badge: badge.clone(),
+                receipt: minted.receipt.to_string(),
+                holder: minted.holder.to_string(),
+                number: minted.number,
+                price_paid: minted.price_paid,
+                minted_at: minted.minted_at,
+                signature: signature.to_string(),
+                slot,
+            };
+            app_state.events.publish(&room, &SocketEvent::BadgeMinted(message.clone()));
+
+            if sold_out {
+                let sold_out_message = BadgeSoldOutMessage {
+                    badge: badge.clone(),
+                    supply: minted.number,
+                    signature: signature.to_string(),
+                    slot,
+                };
+                app_state.events.publish(&room, &SocketEvent::BadgeSoldOut(sold_out_message));
+            }
+
+            let (creator,) = sqlx
+                ::query_as::<_, (Option<String>,)>(
+                    "SELECT COALESCE((SELECT owner FROM badge_search WHERE badge_address = $1), \
+                     (SELECT wallet_address FROM activity WHERE kind = 'badge_created' \
+                     AND badge_address = $1 LIMIT 1))"
+                )
+                .bind(&badge)
+                .fetch_one(&app_state.db).await
+                .map_err(|err| {
+                    println!("Database query failed: {}", err);
+                    Error::InternalServerError
+                })?;
+
+            let holder = minted.holder.to_string();
+            let wallets: Vec<String> = std::iter
+                ::once(holder.clone())
+                .chain(creator.clone())
+                .collect();
+            let data = serde_json::to_value(&message).unwrap_or_default();
+
+            for (user_id, wallet) in fetch_wallet_users(&wallets, app_state).await? {
+                if wallet == holder {
+                    app_state.events.notify(
+                        &user_id,
+                        "badge_minted",
+                        format!("You minted #{} of badge {}.", minted.number, badge),
+                        data.clone()
+                    );
+                }
+
+                if Some(&wallet) == creator.as_ref() {
+                    app_state.events.notify(
+                        &user_id,
+                        "badge_sold",
+                        format!("Badge {} sold #{}.", badge, minted.number),
+                        data.clone()
+                    );
+
+                    if sold_out {
+                        app_state.events.notify(
+                            &user_id,
+                            "badge_sold_out",
+                            format!("Badge {} sold out.", badge),
+                            json!({ "badge": badge, "supply": minted.number })
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(data: serde_json::Value) -> SocketEvent {
+        SocketEvent::Notification(NotificationMessage {
+            id: Uuid::new_v4(),
+            kind: "badge_sold".to_string(),
+            message: "Badge sold.".to_string(),
+            data,
+            created_at: Utc::now(),
+        })
+    }
+
+    #[test]
+    fn envelopes_round_trip_under_the_notify_limit() {
+        let envelope = EventEnvelope {
+            origin: Uuid::new_v4(),
+            room: user_room(&Uuid::new_v4()),
+            event: Some(notification(json!({ "number": 7 }))),
+            stored_id: None,
+        };
+
+        let payload = encode_envelope(&envelope).unwrap().expect("small events fit in NOTIFY");
+        assert!(payload.contains("\"event\":\"notification:new\""));
+
+        let decoded: EventEnvelope = serde_json::from_str(&payload).unwrap();
+        assert_eq!(decoded.room, envelope.room);
+        assert_eq!(decoded.event.unwrap().payload(), envelope.event.unwrap().payload());
+    }
+
+    #[test]
+    fn oversized_envelopes_are_not_encoded() {
+        let envelope = EventEnvelope {
+            origin: Uuid::new_v4(),
+            room: badge_room("badge"),
+            event: Some(notification(json!({ "padding": "x".repeat(MAX_NOTIFY_PAYLOAD) }))),
+            stored_id: None,
+        };
+
+        assert!(encode_envelope(&envelope).unwrap().is_none());
+
+        let stored = EventEnvelope { event: None, stored_id: Some(1), ..envelope };
+        assert!(encode_envelope(&stored).unwrap().is_some());
+    }
+}
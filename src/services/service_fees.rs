@@ -0,0 +1,209 @@
+use crate::errors::error::Error;
+use crate::models::model_network::PriorityFeeEstimate;
+use crate::models::model_transaction::ComputeBudget;
+use crate::services::service_program::{ build_transaction, compute_budget_instructions };
+use crate::services::service_rpc::{ env_or, RpcPool };
+use solana_sdk::compute_budget;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+
+// Runtime ceiling for a single transaction
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+const MIN_COMPUTE_UNIT_LIMIT: u32 = 10_000;
+const FALLBACK_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+// getRecentPrioritizationFees accepts at most 128 accounts
+const MAX_FEE_ACCOUNTS: usize = 128;
+
+// Nearest-rank percentile of an ascending list
+fn percentile(sorted: &[u64], percentile: u64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+
+    let rank = ((percentile * (sorted.len() as u64)).div_ceil(100)).max(1) as usize;
+    sorted[rank.min(sorted.len()) - 1]
+}
+
+// Accounts the instructions write to, which is what priority fees are contended on
+fn writable_accounts(instructions: &[Instruction]) -> Vec<Pubkey> {
+    let mut accounts: Vec<Pubkey> = vec![];
+
+    for account in instructions.iter().flat_map(|instruction| &instruction.accounts) {
+        if account.is_writable && !accounts.contains(&account.pubkey) {
+            accounts.push(account.pubkey);
+        }
+    }
+
+    accounts.truncate(MAX_FEE_ACCOUNTS);
+    accounts
+}
+
+// Prices built transactions from recent prioritization fees and sizes their compute limit by simulation
+pub struct FeeEstimator {
+    percentile: u64,
+    min_unit_price: u64,
+    max_unit_price: u64,
+    unit_margin_percent: u64,
+}
+
+impl FeeEstimator {
+    pub fn from_env() -> FeeEstimator {
+        let percentile = env_or("PRIORITY_FEE_PERCENTILE", 75u64);
+
+        if percentile > 100 {
+            println!("Invalid PRIORITY_FEE_PERCENTILE: {}", percentile);
+            std::process::exit(1);
+        }
+
+        let min_unit_price = env_or("PRIORITY_FEE_MIN_MICRO_LAMPORTS", 1_000u64);
+        let max_unit_price = env_or("PRIORITY_FEE_MAX_MICRO_LAMPORTS", 1_000_000u64);
+
+        // `clamp` panics on every estimate otherwise
+        if min_unit_price > max_unit_price {
+            println!(
+                "PRIORITY_FEE_MIN_MICRO_LAMPORTS ({}) is above PRIORITY_FEE_MAX_MICRO_LAMPORTS ({})",
+                min_unit_price,
+                max_unit_price
+            );
+            std::process::exit(1);
+        }
+
+        FeeEstimator {
+            percentile,
+            min_unit_price,
+            max_unit_price,
+            unit_margin_percent: env_or("COMPUTE_UNIT_MARGIN_PERCENT", 20),
+        }
+    }
+
+    pub async fn estimate(&self, rpc: &RpcPool, accounts: &[Pubkey]) -> Result<PriorityFeeEstimate, Error> {
+        let mut fees: Vec<u64> = rpc
+            .get_recent_prioritization_fees(accounts).await?
+            .into_iter()
+            .map(|fee| fee.prioritization_fee)
+            .collect();
+        fees.sort_unstable();
+
+        let recommended = percentile(&fees, self.percentile).clamp(
+            self.min_unit_price,
+            self.max_unit_price
+        );
+
+        Ok(PriorityFeeEstimate {
+            samples: fees.len(),
+            min: fees.first().copied().unwrap_or(0),
+            low: percentile(&fees, 25),
+            medium: percentile(&fees, 50),
+            high: percentile(&fees, 75),
+            very_high: percentile(&fees, 90),
+            max: fees.last().copied().unwrap_or(0),
+            recommended,
+        })
+    }
+
+    // Simulates with the maximum limit and adds a margin to the units consumed.
+    // Falls back to a fixed limit when simulation fails, so the wallet still sees the real error.
+    pub async fn compute_unit_limit(
+        &self,
+        rpc: &RpcPool,
+        fee_payer: &Pubkey,
+        instructions: &[Instruction],
+        recent_blockhash: Hash
+    ) -> Result<u32, Error> {
+        let mut simulated = compute_budget_instructions(MAX_COMPUTE_UNIT_LIMIT, 0);
+        simulated.extend_from_slice(instructions);
+
+        let transaction = build_transaction(fee_payer, &simulated, recent_blockhash);
+        let result = rpc.simulate_transaction(&transaction).await?;
+
+        match (result.err, result.units_consumed) {
+            (None, Some(units)) => {
+                let limit = units + (units * self.unit_margin_percent) / 100;
+                Ok(limit.clamp(MIN_COMPUTE_UNIT_LIMIT as u64, MAX_COMPUTE_UNIT_LIMIT as u64) as u32)
+            }
+            (err, _) => {
+                println!("Compute unit simulation failed, using the fallback limit: {:?}", err);
+                Ok(FALLBACK_COMPUTE_UNIT_LIMIT)
+            }
+        }
+    }
+
+    // Replaces any compute budget instructions with a freshly estimated limit and price
+    pub async fn with_compute_budget(
+        &self,
+        rpc: &RpcPool,
+        fee_payer: &Pubkey,
+        instructions: Vec<Instruction>,
+        recent_blockhash: Hash
+    ) -> Result<(Vec<Instruction>, ComputeBudget), Error> {
+        let instructions: Vec<Instruction> = instructions
+            .into_iter()
+            .filter(|instruction| instruction.program_id != compute_budget::id())
+            .collect();
+
+        let estimate = self.estimate(rpc, &writable_accounts(&instructions)).await?;
+        let compute_unit_limit = self.compute_unit_limit(
+            rpc,
+            fee_payer,
+            &instructions,
+            recent_blockhash
+        ).await?;
+
+        let budget = ComputeBudget {
+            compute_unit_limit,
+            compute_unit_price: estimate.recommended,
+        };
+
+        let mut budgeted = compute_budget_instructions(
+            budget.compute_unit_limit,
+            budget.compute_unit_price
+        );
+        budgeted.extend(instructions);
+
+        Ok((budgeted, budget))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::AccountMeta;
+
+    #[test]
+    fn percentile_uses_the_nearest_rank() {
+        let fees = [10, 20, 30, 40, 50, 60, 70, 80, 90, 100];
+
+        assert_eq!(percentile(&fees, 0), 10);
+        assert_eq!(percentile(&fees, 10), 10);
+        assert_eq!(percentile(&fees, 11), 20);
+        assert_eq!(percentile(&fees, 50), 50);
+        assert_eq!(percentile(&fees, 75), 80);
+        assert_eq!(percentile(&fees, 100), 100);
+    }
+
+    #[test]
+    fn percentile_handles_short_lists() {
+        assert_eq!(percentile(&[], 75), 0);
+        assert_eq!(percentile(&[42], 0), 42);
+        assert_eq!(percentile(&[42], 75), 42);
+        assert_eq!(percentile(&[1, 2, 3], 150), 3);
+    }
+
+    #[test]
+    fn only_distinct_writable_accounts_are_priced() {
+        let program = Pubkey::new_unique();
+        let writable = Pubkey::new_unique();
+        let readonly = Pubkey::new_unique();
+
+        let instructions = vec![
+            Instruction::new_with_bytes(program, &[], vec![
+                AccountMeta::new(writable, true),
+                AccountMeta::new_readonly(readonly, false),
+            ]),
+            Instruction::new_with_bytes(program, &[], vec![AccountMeta::new(writable, false)])
+        ];
+
+        assert_eq!(writable_accounts(&instructions), vec![writable]);
+    }
+}
@@ -0,0 +1,271 @@
+use crate::database::db::AppState;
+use crate::errors::error::Error;
+use crate::models::model_user::User;
+use crate::services::service_holding::verify_ownership;
+use crate::services::service_rpc::env_or;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
+use solana_sdk::pubkey::Pubkey;
+
+use axum::{
+    body::Body,
+    extract::{ Path, State },
+    http::Request,
+    middleware::Next,
+    response::Response,
+    RequestExt,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GateMode {
+    // At least one of the badges
+    Any,
+    // Every one of the badges
+    All,
+}
+
+impl FromStr for GateMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "any" => Ok(GateMode::Any),
+            "all" => Ok(GateMode::All),
+            _ => Err(format!("unknown badge gate mode {}", mode)),
+        }
+    }
+}
+
+// Which badges a route requires. A badge counts as held once the wallets hold
+// `min_count` receipts of it.
+#[derive(Debug, Clone)]
+pub struct BadgeGate {
+    badges: Vec<Pubkey>,
+    path_param: Option<&'static str>,
+    mode: GateMode,
+    min_count: usize,
+}
+
+impl BadgeGate {
+    // Badges in `badges` are required along with the one named by `path_param`, if any.
+    // A gate with neither would admit everyone, so it is rejected.
+    pub fn new(
+        badges: Vec<Pubkey>,
+        path_param: Option<&'static str>,
+        mode: GateMode,
+        min_count: usize
+    ) -> Result<BadgeGate, String> {
+        if badges.is_empty() && path_param.is_none() {
+            return Err("a badge gate needs at least one badge".to_string());
+        }
+
+        Ok(BadgeGate { badges, path_param, mode, min_count: min_count.max(1) })
+    }
+
+    // Requires the badge named by a path parameter, e.g. `:address`
+    pub fn from_path(param: &'static str) -> BadgeGate {
+        BadgeGate::new(vec![], Some(param), GateMode::All, 1).expect("the path names the badge")
+    }
+
+    fn is_satisfied(&self, counts: &[usize]) -> bool {
+        if counts.is_empty() {
+            return false;
+        }
+
+        let held = counts.iter().filter(|count| **count >= self.min_count);
+
+        match self.mode {
+            GateMode::Any => held.count() > 0,
+            GateMode::All => held.count() == counts.len(),
+        }
+    }
+}
+
+// Receipt counts per (wallet, badge) from recent on-chain checks
+pub struct OwnershipCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<(Pubkey, Pubkey), (usize, Instant)>>,
+}
+
+impl OwnershipCache {
+    pub fn from_env() -> OwnershipCache {
+        OwnershipCache {
+            ttl: Duration::from_secs(env_or("BADGE_GATE_CACHE_TTL_SECS", 60)),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, wallet: &Pubkey, badge: &Pubkey) -> Option<usize> {
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.get(&(*wallet, *badge)) {
+            Some((count, checked_at)) if checked_at.elapsed() < self.ttl => Some(*count),
+            Some(_) => {
+                entries.remove(&(*wallet, *badge));
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, wallet: &Pubkey, badge: &Pubkey, count: usize) {
+        let mut entries = self.entries.lock().unwrap();
+        let ttl = self.ttl;
+
+        entries.retain(|_, (_, checked_at)| checked_at.elapsed() < ttl);
+        entries.insert((*wallet, *badge), (count, Instant::now()));
+    }
+}
+
+// Wallets whose receipts count towards a user's badges
+fn user_wallets(user: &User) -> Vec<Pubkey> {
+    Pubkey::from_str(&user.wallet_address).into_iter().collect()
+}
+
+// Total receipts of `badge` across the wallets, and whether any count came from the cache
+async fn count_receipts(
+    app_state: &AppState,
+    wallets: &[Pubkey],
+    badge: &Pubkey,
+    fresh: bool
+) -> Result<(usize, bool), Error> {
+    let mut total = 0;
+    let mut from_cache = false;
+
+    for wallet in wallets {
+        let cached = if fresh { None } else { app_state.ownership_cache.get(wallet, badge) };
+
+        let count = match cached {
+            Some(count) => {
+                from_cache = true;
+                count
+            }
+            None => {
+                let proof = verify_ownership(app_state, wallet, badge).await?;
+                app_state.ownership_cache.insert(wallet, badge, proof.receipts.len());
+                proof.receipts.len()
+            }
+        };
+
+        total += count;
+    }
+
+    Ok((total, from_cache))
+}
+
+// Middleware requiring the authenticated user to hold the gate's badges.
+// Layer it inside `auth` so the user extension is present.
+pub async fn badge_gate(
+    State((app_state, gate)): State<(Arc<AppState>, Arc<BadgeGate>)>,
+    mut req: Request<Body>,
+    next: Next
+) -> Result<Response<Body>, Error> {
+    let user = req
+        .extensions()
+        .get::<User>()
+        .cloned()
+        .ok_or_else(|| Error::Unauthorized("Not authenticated".to_string()))?;
+
+    let mut badges = gate.badges.clone();
+
+    if let Some(param) = gate.path_param {
+        let Path(params) = req
+            .extract_parts::<Path<HashMap<String, String>>>().await
+            .map_err(|_| Error::InternalServerError)?;
+        let address = params.get(param).ok_or(Error::InternalServerError)?;
+
+        badges.push(
+            Pubkey::from_str(address).map_err(|_|
+                Error::GetAccountError("Badge address is invalid.".to_string())
+            )?
+        );
+    }
+
+    let wallets = user_wallets(&user);
+    let mut counts = vec![];
+    let mut cached = vec![];
+
+    for badge in &badges {
+        let (count, from_cache) = count_receipts(&app_state, &wallets, badge, false).await?;
+        counts.push(count);
+        cached.push(from_cache);
+    }
+
+    // A cached "no" may predate a mint, so recheck those badges on-chain before refusing
+    if !gate.is_satisfied(&counts) {
+        for (index, badge) in badges.iter().enumerate() {
+            if cached[index] && counts[index] < gate.min_count {
+                counts[index] = count_receipts(&app_state, &wallets, badge, true).await?.0;
+            }
+        }
+    }
+
+    if !gate.is_satisfied(&counts) {
+        app_state.metrics.incr("badge_gate_denied");
+        return Err(Error::BadgeRequired("You do not hold the badge required for this.".to_string()));
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gate(badges: usize, mode: GateMode, min_count: usize) -> BadgeGate {
+        let badges = (0..badges).map(|_| Pubkey::new_unique()).collect();
+        BadgeGate::new(badges, None, mode, min_count).unwrap()
+    }
+
+    #[test]
+    fn gates_need_a_badge_unless_it_comes_from_the_path() {
+        assert!(BadgeGate::new(vec![], None, GateMode::All, 1).is_err());
+        assert!(BadgeGate::new(vec![], None, GateMode::Any, 1).is_err());
+        assert!(BadgeGate::new(vec![], Some("address"), GateMode::All, 1).is_ok());
+
+        let from_path = BadgeGate::from_path("address");
+        assert!(!from_path.is_satisfied(&[]));
+        assert!(from_path.is_satisfied(&[1]));
+        assert!(!from_path.is_satisfied(&[0]));
+    }
+
+    #[test]
+    fn any_gates_need_one_held_badge() {
+        let gate = gate(3, GateMode::Any, 1);
+
+        assert!(gate.is_satisfied(&[0, 0, 1]));
+        assert!(gate.is_satisfied(&[2, 1, 1]));
+        assert!(!gate.is_satisfied(&[0, 0, 0]));
+    }
+
+    #[test]
+    fn all_gates_need_every_badge() {
+        let gate = gate(3, GateMode::All, 1);
+
+        assert!(gate.is_satisfied(&[1, 1, 3]));
+        assert!(!gate.is_satisfied(&[1, 0, 1]));
+    }
+
+    #[test]
+    fn badges_count_once_min_count_receipts_are_held() {
+        let any = gate(2, GateMode::Any, 2);
+        assert!(!any.is_satisfied(&[1, 1]));
+        assert!(any.is_satisfied(&[0, 2]));
+
+        let all = gate(2, GateMode::All, 3);
+        assert!(!all.is_satisfied(&[3, 2]));
+        assert!(all.is_satisfied(&[3, 4]));
+
+        assert!(gate(1, GateMode::All, 0).is_satisfied(&[1]));
+        assert!(!gate(1, GateMode::All, 0).is_satisfied(&[0]));
+    }
+
+    #[test]
+    fn gate_modes_parse() {
+        assert_eq!(GateMode::from_str("any"), Ok(GateMode::Any));
+        assert_eq!(GateMode::from_str("all"), Ok(GateMode::All));
+        assert!(GateMode::from_str("most").is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_account::{ account_discriminator, PebbleAccount };
+use crate::models::model_holding::{ BadgeHolding, OwnershipProof };
+use crate::services::service_rpc::env_or;
+use solana_client::rpc_filter::{ Memcmp, RpcFilterType };
+use solana_sdk::pubkey::Pubkey;
+use sqlx::{ Postgres, Pool };
+use std::sync::Arc;
+use std::time::Duration;
+
+// MintReceiptAccount layout: discriminator, badge, holder, ...
+const RECEIPT_BADGE_OFFSET: usize = 8;
+const RECEIPT_HOLDER_OFFSET: usize = 40;
+const MAX_HOLDINGS_PAGE: i64 = 500;
+
+fn receipt_filters(badge: Option<&Pubkey>, holder: Option<&Pubkey>) -> Vec<RpcFilterType> {
+    let mut filters = vec![
+        RpcFilterType::Memcmp(
+            Memcmp::new_base58_encoded(0, &account_discriminator("MintReceiptAccount"))
+        )
+    ];
+
+    if let Some(badge) = badge {
+        filters.push(
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(RECEIPT_BADGE_OFFSET, badge.as_ref()))
+        );
+    }
+
+    if let Some(holder) = holder {
+        filters.push(
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(RECEIPT_HOLDER_OFFSET, holder.as_ref()))
+        );
+    }
+
+    filters
+}
+
+fn page(limit: Option<i64>, offset: Option<i64>) -> (i64, i64) {
+    (limit.unwrap_or(100).clamp(1, MAX_HOLDINGS_PAGE), offset.unwrap_or(0).max(0))
+}
+
+// Gets the indexed holders of a badge, in mint order
+pub async fn fetch_badge_holders(
+    badge_address: &str,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    db: &Pool<Postgres>
+) -> Result<Vec<BadgeHolding>, Error> {
+    let (limit, offset) = page(limit, offset);
+
+    sqlx
+        ::query_as::<_, BadgeHolding>(
+            "SELECT * FROM badge_holdings WHERE badge_address = $1 ORDER BY number LIMIT $2 OFFSET $3"
+        )
+        .bind(badge_address)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })
+}
+
+// Gets the indexed badges held by a wallet, newest first
+pub async fn fetch_wallet_holdings(
+    wallet_address: &str,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    db: &Pool<Postgres>
+) -> Result<Vec<BadgeHolding>, Error> {
+    let (limit, offset) = page(limit, offset);
+
+    sqlx
+        ::query_as::<_, BadgeHolding>(
+            "SELECT * FROM badge_holdings WHERE holder = $1 ORDER BY minted_at DESC LIMIT $2 OFFSET $3"
+        )
+        .bind(wallet_address)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })
+}
+
+// Checks ownership against the chain rather than the index
+pub async fn verify_ownership(
+    app_state: &AppState,
+    wallet: &Pubkey,
+    badge: &Pubkey
+) -> Result<OwnershipProof, Error> {
+    let (slot, accounts) = app_state.rpc.get_filtered_program_accounts(
+        &app_state.program_id,
+        receipt_filters(Some(badge), Some(wallet))
+    ).await?;
+
+    let receipts: Vec<String> = accounts
+        .iter()
+        .filter(|(_, account)| {
+            matches!(
+                PebbleAccount::decode(&account.data),
+                Ok(PebbleAccount::MintReceipt(receipt)) if receipt.holder == *wallet && receipt.badge == *badge
+            )
+        })
+        .map(|(address, _)| address.to_string())
+        .collect();
+
+    Ok(OwnershipProof {
+        wallet: wallet.to_string(),
+        badge: badge.to_string(),
+        owns: !receipts.is_empty(),
+        receipts,
+        slot,
+    })
+}
+
+// Upserts every mint receipt, then drops rows whose receipt no longer exists
+async fn index_holdings(app_state: &AppState) -> Result<(), Error> {
+    let (slot, accounts) = app_state.rpc.get_filtered_program_accounts(
+        &app_state.program_id,
+        receipt_filters(None, None)
+    ).await?;
+
+    let mut session = app_state.db.begin().await.map_err(|_| Error::InternalServerError)?;
+
+    for (address, account) in accounts {
+        let receipt = match PebbleAccount::decode(&account.data) {
+            Ok(PebbleAccount::MintReceipt(receipt)) => receipt,
+            Ok(_) => {
+                continue;
+            }
+            Err(err) => {
+                println!("Failed to decode mint receipt {}: {}", address, err);
+                app_state.metrics.incr("program_account_decode_failures");
+                continue;
+            }
+        };
+
+        sqlx
+            ::query(
+                "INSERT INTO badge_holdings (receipt_address, badge_address, holder, number, price_paid, minted_at, slot) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7) \
+                 ON CONFLICT (receipt_address) DO UPDATE SET holder = EXCLUDED.holder, slot = EXCLUDED.slot, \
+                 updated_at = CASE WHEN badge_holdings.holder = EXCLUDED.holder THEN badge_holdings.updated_at ELSE NOW() END \
+                 WHERE badge_holdings.slot <= EXCLUDED.slot"
+            )
+            .bind(address.to_string())
+            .bind(receipt.badge.to_string())
+            .bind(receipt.holder.to_string())
+            .bind(receipt.number as i64)
+            .bind(receipt.price_paid as i64)
+            .bind(receipt.minted_at)
+            .bind(slot as i64)
+            .execute(&mut session).await
+            .map_err(|err| {
+                println!("Database insert failed: {}", err);
+                Error::InternalServerError
+            })?;
+    }
+
+    let removed = sqlx
+        ::query("DELETE FROM badge_holdings WHERE slot < $1")
+        .bind(slot as i64)
+        .execute(&mut session).await
+        .map_err(|err| {
+            println!("Database delete failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    session.commit().await.map_err(|_| Error::InternalServerError)?;
+
+    if removed.rows_affected() > 0 {
+        println!("Removed {} closed mint receipt(s) from the holder index.", removed.rows_affected());
+    }
+    app_state.metrics.set("holdings_indexed_slot", slot);
+
+    Ok(())
+}
+
+// Background task rescanning mint receipts every HOLDINGS_INDEX_INTERVAL_SECS
+pub async fn track_holdings(app_state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(
+        Duration::from_secs(env_or("HOLDINGS_INDEX_INTERVAL_SECS", 30))
+    );
+
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = index_holdings(&app_state).await {
+            println!("Holder indexer failed: {:?}", err);
+        }
+    }
+}
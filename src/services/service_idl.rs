@@ -0,0 +1,708 @@
+use crate::models::model_account::{ account_discriminator, AccountDecodeError, DISCRIMINATOR_LEN };
+use serde::Deserialize;
+use serde_json::{ Map, Value };
+use solana_sdk::pubkey::Pubkey;
+
+// Field layouts of the hand-written borsh structs, checked against the IDL at boot
+const TYPED_LAYOUTS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "BadgeAccount",
+        &[
+            ("owner", "pubkey"),
+            ("supply", "u64"),
+            ("max_supply", "u64"),
+            ("price", "u64"),
+            ("decimals", "u8"),
+            ("name", "string"),
+            ("symbol", "string"),
+            ("uri", "string"),
+            ("created_at", "i64"),
+        ],
+    ),
+    (
+        "BadgeAccountV2",
+        &[
+            ("owner", "pubkey"),
+            ("supply", "u64"),
+            ("max_supply", "u64"),
+            ("price", "u64"),
+            ("decimals", "u8"),
+            ("name", "string"),
+            ("symbol", "string"),
+            ("uri", "string"),
+            ("created_at", "i64"),
+            ("royalty_basis_points", "u16"),
+            ("paused", "bool"),
+        ],
+    ),
+    (
+        "MintReceiptAccount",
+        &[
+            ("badge", "pubkey"),
+            ("holder", "pubkey"),
+            ("number", "u64"),
+            ("price_paid", "u64"),
+            ("minted_at", "i64"),
+        ],
+    ),
+    (
+        "ConfigAccount",
+        &[
+            ("authority", "pubkey"),
+            ("treasury", "pubkey"),
+            ("fee_basis_points", "u16"),
+            ("badge_count", "u64"),
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdlType {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+    String,
+    Bytes,
+    Pubkey,
+    Option(Box<IdlType>),
+    Vec(Box<IdlType>),
+    Array(Box<IdlType>, usize),
+    Defined(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct IdlField {
+    pub name: String,
+    pub ty: IdlType,
+}
+
+#[derive(Debug, Clone)]
+pub enum IdlFields {
+    Named(Vec<IdlField>),
+    Tuple(Vec<IdlType>),
+}
+
+#[derive(Debug, Clone)]
+pub struct IdlVariant {
+    pub name: String,
+    pub fields: Option<IdlFields>,
+}
+
+#[derive(Debug, Clone)]
+pub enum IdlTypeDef {
+    Struct(IdlFields),
+    Enum(Vec<IdlVariant>),
+}
+
+#[derive(Debug, Clone)]
+pub struct IdlAccount {
+    pub name: String,
+    pub discriminator: [u8; DISCRIMINATOR_LEN],
+    pub layout: IdlTypeDef,
+}
+
+// Anchor IDL, accepting both the legacy (<= 0.29) and the 0.30+ spec
+#[derive(Debug, Clone)]
+pub struct Idl {
+    pub accounts: Vec<IdlAccount>,
+    pub types: Vec<(String, IdlTypeDef)>,
+}
+
+#[derive(Deserialize)]
+struct RawIdl {
+    #[serde(default)]
+    accounts: Vec<RawTypeDef>,
+    #[serde(default)]
+    types: Vec<RawTypeDef>,
+}
+
+#[derive(Deserialize)]
+struct RawTypeDef {
+    name: String,
+    #[serde(default)]
+    discriminator: Option<Vec<u8>>,
+    #[serde(rename = "type", default)]
+    ty: Option<Value>,
+}
+
+fn parse_type(value: &Value) -> Result<IdlType, String> {
+    if let Some(name) = value.as_str() {
+        return match name {
+            "bool" => Ok(IdlType::Bool),
+            "u8" => Ok(IdlType::U8),
+            "u16" => Ok(IdlType::U16),
+            "u32" => Ok(IdlType::U32),
+            "u64" => Ok(IdlType::U64),
+            "u128" => Ok(IdlType::U128),
+            "i8" => Ok(IdlType::I8),
+            "i16" => Ok(IdlType::I16),
+            "i32" => Ok(IdlType::I32),
+            "i64" => Ok(IdlType::I64),
+            "i128" => Ok(IdlType::I128),
+            "f32" => Ok(IdlType::F32),
+            "f64" => Ok(IdlType::F64),
+            "string" => Ok(IdlType::String),
+            "bytes" => Ok(IdlType::Bytes),
+            "publicKey" | "pubkey" => Ok(IdlType::Pubkey),
+            other => Err(format!("unsupported IDL type \"{}\"", other)),
+        };
+    }
+
+    let object = value.as_object().ok_or_else(|| format!("invalid IDL type {}", value))?;
+
+    if let Some(inner) = object.get("option") {
+        return Ok(IdlType::Option(Box::new(parse_type(inner)?)));
+    }
+
+    if let Some(inner) = object.get("vec") {
+        return Ok(IdlType::Vec(Box::new(parse_type(inner)?)));
+    }
+
+    if let Some(array) = object.get("array").and_then(|a| a.as_array()) {
+        if let [inner, Value::Number(len)] = array.as_slice() {
+            let len = len.as_u64().ok_or_else(|| format!("invalid array length {}", len))?;
+            return Ok(IdlType::Array(Box::new(parse_type(inner)?), len as usize));
+        }
+    }
+
+    if let Some(defined) = object.get("defined") {
+        let name = defined
+            .as_str()
+            .or_else(|| defined.get("name").and_then(|n| n.as_str()))
+            .ok_or_else(|| format!("invalid defined type {}", defined))?;
+        return Ok(IdlType::Defined(name.to_string()));
+    }
+
+    Err(format!("unsupported IDL type {}", value))
+}
+
+fn parse_fields(value: &Value) -> Result<IdlFields, String> {
+    let fields = value.as_array().ok_or_else(|| format!("invalid IDL fields {}", value))?;
+
+    if fields.iter().all(|f| f.get("name").is_some()) {
+        let named = fields
+            .iter()
+            .map(|f| {
+                Ok(IdlField {
+                    name: f["name"].as_str().unwrap_or_default().to_string(),
+                    ty: parse_type(&f["type"])?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        return Ok(IdlFields::Named(named));
+    }
+
+    Ok(IdlFields::Tuple(fields.iter().map(parse_type).collect::<Result<Vec<_>, String>>()?))
+}
+
+fn parse_type_def(value: &Value) -> Result<IdlTypeDef, String> {
+    match value.get("kind").and_then(|k| k.as_str()) {
+        Some("struct") => {
+            let fields = value.get("fields").cloned().unwrap_or(Value::Array(vec![]));
+            Ok(IdlTypeDef::Struct(parse_fields(&fields)?))
+        }
+        Some("enum") => {
+            let variants = value
+                .get("variants")
+                .and_then(|v| v.as_array())
+                .ok_or_else(|| "enum without variants".to_string())?
+                .iter()
+                .map(|v| {
+                    Ok(IdlVariant {
+                        name: v["name"].as_str().unwrap_or_default().to_string(),
+                        fields: match v.get("fields") {
+                            Some(fields) => Some(parse_fields(fields)?),
+                            None => None,
+                        },
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok(IdlTypeDef::Enum(variants))
+        }
+        other => Err(format!("unsupported type kind {:?}", other)),
+    }
+}
+
+// Field names are snake_case in 0.30 IDLs and camelCase in legacy ones
+fn normalize_name(name: &str) -> String {
+    name.replace('_', "").to_lowercase()
+}
+
+fn type_name(ty: &IdlType) -> String {
+    match ty {
+        IdlType::Bool => "bool".to_string(),
+        IdlType::U8 => "u8".to_string(),
+        IdlType::U16 => "u16".to_string(),
+        IdlType::U32 => "u32".to_string(),
+        IdlType::U64 => "u64".to_string(),
+        IdlType::U128 => "u128".to_string(),
+        IdlType::I8 => "i8".to_string(),
+        IdlType::I16 => "i16".to_string(),
+        IdlType::I32 => "i32".to_string(),
+        IdlType::I64 => "i64".to_string(),
+        IdlType::I128 => "i128".to_string(),
+        IdlType::F32 => "f32".to_string(),
+        IdlType::F64 => "f64".to_string(),
+        IdlType::String => "string".to_string(),
+        IdlType::Bytes => "bytes".to_string(),
+        IdlType::Pubkey => "pubkey".to_string(),
+        IdlType::Option(inner) => format!("option<{}>", type_name(inner)),
+        IdlType::Vec(inner) => format!("vec<{}>", type_name(inner)),
+        IdlType::Array(inner, len) => format!("[{}; {}]", type_name(inner), len),
+        IdlType::Defined(name) => name.clone(),
+    }
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], String> {
+    if data.len() < len {
+        return Err(format!("unexpected end of data, wanted {} bytes", len));
+    }
+
+    let (head, tail) = data.split_at(len);
+    *data = tail;
+    Ok(head)
+}
+
+fn take_array<const N: usize>(data: &mut &[u8]) -> Result<[u8; N], String> {
+    let mut bytes = [0u8; N];
+    bytes.copy_from_slice(take(data, N)?);
+    Ok(bytes)
+}
+
+fn take_len(data: &mut &[u8]) -> Result<usize, String> {
+    Ok(u32::from_le_bytes(take_array::<4>(data)?) as usize)
+}
+
+impl Idl {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let raw: RawIdl = serde_json::from_str(json).map_err(|err| err.to_string())?;
+
+        let types = raw.types
+            .iter()
+            .filter_map(|t| t.ty.as_ref().map(|ty| (t.name.clone(), ty)))
+            .map(|(name, ty)| Ok((name, parse_type_def(ty)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut accounts = vec![];
+
+        for account in raw.accounts {
+            // Legacy IDLs inline the layout, 0.30+ ones reference an entry in `types`
+            let layout = match &account.ty {
+                Some(ty) => parse_type_def(ty)?,
+                None =>
+                    types
+                        .iter()
+                        .find(|(name, _)| name == &account.name)
+                        .map(|(_, def)| def.clone())
+                        .ok_or_else(|| format!("no layout for account {}", account.name))?,
+            };
+
+            let discriminator = match account.discriminator {
+                Some(bytes) if bytes.len() == DISCRIMINATOR_LEN => {
+                    let mut discriminator = [0u8; DISCRIMINATOR_LEN];
+                    discriminator.copy_from_slice(&bytes);
+                    discriminator
+                }
+                _ => account_discriminator(&account.name),
+            };
+
+            accounts.push(IdlAccount { name: account.name, discriminator, layout });
+        }
+
+        Ok(Idl { accounts, types })
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        Idl::parse(&json)
+    }
+
+    // Checks every typed fast-path layout against the IDL, returning every mismatch. An
+    // account the IDL leaves out is a mismatch too, since its layout cannot be checked.
+    pub fn validate_typed_layouts(&self) -> Vec<String> {
+        let mut mismatches = vec![];
+
+        for (account_name, expected) in TYPED_LAYOUTS {
+            let account = match self.accounts.iter().find(|a| a.name == *account_name) {
+                Some(account) => account,
+                None => {
+                    mismatches.push(format!("{}: missing from the IDL", account_name));
+                    continue;
+                }
+            };
+
+            if account.discriminator != account_discriminator(account_name) {
+                mismatches.push(format!("{}: discriminator differs", account_name));
+            }
+
+            let fields = match &account.layout {
+                IdlTypeDef::Struct(IdlFields::Named(fields)) => fields,
+                _ => {
+                    mismatches.push(format!("{}: not a struct with named fields", account_name));
+                    continue;
+                }
+            };
+
+            if fields.len() != expected.len() {
+                mismatches.push(
+                    format!(
+                        "{}: IDL has {} fields, typed layout has {}",
+                        account_name,
+                        fields.len(),
+                        expected.len()
+                    )
+                );
+            }
+
+            for (field, (name, ty)) in fields.iter().zip(expected.iter()) {
+                if normalize_name(&field.name) != normalize_name(name) || type_name(&field.ty) != *ty {
+                    mismatches.push(
+                        format!(
+                            "{}: expected field {}: {}, IDL has {}: {}",
+                            account_name,
+                            name,
+                            ty,
+                            field.name,
+                            type_name(&field.ty)
+                        )
+                    );
+                }
+            }
+        }
+
+        mismatches
+    }
+
+    // Decodes an account into JSON, returning the IDL account name alongside it
+    pub fn decode_account(&self, data: &[u8]) -> Result<(String, Value), AccountDecodeError> {
+        if data.len() < DISCRIMINATOR_LEN {
+            return Err(AccountDecodeError::TooShort(data.len()));
+        }
+
+        let (discriminator, mut body) = data.split_at(DISCRIMINATOR_LEN);
+
+        let account = self.accounts
+            .iter()
+            .find(|a| a.discriminator == discriminator)
+            .ok_or_else(|| {
+                let mut unknown = [0u8; DISCRIMINATOR_LEN];
+                unknown.copy_from_slice(discriminator);
+                AccountDecodeError::UnknownDiscriminator(unknown)
+            })?;
+
+        let value = self
+            .decode_type_def(&account.layout, &mut body)
+            .map_err(|err| {
+                AccountDecodeError::InvalidData("IDL account", format!("{}: {}", account.name, err))
+            })?;
+
+        Ok((account.name.clone(), value))
+    }
+
+    // Fewest bytes a value of the type can take, so untrusted lengths can be checked up front
+    fn min_size(&self, ty: &IdlType) -> usize {
+        match ty {
+            IdlType::Bool | IdlType::U8 | IdlType::I8 | IdlType::Option(_) => 1,
+            IdlType::U16 | IdlType::I16 => 2,
+            IdlType::U32 | IdlType::I32 | IdlType::F32 => 4,
+            IdlType::String | IdlType::Bytes | IdlType::Vec(_) => 4,
+            IdlType::U64 | IdlType::I64 | IdlType::F64 => 8,
+            IdlType::U128 | IdlType::I128 => 16,
+            IdlType::Pubkey => 32,
+            IdlType::Array(inner, len) => self.min_size(inner).saturating_mul(*len),
+            IdlType::Defined(name) => {
+                match self.types.iter().find(|(type_name, _)| type_name == name) {
+                    Some((_, IdlTypeDef::Struct(IdlFields::Named(fields)))) => {
+                        fields.iter().map(|field| self.min_size(&field.ty)).sum()
+                    }
+                    Some((_, IdlTypeDef::Struct(IdlFields::Tuple(types)))) => {
+                        types.iter().map(|ty| self.min_size(ty)).sum()
+                    }
+                    Some((_, IdlTypeDef::Enum(_))) | None => 1,
+                }
+            }
+        }
+    }
+
+    // Decodes `len` values of one type, refusing lengths the remaining data cannot hold
+    fn decode_sequence(&self, inner: &IdlType, len: usize, data: &mut &[u8]) -> Result<Value, String> {
+        let min_size = self.min_size(inner).max(1);
+        if len.saturating_mul(min_size) > data.len() {
+            return Err(
+                format!("{} elements of {} do not fit in {} bytes", len, type_name(inner), data.len())
+            );
+        }
+
+        Ok(
+            Value::Array(
+                (0..len).map(|_| self.decode_type(inner, data)).collect::<Result<Vec<_>, String>>()?
+            )
+        )
+    }
+
+    fn decode_type_def(&self, def: &IdlTypeDef, data: &mut &[u8]) -> Result<Value, String> {
+        match def {
+            IdlTypeDef::Struct(fields) => self.decode_fields(fields, data),
+            IdlTypeDef::Enum(variants) => {
+                let index = take_array::<1>(data)?[0] as usize;
+                let variant = variants
+                    .get(index)
+                    .ok_or_else(|| format!("enum variant {} out of range", index))?;
+
+                match &variant.fields {
+                    None => Ok(Value::String(variant.name.clone())),
+                    Some(fields) => {
+                        let mut object = Map::new();
+                        object.insert(variant.name.clone(), self.decode_fields(fields, data)?);
+                        Ok(Value::Object(object))
+                    }
+                }
+            }
+        }
+    }
+
+    fn decode_fields(&self, fields: &IdlFields, data: &mut &[u8]) -> Result<Value, String> {
+        match fields {
+            IdlFields::Named(fields) => {
+                let mut object = Map::new();
+                for field in fields {
+                    object.insert(field.name.clone(), self.decode_type(&field.ty, data)?);
+                }
+                Ok(Value::Object(object))
+            }
+            IdlFields::Tuple(types) => {
+                let values = types
+                    .iter()
+                    .map(|ty| self.decode_type(ty, data))
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok(Value::Array(values))
+            }
+        }
+    }
+
+    fn decode_type(&self, ty: &IdlType, data: &mut &[u8]) -> Result<Value, String> {
+        Ok(match ty {
+            IdlType::Bool => Value::Bool(take_array::<1>(data)?[0] != 0),
+            IdlType::U8 => Value::from(take_array::<1>(data)?[0]),
+            IdlType::U16 => Value::from(u16::from_le_bytes(take_array(data)?)),
+            IdlType::U32 => Value::from(u32::from_le_bytes(take_array(data)?)),
+            IdlType::U64 => Value::from(u64::from_le_bytes(take_array(data)?)),
+            IdlType::U128 => Value::String(u128::from_le_bytes(take_array(data)?).to_string()),
+            IdlType::I8 => Value::from(i8::from_le_bytes(take_array(data)?)),
+            IdlType::I16 => Value::from(i16::from_le_bytes(take_array(data)?)),
+            IdlType::I32 => Value::from(i32::from_le_bytes(take_array(data)?)),
+            IdlType::I64 => Value::from(i64::from_le_bytes(take_array(data)?)),
+            IdlType::I128 => Value::String(i128::from_le_bytes(take_array(data)?).to_string()),
+            IdlType::F32 => Value::from(f32::from_le_bytes(take_array(data)?)),
+            IdlType::F64 => Value::from(f64::from_le_bytes(take_array(data)?)),
+            IdlType::String => {
+                let len = take_len(data)?;
+                let bytes = take(data, len)?;
+                Value::String(String::from_utf8(bytes.to_vec()).map_err(|err| err.to_string())?)
+            }
+            IdlType::Bytes => {
+                let len = take_len(data)?;
+                Value::from(take(data, len)?.to_vec())
+            }
+            IdlType::Pubkey => Value::String(Pubkey::new_from_array(take_array(data)?).to_string()),
+            IdlType::Option(inner) => {
+                match take_array::<1>(data)?[0] {
+                    0 => Value::Null,
+                    _ => self.decode_type(inner, data)?,
+                }
+            }
+            IdlType::Vec(inner) => {
+                let len = take_len(data)?;
+                self.decode_sequence(inner, len, data)?
+            }
+            IdlType::Array(inner, len) => self.decode_sequence(inner, *len, data)?,
+            IdlType::Defined(name) => {
+                let def = self.types
+                    .iter()
+                    .find(|(type_name, _)| type_name == name)
+                    .map(|(_, def)| def)
+                    .ok_or_else(|| format!("unknown defined type {}", name))?;
+                self.decode_type_def(def, data)?
+            }
+        })
+    }
+}
+
+// Loads the IDL named by IDL_PATH, exiting if it is unreadable or disagrees with the typed layouts
+pub fn load_from_env() -> Option<Idl> {
+    let path = match std::env::var("IDL_PATH") {
+        Ok(path) => path,
+        Err(_) => {
+            println!("IDL_PATH not set, generic account decoding is disabled.");
+            return None;
+        }
+    };
+
+    let idl = match Idl::load(&path) {
+        Ok(idl) => idl,
+        Err(err) => {
+            println!("Failed to load the program IDL: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let mismatches = idl.validate_typed_layouts();
+    if !mismatches.is_empty() {
+        for mismatch in &mismatches {
+            println!("IDL mismatch: {}", mismatch);
+        }
+        println!("Typed account layouts do not match the IDL at {}.", path);
+        std::process::exit(1);
+    }
+
+    println!("Loaded program IDL with {} account types.", idl.accounts.len());
+    Some(idl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGACY_IDL: &str = include_str!("../../fixtures/idl/pebble_legacy.json");
+    const V030_IDL: &str = include_str!("../../fixtures/idl/pebble_v030.json");
+
+    fn ledger_account(entries: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = account_discriminator("Ledger").to_vec();
+        data.extend_from_slice(&[7u8; 32]);
+        data.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for entry in entries {
+            data.extend_from_slice(entry);
+        }
+        data
+    }
+
+    // Entry { kind, amount, memo, tags } with `kind` already encoded
+    fn entry(kind: &[u8], amount: u64, memo: Option<&str>) -> Vec<u8> {
+        let mut data = kind.to_vec();
+        data.extend_from_slice(&amount.to_le_bytes());
+        match memo {
+            None => data.push(0),
+            Some(memo) => {
+                data.push(1);
+                data.extend_from_slice(&(memo.len() as u32).to_le_bytes());
+                data.extend_from_slice(memo.as_bytes());
+            }
+        }
+        data.extend_from_slice(&[1, 2]);
+        data
+    }
+
+    #[test]
+    fn both_idl_formats_match_the_typed_layouts() {
+        for json in [LEGACY_IDL, V030_IDL] {
+            let idl = Idl::parse(json).expect("fixture IDL should parse");
+
+            assert_eq!(idl.accounts.len(), 5);
+            assert_eq!(idl.validate_typed_layouts(), Vec::<String>::new());
+            assert_eq!(
+                idl.accounts[0].discriminator,
+                account_discriminator("BadgeAccount")
+            );
+        }
+    }
+
+    #[test]
+    fn layout_mismatches_are_reported() {
+        let json = V030_IDL.replacen("\"royalty_basis_points\"", "\"royalty\"", 1);
+        let mismatches = Idl::parse(&json).unwrap().validate_typed_layouts();
+
+        assert_eq!(mismatches.len(), 1);
+        assert!(mismatches[0].starts_with("BadgeAccountV2: expected field royalty_basis_points"));
+    }
+
+    #[test]
+    fn missing_accounts_are_reported() {
+        let mut idl = Idl::parse(LEGACY_IDL).unwrap();
+        idl.accounts.retain(|account| account.name != "ConfigAccount");
+
+        assert_eq!(idl.validate_typed_layouts(), vec!["ConfigAccount: missing from the IDL"]);
+    }
+
+    #[test]
+    fn decodes_the_badge_fixture_with_either_format() {
+        let data = include_bytes!("../../fixtures/accounts/badge_account_v2.bin");
+
+        for json in [LEGACY_IDL, V030_IDL] {
+            let (name, value) = Idl::parse(json).unwrap().decode_account(data).unwrap();
+
+            assert_eq!(name, "BadgeAccountV2");
+            assert_eq!(value["supply"], 12);
+            assert_eq!(value["name"], "Pebble Genesis");
+            assert_eq!(value["paused"], true);
+        }
+    }
+
+    #[test]
+    fn decodes_defined_types_enums_options_and_arrays() {
+        let mut transfer = vec![1];
+        transfer.extend_from_slice(&[9u8; 32]);
+        let mut burn = vec![2];
+        burn.extend_from_slice(&5u64.to_le_bytes());
+
+        let data = ledger_account(
+            &[entry(&[0], 10, None), entry(&transfer, 20, Some("gift")), entry(&burn, 30, None)]
+        );
+
+        for json in [LEGACY_IDL, V030_IDL] {
+            let (name, value) = Idl::parse(json).unwrap().decode_account(&data).unwrap();
+            let entries = value["entries"].as_array().unwrap();
+
+            assert_eq!(name, "Ledger");
+            assert_eq!(entries.len(), 3);
+            assert_eq!(entries[0]["kind"], "Mint");
+            assert_eq!(entries[0]["memo"], Value::Null);
+            assert_eq!(entries[0]["tags"], serde_json::json!([1, 2]));
+            assert_eq!(
+                entries[1]["kind"]["Transfer"]["to"],
+                Pubkey::new_from_array([9u8; 32]).to_string()
+            );
+            assert_eq!(entries[1]["memo"], "gift");
+            assert_eq!(entries[2]["kind"]["Burn"], serde_json::json!([5]));
+            assert_eq!(entries[2]["amount"], 30);
+        }
+    }
+
+    #[test]
+    fn rejects_lengths_the_data_cannot_hold() {
+        let idl = Idl::parse(V030_IDL).unwrap();
+
+        let mut data = ledger_account(&[]);
+        let len_at = data.len() - 4;
+        data[len_at..].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        match idl.decode_account(&data) {
+            Err(AccountDecodeError::InvalidData(_, message)) => {
+                assert!(message.contains("do not fit"), "{}", message);
+            }
+            other => panic!("expected a length error, got {:?}", other.map(|(name, _)| name)),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_discriminators_and_enum_variants() {
+        let idl = Idl::parse(LEGACY_IDL).unwrap();
+
+        assert!(
+            matches!(
+                idl.decode_account(&[0u8; 16]),
+                Err(AccountDecodeError::UnknownDiscriminator(_))
+            )
+        );
+        assert!(idl.decode_account(&ledger_account(&[entry(&[3], 1, None)])).is_err());
+    }
+}
@@ -0,0 +1,419 @@
+use argon2::{ Algorithm, Argon2, Params, Version };
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use chacha20poly1305::aead::{ rand_core::RngCore, Aead, OsRng, Payload };
+use chacha20poly1305::{ KeyInit, XChaCha20Poly1305, XNonce };
+use serde::{ Deserialize, Serialize };
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{ read_keypair_file, Keypair, Signature, Signer };
+use solana_sdk::transaction::Transaction;
+use std::collections::HashMap;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{ Path, PathBuf };
+use std::sync::{ Arc, RwLock };
+use zeroize::Zeroizing;
+
+const KEYSTORE_VERSION: u32 = 1;
+const ARGON2_M_COST: u32 = 64 * 1024;
+const ARGON2_T_COST: u32 = 3;
+const ARGON2_P_COST: u32 = 1;
+
+// Signs with a key whose secret bytes never leave the keystore
+pub trait TransactionSigner: Send + Sync {
+    fn pubkey(&self) -> Pubkey;
+
+    fn sign_message(&self, message: &[u8]) -> Signature;
+
+    // Fills in this key's signature slot, leaving other signers untouched
+    fn sign_transaction(&self, transaction: &mut Transaction) -> Result<(), String> {
+        let positions = transaction
+            .get_signing_keypair_positions(&[self.pubkey()])
+            .map_err(|err| err.to_string())?;
+        let position = positions
+            .first()
+            .copied()
+            .flatten()
+            .ok_or_else(|| format!("{} is not a signer of the transaction", self.pubkey()))?;
+
+        transaction.signatures[position] = self.sign_message(&transaction.message_data());
+        Ok(())
+    }
+}
+
+// A decrypted key. The dalek secret inside `Keypair` is zeroized when dropped.
+pub struct KeystoreKey {
+    pub name: String,
+    pub created_at: i64,
+    keypair: Keypair,
+}
+
+impl TransactionSigner for KeystoreKey {
+    fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Signature {
+        self.keypair.sign_message(message)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct EncryptedKeyFile {
+    version: u32,
+    name: String,
+    pubkey: String,
+    created_at: i64,
+    kdf: String,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    salt: String,
+    cipher: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32
+) -> Result<Zeroizing<[u8; 32]>, String> {
+    let params = Params::new(m_cost, t_cost, p_cost, Some(32)).map_err(|err| err.to_string())?;
+    let mut key = Zeroizing::new([0u8; 32]);
+
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key[..])
+        .map_err(|err| err.to_string())?;
+
+    Ok(key)
+}
+
+fn decode_field(value: &str, field: &str) -> Result<Vec<u8>, String> {
+    STANDARD.decode(value).map_err(|_| format!("invalid {} encoding", field))
+}
+
+// Encrypts a keypair with an Argon2id-derived key and XChaCha20-Poly1305.
+// The public key is bound as associated data so files cannot be swapped around.
+fn encrypt_keypair(
+    name: &str,
+    keypair: &Keypair,
+    created_at: i64,
+    passphrase: &str
+) -> Result<String, String> {
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 24];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let key = derive_key(passphrase, &salt, ARGON2_M_COST, ARGON2_T_COST, ARGON2_P_COST)?;
+    let cipher = XChaCha20Poly1305::new_from_slice(&key[..]).map_err(|err| err.to_string())?;
+    let pubkey = keypair.pubkey().to_string();
+    let secret = Zeroizing::new(keypair.to_bytes().to_vec());
+
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg: &secret, aad: pubkey.as_bytes() })
+        .map_err(|_| "encryption failed".to_string())?;
+
+    let file = EncryptedKeyFile {
+        version: KEYSTORE_VERSION,
+        name: name.to_string(),
+        pubkey,
+        created_at,
+        kdf: "argon2id".to_string(),
+        m_cost: ARGON2_M_COST,
+        t_cost: ARGON2_T_COST,
+        p_cost: ARGON2_P_COST,
+        salt: STANDARD.encode(salt),
+        cipher: "xchacha20poly1305".to_string(),
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    };
+
+    serde_json::to_string_pretty(&file).map_err(|err| err.to_string())
+}
+
+fn decrypt_key_file(json: &str, passphrase: &str) -> Result<KeystoreKey, String> {
+    let file: EncryptedKeyFile = serde_json::from_str(json).map_err(|err| err.to_string())?;
+
+    if file.version != KEYSTORE_VERSION || file.kdf != "argon2id" || file.cipher != "xchacha20poly1305" {
+        return Err(format!("unsupported keystore format for {}", file.name));
+    }
+
+    let salt = decode_field(&file.salt, "salt")?;
+    let nonce = decode_field(&file.nonce, "nonce")?;
+    let ciphertext = decode_field(&file.ciphertext, "ciphertext")?;
+
+    if nonce.len() != 24 {
+        return Err("invalid nonce length".to_string());
+    }
+
+    let key = derive_key(passphrase, &salt, file.m_cost, file.t_cost, file.p_cost)?;
+    let cipher = XChaCha20Poly1305::new_from_slice(&key[..]).map_err(|err| err.to_string())?;
+
+    let secret = Zeroizing::new(
+        cipher
+            .decrypt(XNonce::from_slice(&nonce), Payload {
+                msg: &ciphertext,
+                aad: file.pubkey.as_bytes(),
+            })
+            .map_err(|_| format!("wrong passphrase or corrupted key file for {}", file.name))?
+    );
+
+    let keypair = Keypair::from_bytes(&secret).map_err(|err| err.to_string())?;
+
+    if keypair.pubkey().to_string() != file.pubkey {
+        return Err(format!("public key mismatch for {}", file.name));
+    }
+
+    Ok(KeystoreKey { name: file.name, created_at: file.created_at, keypair })
+}
+
+// Named signing keys loaded from passphrase-encrypted files in KEYSTORE_DIR.
+// Each name keeps every version, newest first; the newest one is active.
+pub struct Keystore {
+    dir: PathBuf,
+    passphrase: Zeroizing<String>,
+    keys: RwLock<HashMap<String, Vec<Arc<KeystoreKey>>>>,
+}
+
+impl Keystore {
+    pub fn open(dir: &Path, passphrase: Zeroizing<String>) -> Result<Keystore, String> {
+        let mut keys: HashMap<String, Vec<Arc<KeystoreKey>>> = HashMap::new();
+
+        if dir.exists() {
+            let entries = std::fs::read_dir(dir).map_err(|err| err.to_string())?;
+
+            for entry in entries {
+                let path = entry.map_err(|err| err.to_string())?.path();
+                if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                    continue;
+                }
+
+                let json = Zeroizing::new(
+                    std::fs::read_to_string(&path).map_err(|err| err.to_string())?
+                );
+                let key = decrypt_key_file(&json, &passphrase).map_err(|err|
+                    format!("{}: {}", path.display(), err)
+                )?;
+                keys.entry(key.name.clone()).or_default().push(Arc::new(key));
+            }
+        }
+
+        for versions in keys.values_mut() {
+            versions.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        }
+
+        Ok(Keystore { dir: dir.to_path_buf(), passphrase, keys: RwLock::new(keys) })
+    }
+
+    // Opens KEYSTORE_DIR with KEYSTORE_PASSPHRASE, or an empty keystore when no passphrase is set
+    pub fn from_env() -> Keystore {
+        let dir = PathBuf::from(std::env::var("KEYSTORE_DIR").unwrap_or("./keystore".to_string()));
+
+        let passphrase = match std::env::var("KEYSTORE_PASSPHRASE") {
+            Ok(passphrase) => Zeroizing::new(passphrase),
+            Err(_) => {
+                println!("KEYSTORE_PASSPHRASE not set, server-side signing is disabled.");
+                return Keystore {
+                    dir,
+                    passphrase: Zeroizing::new(String::new()),
+                    keys: RwLock::new(HashMap::new()),
+                };
+            }
+        };
+
+        match Keystore::open(&dir, passphrase) {
+            Ok(keystore) => {
+                println!("Loaded {} signing key(s) from the keystore.", keystore.names().len());
+                keystore
+            }
+            Err(err) => {
+                println!("Failed to open the keystore: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.keys.read().unwrap().keys().cloned().collect()
+    }
+
+    // The active version of a named key
+    pub fn signer(&self, name: &str) -> Option<Arc<dyn TransactionSigner>> {
+        let keys = self.keys.read().unwrap();
+        let key = keys.get(name)?.first()?.clone();
+        Some(key as Arc<dyn TransactionSigner>)
+    }
+
+    // Every version of a named key, newest first, e.g. to verify signatures made before a rotation
+    pub fn signers(&self, name: &str) -> Vec<Arc<dyn TransactionSigner>> {
+        self.keys
+            .read()
+            .unwrap()
+            .get(name)
+            .map(|versions| {
+                versions
+                    .iter()
+                    .map(|key| key.clone() as Arc<dyn TransactionSigner>)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn store(&self, name: &str, keypair: Keypair) -> Result<Pubkey, String> {
+        if self.passphrase.is_empty() {
+            return Err("KEYSTORE_PASSPHRASE must be set".to_string());
+        }
+
+        // Versions are ordered by `created_at`, so a new one always sorts after the current
+        // active version, even when both are written within the same second
+        let latest = self.keys
+            .read()
+            .unwrap()
+            .get(name)
+            .and_then(|versions| versions.first().map(|key| key.created_at));
+        let created_at = latest.map_or(chrono::Utc::now().timestamp(), |latest| {
+            chrono::Utc::now().timestamp().max(latest + 1)
+        });
+
+        let json = encrypt_keypair(name, &keypair, created_at, &self.passphrase)?;
+
+        // `create_new` refuses to overwrite a key file rather than silently losing a key, and
+        // only the server's user may read it
+        let pubkey = keypair.pubkey();
+        std::fs::create_dir_all(&self.dir).map_err(|err| err.to_string())?;
+        let path = self.dir.join(format!("{}.{}.{}.json", name, created_at, pubkey));
+        std::fs::OpenOptions
+            ::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .and_then(|mut key_file| key_file.write_all(json.as_bytes()))
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+
+        let key = Arc::new(KeystoreKey {
+            name: name.to_string(),
+            created_at,
+            keypair,
+        });
+        self.keys.write().unwrap().entry(name.to_string()).or_default().insert(0, key);
+
+        Ok(pubkey)
+    }
+
+    // Encrypts an existing Solana keypair file into the keystore as the active version of `name`
+    pub fn import(&self, name: &str, keypair_path: &str) -> Result<Pubkey, String> {
+        let keypair = read_keypair_file(keypair_path).map_err(|err| err.to_string())?;
+        self.store(name, keypair)
+    }
+
+    // Generates a new active version of `name`; previous versions stay loaded
+    pub fn rotate(&self, name: &str) -> Result<Pubkey, String> {
+        self.store(name, Keypair::new())
+    }
+}
+
+// `resty keystore import <name> <keypair.json>` or `resty keystore rotate <name>`
+pub fn run_cli(args: &[String]) -> Result<(), String> {
+    let keystore = Keystore::from_env();
+
+    match args {
+        [command, name, path] if command == "import" => {
+            let pubkey = keystore.import(name, path)?;
+            println!("Imported {} as {}.", pubkey, name);
+        }
+        [command, name] if command == "rotate" => {
+            let pubkey = keystore.rotate(name)?;
+            println!("Rotated {} to {}.", name, pubkey);
+        }
+        _ => {
+            return Err("usage: keystore import <name> <keypair.json> | keystore rotate <name>".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn temp_keystore(passphrase: &str) -> (PathBuf, Keystore) {
+        let dir = std::env::temp_dir().join(format!("keystore-{}", uuid::Uuid::new_v4()));
+        let keystore = Keystore::open(&dir, Zeroizing::new(passphrase.to_string())).unwrap();
+        (dir, keystore)
+    }
+
+    #[test]
+    fn key_files_round_trip() {
+        let keypair = Keypair::new();
+        let json = encrypt_keypair("fee-payer", &keypair, 1_700_000_000, "correct horse").unwrap();
+
+        assert!(!json.contains(&STANDARD.encode(keypair.to_bytes())));
+
+        let key = decrypt_key_file(&json, "correct horse").unwrap();
+        assert_eq!(key.name, "fee-payer");
+        assert_eq!(key.pubkey(), keypair.pubkey());
+        assert_eq!(key.sign_message(b"message"), keypair.sign_message(b"message"));
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let json = encrypt_keypair("fee-payer", &Keypair::new(), 1_700_000_000, "correct horse").unwrap();
+
+        let err = decrypt_key_file(&json, "battery staple").err().unwrap();
+        assert!(err.starts_with("wrong passphrase"), "{}", err);
+    }
+
+    #[test]
+    fn swapped_public_key_is_rejected() {
+        let json = encrypt_keypair("fee-payer", &Keypair::new(), 1_700_000_000, "correct horse").unwrap();
+        let mut file: EncryptedKeyFile = serde_json::from_str(&json).unwrap();
+        file.pubkey = Keypair::new().pubkey().to_string();
+
+        let swapped = serde_json::to_string(&file).unwrap();
+        assert!(decrypt_key_file(&swapped, "correct horse").is_err());
+    }
+
+    #[test]
+    fn rotations_within_a_second_keep_every_version() {
+        let (dir, keystore) = temp_keystore("correct horse");
+
+        let first = keystore.rotate("fee-payer").unwrap();
+        let second = keystore.rotate("fee-payer").unwrap();
+        assert_eq!(keystore.signer("fee-payer").unwrap().pubkey(), second);
+
+        let reopened = Keystore::open(&dir, Zeroizing::new("correct horse".to_string())).unwrap();
+        let versions: Vec<Pubkey> = reopened
+            .signers("fee-payer")
+            .iter()
+            .map(|signer| signer.pubkey())
+            .collect();
+        assert_eq!(versions, vec![second, first]);
+
+        assert!(Keystore::open(&dir, Zeroizing::new("battery staple".to_string())).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn key_files_are_private_to_the_owner() {
+        let (dir, keystore) = temp_keystore("correct horse");
+        let pubkey = keystore.rotate("fee-payer").unwrap();
+
+        let key_file = std::fs
+            ::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.to_string_lossy().ends_with(&format!("{}.json", pubkey)))
+            .expect("the key file was written");
+        let mode = std::fs::metadata(&key_file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,199 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_leaderboard::{ CollectorScore, TrendingBadge };
+use crate::services::service_rpc::env_or;
+use sqlx::{ Postgres, Pool };
+use std::sync::Arc;
+use std::time::Duration;
+
+const MAX_LEADERBOARD_PAGE: i64 = 100;
+// Rolling windows trending is computed over, by name and length in seconds
+const TRENDING_WINDOWS: [(&str, i64); 4] = [
+    ("1h", 3600),
+    ("24h", 86_400),
+    ("7d", 604_800),
+    ("30d", 2_592_000),
+];
+
+// The requested trending window, 24h by default
+fn trending_window(window: Option<&str>) -> Result<&str, Error> {
+    let window = window.unwrap_or("24h");
+
+    if !TRENDING_WINDOWS.iter().any(|(name, _)| *name == window) {
+        return Err(Error::LeaderboardError("Window must be 1h, 24h, 7d or 30d.".to_string()));
+    }
+
+    Ok(window)
+}
+
+// Gets a trending snapshot, best first
+pub async fn fetch_trending(
+    window: Option<&str>,
+    limit: Option<i64>,
+    db: &Pool<Postgres>
+) -> Result<Vec<TrendingBadge>, Error> {
+    let window = trending_window(window)?;
+
+    sqlx
+        ::query_as::<_, TrendingBadge>(
+            "SELECT t.rank, t.badge_address, s.name, s.symbol, s.image, t.mints, t.previous_mints, \
+             t.unique_minters, t.velocity, t.score, t.computed_at \
+             FROM trending_snapshots t LEFT JOIN badge_search s ON s.badge_address = t.badge_address \
+             WHERE t.period = $1 ORDER BY t.rank, t.badge_address LIMIT $2"
+        )
+        .bind(window)
+        .bind(limit.unwrap_or(20).clamp(1, MAX_LEADERBOARD_PAGE))
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })
+}
+
+// Gets the collector snapshot, best first
+pub async fn fetch_collectors(
+    limit: Option<i64>,
+    offset: Option<i64>,
+    db: &Pool<Postgres>
+) -> Result<Vec<CollectorScore>, Error> {
+    sqlx
+        ::query_as::<_, CollectorScore>(
+            "SELECT rank, holder, user_id, username, badges_held, unique_badges, score, computed_at \
+             FROM collector_snapshots ORDER BY rank, holder LIMIT $1 OFFSET $2"
+        )
+        .bind(limit.unwrap_or(50).clamp(1, MAX_LEADERBOARD_PAGE))
+        .bind(offset.unwrap_or(0).max(0))
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })
+}
+
+// Replaces every snapshot in one transaction from the holdings index, so readers always see a
+// complete set. Runs are serialized across instances, so two cannot insert the same rows.
+//
+// Trending score: unique minters per hour, boosted by the square root of the growth over the
+// previous window. Collector score: each badge held adds ln(1 + collectors / its holders), so
+// badges few collectors have count for more.
+async fn compute_leaderboards(app_state: &AppState) -> Result<(), Error> {
+    let now = chrono::Utc::now().timestamp();
+    let mut session = app_state.db.begin().await.map_err(|_| Error::InternalServerError)?;
+
+    sqlx
+        ::query("SELECT pg_advisory_xact_lock(hashtext('leaderboards'))")
+        .execute(&mut session).await
+        .map_err(|_| Error::InternalServerError)?;
+
+    sqlx
+        ::query("DELETE FROM trending_snapshots")
+        .execute(&mut session).await
+        .map_err(|err| {
+            println!("Database delete failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    for (name, seconds) in TRENDING_WINDOWS {
+        sqlx
+            ::query(
+                "INSERT INTO trending_snapshots (period, badge_address, mints, previous_mints, unique_minters, velocity, score, rank) \
+                 SELECT $1, badge_address, mints, previous_mints, unique_minters, velocity, score, \
+                 RANK() OVER (ORDER BY score DESC, mints DESC) \
+                 FROM (SELECT badge_address, mints, previous_mints, unique_minters, \
+                 mints::FLOAT8 / $4 AS velocity, \
+                 unique_minters::FLOAT8 / $4 * SQRT((mints + 1)::FLOAT8 / (previous_mints + 1)) AS score \
+                 FROM (SELECT badge_address, \
+                 COUNT(*) FILTER (WHERE minted_at >= $2 - $3) AS mints, \
+                 COUNT(*) FILTER (WHERE minted_at < $2 - $3) AS previous_mints, \
+                 COUNT(DISTINCT holder) FILTER (WHERE minted_at >= $2 - $3) AS unique_minters \
+                 FROM badge_holdings WHERE minted_at >= $2 - 2 * $3 GROUP BY badge_address) AS counts \
+                 WHERE mints > 0) AS scored"
+            )
+            .bind(name)
+            .bind(now)
+            .bind(seconds)
+            .bind((seconds as f64) / 3600.0)
+            .execute(&mut session).await
+            .map_err(|err| {
+                println!("Database insert failed: {}", err);
+                Error::InternalServerError
+            })?;
+    }
+
+    sqlx
+        ::query("DELETE FROM collector_snapshots")
+        .execute(&mut session).await
+        .map_err(|err| {
+            println!("Database delete failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    sqlx
+        ::query(
+            "INSERT INTO collector_snapshots (holder, user_id, username, badges_held, unique_badges, score, rank) \
+             SELECT holder, user_id, username, badges_held, unique_badges, score, \
+             RANK() OVER (ORDER BY score DESC, badges_held DESC) \
+             FROM (SELECT h.holder, u.id AS user_id, u.username, COUNT(*) AS badges_held, \
+             COUNT(DISTINCT h.badge_address) AS unique_badges, \
+             SUM(LN(1 + c.total::FLOAT8 / b.holders)) AS score \
+             FROM badge_holdings h \
+             JOIN (SELECT badge_address, COUNT(DISTINCT holder) AS holders FROM badge_holdings \
+             GROUP BY badge_address) AS b ON b.badge_address = h.badge_address \
+             CROSS JOIN (SELECT COUNT(DISTINCT holder) AS total FROM badge_holdings) AS c \
+             LEFT JOIN users u ON u.wallet_address = h.holder \
+             GROUP BY h.holder, u.id, u.username) AS scored"
+        )
+        .execute(&mut session).await
+        .map_err(|err| {
+            println!("Database insert failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    session.commit().await.map_err(|_| Error::InternalServerError)?;
+    app_state.metrics.set("leaderboards_computed_at", now as u64);
+
+    Ok(())
+}
+
+// Background task recomputing leaderboards every LEADERBOARD_INTERVAL_SECS
+pub async fn track_leaderboards(app_state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(
+        Duration::from_secs(env_or("LEADERBOARD_INTERVAL_SECS", 300))
+    );
+
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = compute_leaderboards(&app_state).await {
+            println!("Leaderboard job failed: {:?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_to_the_daily_window() {
+        assert_eq!(trending_window(None).ok(), Some("24h"));
+    }
+
+    #[test]
+    fn accepts_every_trending_window() {
+        for (name, _) in TRENDING_WINDOWS {
+            assert_eq!(trending_window(Some(name)).ok(), Some(name));
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_windows() {
+        for window in ["", "2h", "24H", "1d", " 24h"] {
+            assert!(
+                matches!(trending_window(Some(window)), Err(Error::LeaderboardError(_))),
+                "{} should be rejected",
+                window
+            );
+        }
+    }
+}
@@ -0,0 +1,638 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_badge::Badge;
+use crate::models::model_metadata::{ BadgeMetadata, MetadataRecord };
+use crate::services::service_rpc::env_or;
+use chrono::Utc;
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{ Addrs, Resolve, Resolving };
+use reqwest::header::{ ETAG, IF_NONE_MATCH };
+use reqwest::redirect::Policy;
+use reqwest::{ StatusCode, Url };
+use sqlx::{ Postgres, Pool };
+use std::collections::{ HashMap, HashSet };
+use std::net::{ IpAddr, Ipv4Addr, SocketAddr };
+use std::sync::{ Arc, Mutex };
+use std::time::Duration;
+
+const MAX_REDIRECTS: usize = 3;
+const MAX_NAME_LEN: usize = 32;
+const MAX_SYMBOL_LEN: usize = 10;
+const MAX_ATTRIBUTES: usize = 64;
+
+// Fetches badge metadata over HTTP(S), IPFS and Arweave gateways and caches it in `badge_metadata`
+pub struct MetadataResolver {
+    client: reqwest::Client,
+    ipfs_gateway: String,
+    arweave_gateway: String,
+    max_bytes: usize,
+    refresh_after: chrono::Duration,
+    allow_private_hosts: bool,
+    refreshing: Mutex<HashSet<String>>,
+}
+
+fn gateway(name: &str, default: &str) -> String {
+    let url = std::env::var(name).unwrap_or(default.to_string());
+    if url.ends_with('/') {
+        url
+    } else {
+        format!("{}/", url)
+    }
+}
+
+fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+
+            ip.is_loopback() ||
+                ip.is_private() ||
+                ip.is_link_local() ||
+                // "This network", 0.0.0.0/8
+                first == 0 ||
+                // Carrier-grade NAT range
+                (first == 100 && (second & 0xc0) == 64) ||
+                // Benchmarking range, 198.18.0.0/15
+                (first == 198 && (second & 0xfe) == 18) ||
+                // Reserved range, 240.0.0.0/4, which includes the broadcast address
+                first >= 240
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            // The IPv4 address carried in the last 32 bits
+            let embedded = Ipv4Addr::from(((segments[6] as u32) << 16) | (segments[7] as u32));
+            // The IPv4 address a 6to4 address, 2002:AABB:CCDD::/48, routes to
+            let six_to_four = Ipv4Addr::from(((segments[1] as u32) << 16) | (segments[2] as u32));
+
+            ip.is_loopback() ||
+                ip.is_unspecified() ||
+                ip.to_ipv4_mapped().map_or(false, |ip| is_private_ip(IpAddr::V4(ip))) ||
+                // NAT64, 64:ff9b::/96, translating to the embedded IPv4 address
+                (segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] &&
+                    is_private_ip(IpAddr::V4(embedded))) ||
+                (segments[0] == 0x2002 && is_private_ip(IpAddr::V4(six_to_four))) ||
+                // Unique local and link-local ranges
+                (segments[0] & 0xfe00) == 0xfc00 ||
+                (segments[0] & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+// Literal addresses only; hostnames are checked once resolved, by `PublicResolver`
+pub fn is_private_host(url: &Url) -> bool {
+    let host = url.host_str().unwrap_or_default();
+    match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => is_private_ip(ip),
+        Err(_) => host.is_empty() || host == "localhost" || host.ends_with(".localhost"),
+    }
+}
+
+// System DNS resolution that drops private addresses, so a public hostname cannot point a
+// request at the internal network. Checking at connect time also covers redirects and
+// DNS answers that change after a URL was validated.
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net
+                ::lookup_host((name.as_str(), 0)).await?
+                .filter(|addr| !is_private_ip(addr.ip()))
+                .collect();
+
+            if addrs.is_empty() {
+                return Err(format!("{} does not resolve to a public address", name.as_str()).into());
+            }
+
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok::<Addrs, Box<dyn std::error::Error + Send + Sync>>(addrs)
+        })
+    }
+}
+
+// Follows at most MAX_REDIRECTS redirects, re-checking every hop like the original URL
+fn checked_redirects(allow_private_hosts: bool) -> Policy {
+    Policy::custom(move |attempt| {
+        if attempt.previous().len() > MAX_REDIRECTS {
+            return attempt.error("too many redirects");
+        }
+
+        let url = attempt.url();
+        if url.scheme() != "https" && url.scheme() != "http" {
+            let error = format!("redirected to unsupported scheme {}", url.scheme());
+            return attempt.error(error);
+        }
+
+        if !allow_private_hosts && is_private_host(url) {
+            return attempt.error("redirected to a private host");
+        }
+
+        attempt.follow()
+    })
+}
+
+fn build_client(timeout: Duration, allow_private_hosts: bool) -> reqwest::Result<reqwest::Client> {
+    let builder = reqwest::Client
+        ::builder()
+        .timeout(timeout)
+        .redirect(checked_redirects(allow_private_hosts))
+        .user_agent("pebble-metadata-resolver");
+
+    if allow_private_hosts {
+        builder.build()
+    } else {
+        builder.dns_resolver(Arc::new(PublicResolver)).build()
+    }
+}
+
+// Field limits shared by fetched and uploaded metadata
+pub fn check_fields(metadata: &BadgeMetadata) -> Result<(), String> {
+    if metadata.name.trim().is_empty() || metadata.name.chars().count() > MAX_NAME_LEN {
+        return Err(format!("name must be 1 to {} characters", MAX_NAME_LEN));
+    }
+
+    if metadata.symbol.chars().count() > MAX_SYMBOL_LEN {
+        return Err(format!("symbol must be at most {} characters", MAX_SYMBOL_LEN));
+    }
+
+    if metadata.attributes.len() > MAX_ATTRIBUTES {
+        return Err(format!("at most {} attributes are allowed", MAX_ATTRIBUTES));
+    }
+
+    for attribute in &metadata.attributes {
+        if !attribute.value.is_string() && !attribute.value.is_number() {
+            return Err(format!("attribute {} must be a string or number", attribute.trait_type));
+        }
+    }
+
+    Ok(())
+}
+
+impl MetadataResolver {
+    pub fn from_env() -> MetadataResolver {
+        let allow_private_hosts = env_or("METADATA_ALLOW_PRIVATE_HOSTS", false);
+        let client = build_client(
+            Duration::from_secs(env_or("METADATA_TIMEOUT_SECS", 10)),
+            allow_private_hosts
+        ).unwrap_or_else(|err| {
+            println!("Failed to build the metadata HTTP client: {}", err);
+            std::process::exit(1);
+        });
+
+        MetadataResolver {
+            client,
+            ipfs_gateway: gateway("METADATA_IPFS_GATEWAY", "https://ipfs.io/ipfs/"),
+            arweave_gateway: gateway("METADATA_ARWEAVE_GATEWAY", "https://arweave.net/"),
+            max_bytes: env_or("METADATA_MAX_BYTES", 256 * 1024),
+            refresh_after: chrono::Duration::seconds(env_or("METADATA_REFRESH_SECS", 3600)),
+            allow_private_hosts,
+            refreshing: Mutex::new(HashSet::new()),
+        }
+    }
+
+    // Maps ipfs:// and ar:// URIs onto the configured gateways
+    pub fn resolve_url(&self, uri: &str) -> Result<Url, String> {
+        let url = if let Some(path) = uri.strip_prefix("ipfs://") {
+            format!("{}{}", self.ipfs_gateway, path.trim_start_matches("ipfs/"))
+        } else if let Some(path) = uri.strip_prefix("ar://") {
+            format!("{}{}", self.arweave_gateway, path)
+        } else {
+            uri.to_string()
+        };
+
+        let url = Url::parse(&url).map_err(|err| format!("invalid metadata URI: {}", err))?;
+
+        if url.scheme() != "https" && url.scheme() != "http" {
+            return Err(format!("unsupported metadata URI scheme {}", url.scheme()));
+        }
+
+        if !self.allow_private_hosts && is_private_host(&url) {
+            return Err("metadata URI points at a private host".to_string());
+        }
+
+        Ok(url)
+    }
+
+    // Checks a fetched document against the Metaplex token metadata shape
+    pub fn validate(&self, content: &serde_json::Value) -> Result<BadgeMetadata, String> {
+        if !content.is_object() {
+            return Err("metadata is not a JSON object".to_string());
+        }
+
+        let mut metadata: BadgeMetadata = serde_json
+            ::from_value(content.clone())
+            .map_err(|err| format!("metadata does not match the schema: {}", err))?;
+
+        check_fields(&metadata)?;
+
+        metadata.image = self
+            .resolve_url(&metadata.image)
+            .map_err(|err| format!("image: {}", err))?
+            .to_string();
+
+        if let Some(animation_url) = &metadata.animation_url {
+            let animation_url = self
+                .resolve_url(animation_url)
+                .map_err(|err| format!("animation_url: {}", err))?;
+            metadata.animation_url = Some(animation_url.to_string());
+        }
+
+        Ok(metadata)
+    }
+
+    // Reads the body, refusing documents over METADATA_MAX_BYTES
+    async fn read_body(&self, mut response: reqwest::Response) -> Result<Vec<u8>, String> {
+        if response.content_length().map_or(false, |len| len as usize > self.max_bytes) {
+            return Err("metadata document is too large".to_string());
+        }
+
+        let mut body = vec![];
+        while let Some(chunk) = response.chunk().await.map_err(|err| err.to_string())? {
+            body.extend_from_slice(&chunk);
+            if body.len() > self.max_bytes {
+                return Err("metadata document is too large".to_string());
+            }
+        }
+
+        Ok(body)
+    }
+
+    // Fetches the document, sending the cached ETag so unchanged metadata costs a 304
+    async fn fetch(
+        &self,
+        uri: &str,
+        cached: Option<&MetadataRecord>
+    ) -> Result<Option<(Option<String>, serde_json::Value)>, String> {
+        let url = self.resolve_url(uri)?;
+        let mut request = self.client.get(url);
+
+        if let Some(etag) = cached.and_then(|record| record.etag.as_ref()) {
+            if cached.map_or(false, |record| record.content.is_some()) {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+        }
+
+        let response = request.send().await.map_err(|err| err.to_string())?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+
+        if !response.status().is_success() {
+            return Err(format!("metadata request returned {}", response.status()));
+        }
+
+        let etag = response.headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(|etag| etag.to_string());
+        let body = self.read_body(response).await?;
+        let content: serde_json::Value = serde_json
+            ::from_slice(&body)
+            .map_err(|err| format!("metadata is not valid JSON: {}", err))?;
+
+        self.validate(&content)?;
+
+        Ok(Some((etag, content)))
+    }
+
+    async fn cached(
+        &self,
+        uris: &[String],
+        db: &Pool<Postgres>
+    ) -> Result<Vec<MetadataRecord>, Error> {
+        sqlx
+            ::query_as::<_, MetadataRecord>(
+                "SELECT uri, etag, content, error, fetched_at FROM badge_metadata WHERE uri = ANY($1)"
+            )
+            .bind(uris)
+            .fetch_all(db).await
+            .map_err(|err| {
+                println!("Database query failed: {}", err);
+                Error::InternalServerError
+            })
+    }
+
+    async fn store(
+        &self,
+        uri: &str,
+        etag: Option<String>,
+        content: Option<serde_json::Value>,
+        error: Option<String>,
+        db: &Pool<Postgres>
+    ) -> Result<(), Error> {
+        sqlx
+            ::query(
+                "INSERT INTO badge_metadata (uri, etag, content, error, fetched_at) VALUES ($1, $2, $3, $4, NOW()) \
+                 ON CONFLICT (uri) DO UPDATE SET etag = COALESCE(EXCLUDED.etag, badge_metadata.etag), \
+                 content = COALESCE(EXCLUDED.content, badge_metadata.content), error = EXCLUDED.error, \
+                 fetched_at = NOW(), updated_at = NOW()"
+            )
+            .bind(uri)
+            .bind(etag)
+            .bind(content)
+            .bind(error)
+            .execute(db).await
+            .map_err(|err| {
+                println!("Database insert failed: {}", err);
+                Error::InternalServerError
+            })?;
+
+        Ok(())
+    }
+
+    // Fetches metadata that is missing or older than METADATA_REFRESH_SECS.
+    // A failed refresh keeps serving the last valid document.
+    pub async fn refresh(
+        &self,
+        uri: &str,
+        cached: Option<&MetadataRecord>,
+        db: &Pool<Postgres>
+    ) -> Result<Option<BadgeMetadata>, Error> {
+        let is_fresh = cached.map_or(false, |record| {
+            Utc::now() - record.fetched_at < self.refresh_after
+        });
+
+        let content = if is_fresh {
+            cached.and_then(|record| record.content.clone())
+        } else {
+            match self.fetch(uri, cached).await {
+                Ok(Some((etag, content))) => {
+                    self.store(uri, etag, Some(content.clone()), None, db).await?;
+                    Some(content)
+                }
+                Ok(None) => {
+                    self.store(uri, None, None, None, db).await?;
+                    cached.and_then(|record| record.content.clone())
+                }
+                Err(err) => {
+                    println!("Failed to fetch metadata from {}: {}", uri, err);
+                    self.store(uri, None, None, Some(err), db).await?;
+                    cached.and_then(|record| record.content.clone())
+                }
+            }
+        };
+
+        Ok(content.and_then(|content| self.validate(&content).ok()))
+    }
+
+    // Resolves one URI, fetching it if needed
+    pub async fn resolve(
+        &self,
+        uri: &str,
+        db: &Pool<Postgres>
+    ) -> Result<Option<BadgeMetadata>, Error> {
+        let cached = self.cached(&[uri.to_string()], db).await?;
+        self.refresh(uri, cached.first(), db).await
+    }
+
+    // Attaches cached metadata to many badges at once; anything missing or stale is
+    // returned so the caller can refresh it in the background
+    pub async fn attach_cached(
+        &self,
+        badges: &mut [Badge],
+        db: &Pool<Postgres>
+    ) -> Result<Vec<(String, Option<MetadataRecord>)>, Error> {
+        let uris: Vec<String> = badges
+            .iter()
+            .map(|badge| badge.uri.clone())
+            .collect();
+        let records: HashMap<String, MetadataRecord> = self
+            .cached(&uris, db).await?
+            .into_iter()
+            .map(|record| (record.uri.clone(), record))
+            .collect();
+
+        let mut stale = vec![];
+
+        for badge in badges.iter_mut() {
+            let record = records.get(&badge.uri);
+
+            badge.metadata = record
+                .and_then(|record| record.content.as_ref())
+                .and_then(|content| self.validate(content).ok());
+
+            let is_fresh = record.map_or(false, |record| {
+                Utc::now() - record.fetched_at < self.refresh_after
+            });
+
+            if !is_fresh && !stale.iter().any(|(uri, _)| uri == &badge.uri) {
+                stale.push((badge.uri.clone(), record.cloned()));
+            }
+        }
+
+        Ok(stale)
+    }
+}
+
+// Refreshes stale metadata off the request path, skipping URIs already being fetched
+pub fn refresh_in_background(app_state: Arc<AppState>, stale: Vec<(String, Option<MetadataRecord>)>) {
+    let stale: Vec<(String, Option<MetadataRecord>)> = {
+        let mut refreshing = app_state.metadata.refreshing.lock().unwrap();
+        stale
+            .into_iter()
+            .filter(|(uri, _)| refreshing.insert(uri.clone()))
+            .collect()
+    };
+
+    if stale.is_empty() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        for (uri, record) in stale {
+            if let Err(err) = app_state.metadata.refresh(&uri, record.as_ref(), &app_state.db).await {
+                println!("Failed to refresh metadata for {}: {:?}", uri, err);
+            }
+            app_state.metadata.refreshing.lock().unwrap().remove(&uri);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{ header::{ ETAG, IF_NONE_MATCH }, HeaderMap, StatusCode };
+    use axum::response::{ IntoResponse, Redirect };
+    use axum::{ routing::get, Json, Router };
+    use serde_json::json;
+    use std::str::FromStr;
+
+    const VERSION: &str = "\"v1\"";
+
+    fn resolver(max_bytes: usize) -> MetadataResolver {
+        MetadataResolver {
+            client: build_client(Duration::from_secs(5), true).unwrap(),
+            ipfs_gateway: "https://ipfs.io/ipfs/".to_string(),
+            arweave_gateway: "https://arweave.net/".to_string(),
+            max_bytes,
+            refresh_after: chrono::Duration::seconds(3600),
+            allow_private_hosts: true,
+            refreshing: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn document(description: &str) -> serde_json::Value {
+        json!({
+            "name": "Early Adopter",
+            "symbol": "EARLY",
+            "description": description,
+            "image": "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+            "attributes": [{ "trait_type": "Season", "value": 1 }]
+        })
+    }
+
+    // Local stand-in for a metadata host
+    async fn stand_in() -> SocketAddr {
+        let app = Router::new()
+            .route(
+                "/badge.json",
+                get(|headers: HeaderMap| async move {
+                    if headers.get(IF_NONE_MATCH).map_or(false, |etag| etag == VERSION) {
+                        return StatusCode::NOT_MODIFIED.into_response();
+                    }
+                    ([(ETAG, VERSION)], Json(document("First season"))).into_response()
+                })
+            )
+            .route(
+                "/large.json",
+                get(|| async { Json(document(&"a".repeat(4096))) })
+            )
+            .route(
+                "/wrong-type.json",
+                get(|| async { Json(json!({ "name": 5, "image": "https://example.com/a.png" })) })
+            )
+            .route(
+                "/array.json",
+                get(|| async { Json(json!([document("First season")])) })
+            )
+            .route(
+                "/file-image.json",
+                get(|| async {
+                    Json(json!({ "name": "Early Adopter", "image": "file:///etc/passwd" }))
+                })
+            )
+            .route(
+                "/not-json",
+                get(|| async { "<html></html>" })
+            )
+            .route(
+                "/redirect",
+                get(|| async { Redirect::temporary("/badge.json") })
+            )
+            .route(
+                "/loop",
+                get(|| async { Redirect::temporary("/loop") })
+            );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        address
+    }
+
+    #[tokio::test]
+    async fn unchanged_documents_are_revalidated_with_the_etag() {
+        let address = stand_in().await;
+        let resolver = resolver(64 * 1024);
+        let uri = format!("http://{}/badge.json", address);
+
+        let (etag, content) = resolver.fetch(&uri, None).await.unwrap().expect("a document");
+        assert_eq!(etag.as_deref(), Some(VERSION));
+        assert_eq!(content["name"], "Early Adopter");
+
+        let mut record = MetadataRecord {
+            uri: uri.clone(),
+            etag,
+            content: Some(content),
+            error: None,
+            fetched_at: Utc::now(),
+        };
+        assert!(resolver.fetch(&uri, Some(&record)).await.unwrap().is_none());
+
+        // Without a cached document to fall back on, the ETag is not sent
+        record.content = None;
+        assert!(resolver.fetch(&uri, Some(&record)).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn documents_over_the_size_limit_are_refused() {
+        let address = stand_in().await;
+        let uri = format!("http://{}/large.json", address);
+
+        let err = resolver(1024).fetch(&uri, None).await.unwrap_err();
+        assert_eq!(err, "metadata document is too large");
+        assert!(resolver(64 * 1024).fetch(&uri, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn documents_outside_the_schema_are_rejected() {
+        let address = stand_in().await;
+        let resolver = resolver(64 * 1024);
+        let fetch = |path: &str| {
+            let uri = format!("http://{}{}", address, path);
+            let resolver = &resolver;
+            async move { resolver.fetch(&uri, None).await.unwrap_err() }
+        };
+
+        assert!(fetch("/wrong-type.json").await.starts_with("metadata does not match the schema"));
+        assert_eq!(fetch("/array.json").await, "metadata is not a JSON object");
+        assert!(fetch("/file-image.json").await.starts_with("image: unsupported metadata URI scheme"));
+        assert!(fetch("/not-json").await.starts_with("metadata is not valid JSON"));
+    }
+
+    #[tokio::test]
+    async fn redirects_are_checked_hop_by_hop() {
+        let address = stand_in().await;
+
+        let (etag, _) = resolver(64 * 1024)
+            .fetch(&format!("http://{}/redirect", address), None).await
+            .unwrap()
+            .expect("the redirect is followed");
+        assert_eq!(etag.as_deref(), Some(VERSION));
+
+        assert!(resolver(64 * 1024).fetch(&format!("http://{}/loop", address), None).await.is_err());
+
+        // A public URL redirecting into the private network is refused
+        let client = build_client(Duration::from_secs(5), false).unwrap();
+        assert!(client.get(format!("http://{}/redirect", address)).send().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn private_addresses_are_not_resolved() {
+        let name = Name::from_str("localhost").unwrap();
+        assert!(PublicResolver.resolve(name).await.is_err());
+    }
+
+    #[test]
+    fn private_hosts_are_recognized() {
+        let private = |url: &str| is_private_host(&Url::parse(url).unwrap());
+
+        assert!(private("http://127.0.0.1/a.json"));
+        assert!(private("http://10.1.2.3/a.json"));
+        assert!(private("http://169.254.169.254/latest/meta-data"));
+        assert!(private("http://100.64.0.1/a.json"));
+        assert!(private("http://[::1]/a.json"));
+        assert!(private("http://[::ffff:192.168.0.1]/a.json"));
+        assert!(private("http://[fd00::1]/a.json"));
+        assert!(private("http://0.1.2.3/a.json"));
+        assert!(private("http://198.18.0.1/a.json"));
+        assert!(private("http://198.19.255.254/a.json"));
+        assert!(private("http://240.0.0.1/a.json"));
+        assert!(private("http://255.255.255.255/a.json"));
+        assert!(private("http://[64:ff9b::a9fe:a9fe]/latest/meta-data"));
+        assert!(private("http://[64:ff9b::7f00:1]/a.json"));
+        assert!(private("http://[2002:a00:1::]/a.json"));
+        assert!(private("http://[2002:c0a8:1::1]/a.json"));
+        assert!(private("http://localhost:8080/a.json"));
+        assert!(private("http://metadata.localhost/a.json"));
+
+        assert!(!private("https://arweave.net/abc"));
+        assert!(!private("https://8.8.8.8/a.json"));
+        assert!(!private("https://100.128.0.1/a.json"));
+        assert!(!private("https://[2606:4700::1111]/a.json"));
+        assert!(!private("https://198.20.0.1/a.json"));
+        assert!(!private("https://[64:ff9b::808:808]/a.json"));
+        assert!(!private("https://[2002:808:808::1]/a.json"));
+    }
+}
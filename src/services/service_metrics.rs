@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// In-process counters and gauges, exposed through GET /api/metrics
+#[derive(Default)]
+pub struct Metrics {
+    counters: Mutex<HashMap<String, u64>>,
+}
+
+impl Metrics {
+    pub fn incr(&self, name: &str) {
+        self.incr_by(name, 1);
+    }
+
+    pub fn incr_by(&self, name: &str, value: u64) {
+        let mut counters = self.counters.lock().unwrap();
+        *counters.entry(name.to_string()).or_insert(0) += value;
+    }
+
+    // Records a sample as a running sum and count, e.g. for latencies. The suffixes go on the
+    // metric name, before any labels: `rpc_latency_ms_sum{endpoint="x"}`.
+    pub fn observe(&self, name: &str, value: u64) {
+        let (base, labels) = name.split_at(name.find('{').unwrap_or(name.len()));
+        let mut counters = self.counters.lock().unwrap();
+        *counters.entry(format!("{}_sum{}", base, labels)).or_insert(0) += value;
+        *counters.entry(format!("{}_count{}", base, labels)).or_insert(0) += 1;
+    }
+
+    pub fn set(&self, name: &str, value: u64) {
+        self.counters.lock().unwrap().insert(name.to_string(), value);
+    }
+
+    pub fn snapshot(&self) -> HashMap<String, u64> {
+        self.counters.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn observations_add_a_sum_and_a_count() {
+        let metrics = Metrics::default();
+        metrics.observe("latency_ms", 5);
+        metrics.observe("latency_ms", 7);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot["latency_ms_sum"], 12);
+        assert_eq!(snapshot["latency_ms_count"], 2);
+    }
+
+    #[test]
+    fn observation_suffixes_go_before_the_labels() {
+        let metrics = Metrics::default();
+        metrics.observe("rpc_latency_ms{endpoint=\"x\"}", 5);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot["rpc_latency_ms_sum{endpoint=\"x\"}"], 5);
+        assert_eq!(snapshot["rpc_latency_ms_count{endpoint=\"x\"}"], 1);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::errors::error::Error;
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use borsh::BorshSerialize;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::ed25519_instruction::{ DATA_START, PUBKEY_SERIALIZED_SIZE, SIGNATURE_SERIALIZED_SIZE };
+use solana_sdk::ed25519_program;
+use solana_sdk::hash::{ hash, Hash };
+use solana_sdk::instruction::{ AccountMeta, Instruction };
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::{ system_program, sysvar };
+use solana_sdk::transaction::Transaction;
+
+#[derive(BorshSerialize, Debug)]
+pub struct CreateBadgeArgs {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub price: u64,
+    pub max_supply: u64,
+    pub decimals: u8,
+}
+
+#[derive(BorshSerialize, Debug)]
+pub struct MintBadgeArgs {
+    // The program rejects the mint if the badge price changed after the quote
+    pub expected_price: u64,
+}
+
+#[derive(BorshSerialize, Debug)]
+pub struct ClaimBadgeArgs {
+    // Granted by the voucher. The program charges the badge price instead if that is lower.
+    pub price: u64,
+    pub nonce: [u8; 16],
+    pub expires_at: i64,
+}
+
+// A voucher signed off-chain, ready to be checked by the ed25519 precompile
+#[derive(Debug)]
+pub struct SignedVoucher {
+    pub signer: Pubkey,
+    pub signature: Signature,
+    pub message: Vec<u8>,
+    pub args: ClaimBadgeArgs,
+}
+
+// Anchor instruction discriminator: the first 8 bytes of sha256("global:<name>")
+pub fn instruction_discriminator(name: &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(format!("global:{}", name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+fn instruction_data<T: BorshSerialize>(name: &str, args: &T) -> Result<Vec<u8>, Error> {
+    let mut data = instruction_discriminator(name).to_vec();
+    args.serialize(&mut data).map_err(|_| Error::InternalServerError)?;
+    Ok(data)
+}
+
+// Seeds: ["badge", owner, symbol]
+pub fn badge_pda(program_id: &Pubkey, owner: &Pubkey, symbol: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"badge", owner.as_ref(), symbol.as_bytes()], program_id)
+}
+
+// Seeds: ["config"]
+pub fn config_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config"], program_id)
+}
+
+// Seeds: ["receipt", badge, number as little-endian u64]
+pub fn mint_receipt_pda(program_id: &Pubkey, badge: &Pubkey, number: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"receipt", badge.as_ref(), &number.to_le_bytes()],
+        program_id
+    )
+}
+
+// Seeds: ["voucher", nonce]. Created when a voucher is redeemed, so it cannot be used twice.
+pub fn voucher_pda(program_id: &Pubkey, nonce: &[u8; 16]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"voucher", nonce], program_id)
+}
+
+pub fn create_badge_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    args: &CreateBadgeArgs
+) -> Result<Instruction, Error> {
+    let (badge, _) = badge_pda(program_id, owner, &args.symbol);
+    let (config, _) = config_pda(program_id);
+
+    Ok(
+        Instruction::new_with_bytes(
+            *program_id,
+            &instruction_data("create_badge", args)?,
+            vec![
+                AccountMeta::new(badge, false),
+                AccountMeta::new(*owner, true),
+                AccountMeta::new(config, false),
+                AccountMeta::new_readonly(system_program::id(), false)
+            ]
+        )
+    )
+}
+
+// Mints receipt `number` of `badge` to `buyer`, paying the badge owner
+pub fn mint_badge_instruction(
+    program_id: &Pubkey,
+    badge: &Pubkey,
+    badge_owner: &Pubkey,
+    buyer: &Pubkey,
+    number: u64,
+    args: &MintBadgeArgs
+) -> Result<Instruction, Error> {
+    let (receipt, _) = mint_receipt_pda(program_id, badge, number);
+    let (config, _) = config_pda(program_id);
+
+    Ok(
+        Instruction::new_with_bytes(
+            *program_id,
+            &instruction_data("mint_badge", args)?,
+            vec![
+                AccountMeta::new(*badge, false),
+                AccountMeta::new(receipt, false),
+                AccountMeta::new(*buyer, true),
+                AccountMeta::new(*badge_owner, false),
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new_readonly(system_program::id(), false)
+            ]
+        )
+    )
+}
+
+// Mints receipt `number` of `badge` to `buyer` at a voucher price. The program reads the
+// voucher from the ed25519 verify instruction right before this one through the instructions
+// sysvar, and only accepts it when signed by the config authority.
+pub fn claim_badge_instruction(
+    program_id: &Pubkey,
+    badge: &Pubkey,
+    badge_owner: &Pubkey,
+    buyer: &Pubkey,
+    number: u64,
+    args: &ClaimBadgeArgs
+) -> Result<Instruction, Error> {
+    let (receipt, _) = mint_receipt_pda(program_id, badge, number);
+    let (config, _) = config_pda(program_id);
+    let (voucher, _) = voucher_pda(program_id, &args.nonce);
+
+    Ok(
+        Instruction::new_with_bytes(
+            *program_id,
+            &instruction_data("claim_badge", args)?,
+            vec![
+                AccountMeta::new(*badge, false),
+                AccountMeta::new(receipt, false),
+                AccountMeta::new(*buyer, true),
+                AccountMeta::new(*badge_owner, false),
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new(voucher, false),
+                AccountMeta::new_readonly(sysvar::instructions::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false)
+            ]
+        )
+    )
+}
+
+// The ed25519 check of the voucher followed by the claim it authorizes
+pub fn claim_badge_instructions(
+    program_id: &Pubkey,
+    badge: &Pubkey,
+    badge_owner: &Pubkey,
+    buyer: &Pubkey,
+    number: u64,
+    voucher: &SignedVoucher
+) -> Result<Vec<Instruction>, Error> {
+    Ok(
+        vec![
+            ed25519_verify_instruction(&voucher.signer, &voucher.signature, &voucher.message),
+            claim_badge_instruction(program_id, badge, badge_owner, buyer, number, &voucher.args)?
+        ]
+    )
+}
+
+// Ed25519 precompile instruction checking one signature, with the key, signature and message
+// inline in its own data. Built from an existing signature, so the signing key can stay in
+// the keystore.
+pub fn ed25519_verify_instruction(
+    signer: &Pubkey,
+    signature: &Signature,
+    message: &[u8]
+) -> Instruction {
+    let public_key_offset = DATA_START;
+    let signature_offset = public_key_offset + PUBKEY_SERIALIZED_SIZE;
+    let message_data_offset = signature_offset + SIGNATURE_SERIALIZED_SIZE;
+
+    // One signature, then a padding byte
+    let mut data = vec![1u8, 0];
+
+    // Ed25519SignatureOffsets, u16::MAX pointing every field at this instruction
+    for field in [
+        signature_offset as u16,
+        u16::MAX,
+        public_key_offset as u16,
+        u16::MAX,
+        message_data_offset as u16,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(message);
+
+    Instruction::new_with_bytes(ed25519_program::id(), &data, vec![])
+}
+
+pub fn compute_budget_instructions(unit_limit: u32, unit_price: u64) -> Vec<Instruction> {
+    vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(unit_limit),
+        ComputeBudgetInstruction::set_compute_unit_price(unit_price)
+    ]
+}
+
+// Builds an unsigned transaction for the client's wallet to sign
+pub fn build_transaction(
+    fee_payer: &Pubkey,
+    instructions: &[Instruction],
+    recent_blockhash: Hash
+) -> Transaction {
+    Transaction::new_unsigned(
+        Message::new_with_blockhash(instructions, Some(fee_payer), &recent_blockhash)
+    )
+}
+
+pub fn serialize_transaction(transaction: &Transaction) -> Result<String, Error> {
+    let bytes = bincode::serialize(transaction).map_err(|_| Error::InternalServerError)?;
+    Ok(STANDARD.encode(bytes))
+}
+
+pub fn deserialize_transaction(encoded: &str) -> Result<Transaction, Error> {
+    let bytes = STANDARD.decode(encoded).map_err(|_|
+        Error::SubmitTransactionError("Transaction is not valid base64.".to_string())
+    )?;
+
+    bincode
+        ::deserialize(&bytes)
+        .map_err(|_| Error::SubmitTransactionError("Transaction could not be decoded.".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::ed25519_instruction::verify;
+    use solana_sdk::feature_set::FeatureSet;
+    use solana_sdk::signature::{ Keypair, Signer };
+
+    #[test]
+    fn ed25519_instruction_verifies_the_signed_message() {
+        let keypair = Keypair::new();
+        let message = b"pebble voucher";
+        let instruction = ed25519_verify_instruction(
+            &keypair.pubkey(),
+            &keypair.sign_message(message),
+            message
+        );
+
+        assert_eq!(instruction.program_id, ed25519_program::id());
+        assert!(instruction.accounts.is_empty());
+        assert!(
+            verify(&instruction.data, &[&instruction.data], &FeatureSet::all_enabled()).is_ok()
+        );
+    }
+
+    #[test]
+    fn ed25519_instruction_rejects_another_message() {
+        let keypair = Keypair::new();
+        let instruction = ed25519_verify_instruction(
+            &keypair.pubkey(),
+            &keypair.sign_message(b"pebble voucher"),
+            b"pebble vouches"
+        );
+
+        assert!(
+            verify(&instruction.data, &[&instruction.data], &FeatureSet::all_enabled()).is_err()
+        );
+    }
+}
@@ -0,0 +1,663 @@
+use crate::errors::error::Error;
+use crate::services::service_metrics::Metrics;
+use solana_client::client_error::{ ClientError, ClientErrorKind };
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_custom_error::{
+    JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE,
+    JSON_RPC_SERVER_ERROR_BLOCK_STATUS_NOT_AVAILABLE_YET,
+    JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED,
+    JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+    JSON_RPC_SERVER_ERROR_SLOT_SKIPPED,
+};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::{
+    RpcAccountInfoConfig,
+    RpcProgramAccountsConfig,
+    RpcSendTransactionConfig,
+    RpcSimulateTransactionConfig,
+    RpcTransactionConfig,
+};
+use solana_client::rpc_filter::RpcFilterType;
+use solana_client::rpc_request::{ RpcError, RpcRequest };
+use solana_client::rpc_response::{
+    OptionalContext,
+    RpcConfirmedTransactionStatusWithSignature,
+    RpcKeyedAccount,
+    RpcPrioritizationFee,
+    RpcSimulateTransactionResult,
+};
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta,
+    TransactionStatus,
+    UiTransactionEncoding,
+};
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
+
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+struct EndpointHealth {
+    healthy: bool,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+pub struct RpcEndpoint {
+    pub label: String,
+    pub weight: u32,
+    client: Arc<RpcClient>,
+    health: Mutex<EndpointHealth>,
+}
+
+impl RpcEndpoint {
+    fn is_available(&self, now: Instant) -> bool {
+        let health = self.health.lock().unwrap();
+        let circuit_closed = health.open_until.map_or(true, |until| now >= until);
+        health.healthy && circuit_closed
+    }
+
+    fn record_success(&self) {
+        let mut health = self.health.lock().unwrap();
+        health.consecutive_failures = 0;
+        health.open_until = None;
+    }
+
+    // Opens the circuit once the failure threshold is hit; a failed probe after the
+    // cooldown re-opens it straight away since the failure count is only reset on success
+    fn record_failure(&self, threshold: u32, cooldown: Duration) -> bool {
+        let mut health = self.health.lock().unwrap();
+        health.consecutive_failures += 1;
+
+        if health.consecutive_failures >= threshold {
+            health.open_until = Some(Instant::now() + cooldown);
+            return true;
+        }
+
+        false
+    }
+}
+
+// Pool of RPC endpoints with weighted round-robin, health checks, retries and circuit breaking
+pub struct RpcPool {
+    endpoints: Vec<RpcEndpoint>,
+    schedule: Vec<usize>,
+    cursor: AtomicUsize,
+    commitment: CommitmentConfig,
+    metrics: Arc<Metrics>,
+    max_retries: u32,
+    retry_base: Duration,
+    failure_threshold: u32,
+    cooldown: Duration,
+    max_slot_lag: u64,
+    pub health_interval: Duration,
+}
+
+pub fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    std::env
+        ::var(name)
+        .ok()
+        .and_then(|value| value.parse::<T>().ok())
+        .unwrap_or(default)
+}
+
+// Scheme and host only, so provider API keys in paths or query strings stay out of metrics
+fn endpoint_label(url: &str) -> String {
+    let without_query = url.split('?').next().unwrap_or(url);
+
+    match without_query.split_once("://") {
+        Some((scheme, rest)) => format!("{}://{}", scheme, rest.split('/').next().unwrap_or(rest)),
+        None => without_query.to_string(),
+    }
+}
+
+// Endpoint indexes repeated by weight, walked round-robin by `candidates`
+fn weighted_schedule(weights: &[u32]) -> Vec<usize> {
+    weights
+        .iter()
+        .enumerate()
+        .flat_map(|(index, weight)| std::iter::repeat(index).take(*weight as usize))
+        .collect()
+}
+
+// Exponential backoff before retry `attempt` (1-based), capped so a large RPC_MAX_RETRIES
+// neither overflows nor stalls a request for minutes
+fn retry_delay(base: Duration, attempt: u32) -> Duration {
+    let factor = (2u32).checked_pow(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+    base.saturating_mul(factor).min(MAX_RETRY_DELAY)
+}
+
+fn is_transient(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(err) => {
+            err.is_timeout() ||
+                err.is_connect() ||
+                err.status().map_or(true, |status| {
+                    status.is_server_error() || status.as_u16() == 429
+                })
+        }
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(_)) => true,
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) =>
+            matches!(
+                *code,
+                JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE |
+                    JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY |
+                    JSON_RPC_SERVER_ERROR_SLOT_SKIPPED |
+                    JSON_RPC_SERVER_ERROR_BLOCK_STATUS_NOT_AVAILABLE_YET |
+                    JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED
+            ),
+        _ => false,
+    }
+}
+
+impl RpcPool {
+    // Builds the pool from RPC_URLS (comma separated, falling back to RPC_URL) and RPC_WEIGHTS
+    pub fn connect(metrics: Arc<Metrics>) -> RpcPool {
+        let urls: Vec<String> = std::env
+            ::var("RPC_URLS")
+            .or_else(|_| std::env::var("RPC_URL"))
+            .expect("RPC_URLS or RPC_URL must be set")
+            .split(',')
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect();
+
+        if urls.is_empty() {
+            println!("No RPC endpoints configured.");
+            std::process::exit(1);
+        }
+
+        let weights: Vec<u32> = std::env
+            ::var("RPC_WEIGHTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|weight| weight.trim().parse::<u32>().unwrap_or(1).max(1))
+            .collect();
+
+        let commitment = match std::env::var("RPC_COMMITMENT") {
+            Ok(commitment) =>
+                CommitmentConfig::from_str(&commitment).unwrap_or_else(|_| {
+                    println!("Invalid RPC_COMMITMENT: {}", commitment);
+                    std::process::exit(1);
+                }),
+            Err(_) => CommitmentConfig::confirmed(),
+        };
+
+        let timeout = Duration::from_secs(env_or("RPC_TIMEOUT_SECS", 30));
+
+        let endpoints: Vec<RpcEndpoint> = urls
+            .into_iter()
+            .enumerate()
+            .map(|(index, url)| RpcEndpoint {
+                label: endpoint_label(&url),
+                weight: weights.get(index).copied().unwrap_or(1),
+                client: Arc::new(
+                    RpcClient::new_with_timeout_and_commitment(url, timeout, commitment)
+                ),
+                health: Mutex::new(EndpointHealth {
+                    healthy: true,
+                    consecutive_failures: 0,
+                    open_until: None,
+                }),
+            })
+            .collect();
+
+        let schedule = weighted_schedule(
+            &endpoints
+                .iter()
+                .map(|endpoint| endpoint.weight)
+                .collect::<Vec<u32>>()
+        );
+
+        println!("Using {} RPC endpoint(s).", endpoints.len());
+
+        RpcPool {
+            endpoints,
+            schedule,
+            cursor: AtomicUsize::new(0),
+            commitment,
+            metrics,
+            max_retries: env_or("RPC_MAX_RETRIES", 3),
+            retry_base: Duration::from_millis(env_or("RPC_RETRY_BASE_MS", 200)),
+            failure_threshold: env_or("RPC_CIRCUIT_FAILURES", 5),
+            cooldown: Duration::from_secs(env_or("RPC_CIRCUIT_COOLDOWN_SECS", 30)),
+            max_slot_lag: env_or("RPC_MAX_SLOT_LAG", 50),
+            health_interval: Duration::from_secs(env_or("RPC_HEALTH_INTERVAL_SECS", 15)),
+        }
+    }
+
+    pub fn commitment(&self) -> CommitmentConfig {
+        self.commitment
+    }
+
+    // Historical queries reject `processed`, so those fall back to `confirmed`
+    fn history_commitment(&self) -> CommitmentConfig {
+        if self.commitment.is_at_least_confirmed() {
+            self.commitment
+        } else {
+            CommitmentConfig::confirmed()
+        }
+    }
+
+    // Distinct endpoints in weighted round-robin order, skipping unhealthy or open circuits.
+    // When nothing is available every endpoint is returned so requests still get a chance.
+    fn candidates(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let start = self.cursor.fetch_add(1, Ordering::Relaxed);
+        let mut ordered: Vec<usize> = vec![];
+
+        for offset in 0..self.schedule.len() {
+            let index = self.schedule[(start + offset) % self.schedule.len()];
+            if !ordered.contains(&index) {
+                ordered.push(index);
+            }
+        }
+
+        let available: Vec<usize> = ordered
+            .iter()
+            .copied()
+            .filter(|index| self.endpoints[*index].is_available(now))
+            .collect();
+
+        if available.is_empty() {
+            ordered
+        } else {
+            available
+        }
+    }
+
+    // Runs an RPC call against the pool, failing over between endpoints and
+    // retrying transient errors with exponential backoff
+    pub async fn call<T, F, Fut>(&self, method: &str, request: F) -> Result<T, Error>
+        where F: Fn(Arc<RpcClient>) -> Fut, Fut: Future<Output = Result<T, ClientError>>
+    {
+        let mut attempt = 0;
+
+        loop {
+            let mut last_error = None;
+
+            for index in self.candidates() {
+                let endpoint = &self.endpoints[index];
+                let started = Instant::now();
+                let result = request(endpoint.client.clone()).await;
+                let elapsed = started.elapsed().as_millis() as u64;
+
+                self.metrics.observe(
+                    &format!("rpc_latency_ms{{endpoint=\"{}\"}}", endpoint.label),
+                    elapsed
+                );
+
+                match result {
+                    Ok(value) => {
+                        endpoint.record_success();
+                        return Ok(value);
+                    }
+                    Err(err) => {
+                        self.metrics.incr(
+                            &format!("rpc_errors_total{{endpoint=\"{}\"}}", endpoint.label)
+                        );
+
+                        // The endpoint answered, the request itself is bad
+                        if !is_transient(&err) {
+                            endpoint.record_success();
+                            return Err(Error::RpcError(format!("{} failed: {}", method, err)));
+                        }
+
+                        println!("RPC {} failed on {}: {}", method, endpoint.label, err);
+
+                        if endpoint.record_failure(self.failure_threshold, self.cooldown) {
+                            self.metrics.incr(
+                                &format!("rpc_circuit_opened_total{{endpoint=\"{}\"}}", endpoint.label)
+                            );
+                        }
+
+                        last_error = Some(err);
+                    }
+                }
+            }
+
+            attempt += 1;
+            if attempt > self.max_retries {
+                return Err(
+                    Error::RpcError(
+                        format!(
+                            "{} failed on every RPC endpoint: {}",
+                            method,
+                            last_error.map(|err| err.to_string()).unwrap_or_default()
+                        )
+                    )
+                );
+            }
+
+            tokio::time::sleep(retry_delay(self.retry_base, attempt)).await;
+        }
+    }
+
+    pub async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey
+    ) -> Result<Vec<(Pubkey, Account)>, Error> {
+        let program_id = *program_id;
+        self.call("getProgramAccounts", move |client| async move {
+            client.get_program_accounts(&program_id).await
+        }).await
+    }
+
+    // Program accounts matching `filters`, along with the slot the node read them at
+    pub async fn get_filtered_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>
+    ) -> Result<(u64, Vec<(Pubkey, Account)>), Error> {
+        let program_id = *program_id;
+        let commitment = self.commitment;
+        let (slot, keyed_accounts) = self.call("getProgramAccounts", move |client| {
+            let config = RpcProgramAccountsConfig {
+                filters: Some(filters.clone()),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(commitment),
+                    ..Default::default()
+                },
+                with_context: Some(true),
+                ..Default::default()
+            };
+            async move {
+                let response = client.send::<OptionalContext<Vec<RpcKeyedAccount>>>(
+                    RpcRequest::GetProgramAccounts,
+                    serde_json::json!([program_id.to_string(), config])
+                ).await?;
+
+                match response {
+                    OptionalContext::Context(response) =>
+                        Ok((response.context.slot, response.value)),
+                    OptionalContext::NoContext(value) =>
+                        Ok((client.get_slot_with_commitment(commitment).await?, value)),
+                }
+            }
+        }).await?;
+
+        let accounts = keyed_accounts
+            .into_iter()
+            .filter_map(|keyed| {
+                let pubkey = Pubkey::from_str(&keyed.pubkey).ok()?;
+                let account = keyed.account.decode::<Account>()?;
+                Some((pubkey, account))
+            })
+            .collect();
+
+        Ok((slot, accounts))
+    }
+
+    pub async fn get_account(&self, pubkey: &Pubkey) -> Result<Option<Account>, Error> {
+        let pubkey = *pubkey;
+        let commitment = self.commitment;
+        self.call("getAccountInfo", move |client| async move {
+            client.get_account_with_commitment(&pubkey, commitment).await
+        }).await.map(|response| response.value)
+    }
+
+    // Returns the blockhash and the last block height it stays valid for
+    pub async fn get_latest_blockhash(&self) -> Result<(Hash, u64), Error> {
+        let commitment = self.commitment;
+        self.call("getLatestBlockhash", move |client| async move {
+            client.get_latest_blockhash_with_commitment(commitment).await
+        }).await
+    }
+
+    pub async fn get_fee_for_message(&self, message: &Message) -> Result<u64, Error> {
+        let message = message.clone();
+        self.call("getFeeForMessage", move |client| {
+            let message = message.clone();
+            async move { client.get_fee_for_message(&message).await }
+        }).await
+    }
+
+    // Per-slot minimum priority fees paid by transactions that wrote to all of `accounts`
+    pub async fn get_recent_prioritization_fees(
+        &self,
+        accounts: &[Pubkey]
+    ) -> Result<Vec<RpcPrioritizationFee>, Error> {
+        let accounts = accounts.to_vec();
+        self.call("getRecentPrioritizationFees", move |client| {
+            let accounts = accounts.clone();
+            async move { client.get_recent_prioritization_fees(&accounts).await }
+        }).await
+    }
+
+    // Simulates without signature checks, callers are expected to verify signatures themselves
+    pub async fn simulate_transaction(
+        &self,
+        transaction: &Transaction
+    ) -> Result<RpcSimulateTransactionResult, Error> {
+        let transaction = transaction.clone();
+        let commitment = self.commitment;
+        self.call("simulateTransaction", move |client| {
+            let transaction = transaction.clone();
+            async move {
+                client.simulate_transaction_with_config(&transaction, RpcSimulateTransactionConfig {
+                    sig_verify: false,
+                    commitment: Some(commitment),
+                    ..Default::default()
+                }).await
+            }
+        }).await.map(|response| response.value)
+    }
+
+    // Sends an already simulated transaction, so preflight is skipped
+    pub async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, Error> {
+        let transaction = transaction.clone();
+        self.call("sendTransaction", move |client| {
+            let transaction = transaction.clone();
+            async move {
+                client.send_transaction_with_config(&transaction, RpcSendTransactionConfig {
+                    skip_preflight: true,
+                    ..Default::default()
+                }).await
+            }
+        }).await
+    }
+
+    pub async fn get_signature_statuses(
+        &self,
+        signatures: &[Signature]
+    ) -> Result<Vec<Option<TransactionStatus>>, Error> {
+        let signatures = signatures.to_vec();
+        self.call("getSignatureStatuses", move |client| {
+            let signatures = signatures.clone();
+            async move { client.get_signature_statuses(&signatures).await }
+        }).await.map(|response| response.value)
+    }
+
+    // Also searches the ledger history, for transactions past the recent status cache
+    pub async fn get_signature_statuses_with_history(
+        &self,
+        signatures: &[Signature]
+    ) -> Result<Vec<Option<TransactionStatus>>, Error> {
+        let signatures = signatures.to_vec();
+        self.call("getSignatureStatuses", move |client| {
+            let signatures = signatures.clone();
+            async move { client.get_signature_statuses_with_history(&signatures).await }
+        }).await.map(|response| response.value)
+    }
+
+    // Signatures touching `address`, newest first, between `before` and `until`
+    pub async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<Signature>,
+        until: Option<Signature>,
+        limit: usize
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, Error> {
+        let address = *address;
+        let commitment = self.history_commitment();
+        self.call("getSignaturesForAddress", move |client| async move {
+            client.get_signatures_for_address_with_config(
+                &address,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: Some(limit),
+                    commitment: Some(commitment),
+                }
+            ).await
+        }).await
+    }
+
+    pub async fn get_transaction(
+        &self,
+        signature: &Signature
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta, Error> {
+        let signature = *signature;
+        let commitment = self.history_commitment();
+        self.call("getTransaction", move |client| async move {
+            client.get_transaction_with_config(&signature, RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(commitment),
+                max_supported_transaction_version: Some(0),
+            }).await
+        }).await
+    }
+
+    pub async fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool, Error> {
+        let blockhash = *blockhash;
+        let commitment = self.commitment;
+        self.call("isBlockhashValid", move |client| async move {
+            client.is_blockhash_valid(&blockhash, commitment).await
+        }).await
+    }
+
+    // Polls getHealth and getSlot on every endpoint, marking ones that are down or lagging
+    pub async fn check_health(&self) {
+        let mut slots = vec![];
+
+        for endpoint in &self.endpoints {
+            let healthy = endpoint.client.get_health().await.is_ok();
+            let slot = endpoint.client.get_slot().await.ok();
+            slots.push((healthy, slot));
+        }
+
+        let highest = slots
+            .iter()
+            .filter_map(|(_, slot)| *slot)
+            .max()
+            .unwrap_or(0);
+
+        for (endpoint, (healthy, slot)) in self.endpoints.iter().zip(slots) {
+            let lag = slot.map_or(u64::MAX, |slot| highest.saturating_sub(slot));
+            let healthy = healthy && lag <= self.max_slot_lag;
+
+            let mut health = endpoint.health.lock().unwrap();
+            if health.healthy != healthy {
+                println!(
+                    "RPC endpoint {} is now {} (slot lag {})",
+                    endpoint.label,
+                    if healthy { "healthy" } else { "unhealthy" },
+                    lag
+                );
+            }
+            health.healthy = healthy;
+
+            self.metrics.set(
+                &format!("rpc_healthy{{endpoint=\"{}\"}}", endpoint.label),
+                healthy as u64
+            );
+            self.metrics.set(
+                &format!("rpc_slot_lag{{endpoint=\"{}\"}}", endpoint.label),
+                lag.min(highest)
+            );
+        }
+    }
+}
+
+// Reads the Pebble program id from PROGRAM_ID
+pub fn program_id() -> Pubkey {
+    let program_id = std::env::var("PROGRAM_ID").expect("PROGRAM_ID must be set");
+
+    match Pubkey::from_str(&program_id) {
+        Ok(program_id) => program_id,
+        Err(err) => {
+            println!("Invalid PROGRAM_ID {}: {:?}", program_id, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_client::rpc_request::RpcResponseErrorData;
+
+    fn response_error(code: i64) -> ClientError {
+        ClientError::from(RpcError::RpcResponseError {
+            code,
+            message: "error".to_string(),
+            data: RpcResponseErrorData::Empty,
+        })
+    }
+
+    #[test]
+    fn schedule_repeats_endpoints_by_weight() {
+        assert_eq!(weighted_schedule(&[1, 1]), vec![0, 1]);
+        assert_eq!(weighted_schedule(&[3, 1]), vec![0, 0, 0, 1]);
+        assert_eq!(weighted_schedule(&[1, 2, 1]), vec![0, 1, 1, 2]);
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        let base = Duration::from_millis(200);
+
+        assert_eq!(retry_delay(base, 1), Duration::from_millis(200));
+        assert_eq!(retry_delay(base, 2), Duration::from_millis(400));
+        assert_eq!(retry_delay(base, 4), Duration::from_millis(1600));
+        assert_eq!(retry_delay(base, 10), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(base, 40), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(base, u32::MAX), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(Duration::MAX, 2), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn labels_keep_only_scheme_and_host() {
+        assert_eq!(
+            endpoint_label("https://mainnet.helius-rpc.com/?api-key=secret"),
+            "https://mainnet.helius-rpc.com"
+        );
+        assert_eq!(
+            endpoint_label("https://solana-mainnet.g.alchemy.com/v2/secret"),
+            "https://solana-mainnet.g.alchemy.com"
+        );
+        assert_eq!(endpoint_label("http://localhost:8899"), "http://localhost:8899");
+        assert_eq!(endpoint_label("localhost:8899?key=secret"), "localhost:8899");
+    }
+
+    #[test]
+    fn io_and_node_errors_are_transient() {
+        let io = ClientError::from(
+            std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset")
+        );
+
+        assert!(is_transient(&io));
+        assert!(is_transient(&response_error(JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY)));
+        assert!(is_transient(&response_error(JSON_RPC_SERVER_ERROR_SLOT_SKIPPED)));
+        assert!(is_transient(&response_error(JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED)));
+        assert!(is_transient(&ClientError::from(RpcError::RpcRequestError("busy".to_string()))));
+    }
+
+    #[test]
+    fn request_errors_are_not_transient() {
+        assert!(!is_transient(&response_error(-32602)));
+        assert!(!is_transient(&ClientError::from(RpcError::ForUser("bad input".to_string()))));
+        assert!(!is_transient(&ClientError::from(ClientErrorKind::Custom("custom".to_string()))));
+    }
+}
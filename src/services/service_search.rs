@@ -0,0 +1,332 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_account::{ account_discriminator, PebbleAccount };
+use crate::models::model_search::{
+    BadgeSearchResult,
+    FacetCount,
+    SearchFacets,
+    SearchQuery,
+    SearchResults,
+};
+use crate::models::model_user::User;
+use crate::services::service_metadata::refresh_in_background;
+use crate::services::service_rpc::env_or;
+use solana_client::rpc_filter::{ Memcmp, RpcFilterType };
+use sqlx::{ Postgres, Pool };
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+const MAX_QUERY_LEN: usize = 100;
+const MAX_QUERY_TERMS: usize = 8;
+const MAX_SEARCH_PAGE: i64 = 100;
+const MAX_USER_RESULTS: i64 = 10;
+const MAX_CREATOR_FACETS: i64 = 20;
+const PRICE_BUCKETS: [&str; 4] = ["free", "under_1", "1_to_10", "over_10"];
+const AVAILABILITIES: [&str; 3] = ["available", "sold_out", "paused"];
+
+// Badges matching the tsquery ($1) or, for typo tolerance, trigram-similar to the raw query ($2)
+const MATCHES: &str =
+    "WITH matches AS (SELECT s.*, \
+     ts_rank(s.document, to_tsquery('simple', $1)) + GREATEST(similarity(s.name, $2), \
+     similarity(s.symbol, $2), similarity(COALESCE(s.creator_username, ''), $2)) AS rank, \
+     CASE WHEN s.price = 0 THEN 'free' \
+     WHEN s.price < POWER(10::NUMERIC, s.decimals) THEN 'under_1' \
+     WHEN s.price < 10 * POWER(10::NUMERIC, s.decimals) THEN '1_to_10' \
+     ELSE 'over_10' END AS price_bucket, \
+     CASE WHEN s.supply >= s.max_supply THEN 'sold_out' WHEN s.paused THEN 'paused' \
+     ELSE 'available' END AS availability, \
+     COALESCE(s.creator_username, s.owner) AS creator \
+     FROM badge_search s \
+     WHERE s.document @@ to_tsquery('simple', $1) \
+     OR s.name % $2 OR s.symbol % $2 OR s.creator_username % $2) ";
+
+// Every term of the query as a prefix match, e.g. "gold pass" -> "gold:* & pass:*"
+fn prefix_tsquery(query: &str) -> String {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .take(MAX_QUERY_TERMS)
+        .map(|term| format!("{}:*", term.to_lowercase()))
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
+
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+fn check_facet(
+    value: Option<String>,
+    allowed: &[&str],
+    name: &str
+) -> Result<Option<String>, Error> {
+    match value {
+        Some(value) if !allowed.contains(&value.as_str()) => {
+            Err(Error::SearchError(format!("{} must be one of {}.", name, allowed.join(", "))))
+        }
+        value => Ok(value),
+    }
+}
+
+// Searches indexed badges and usernames. Facet counts cover every match, so a client can
+// show how many results each filter would leave.
+pub async fn search(params: SearchQuery, db: &Pool<Postgres>) -> Result<SearchResults, Error> {
+    let query = params.q.trim();
+
+    if query.chars().count() > MAX_QUERY_LEN {
+        return Err(
+            Error::SearchError(format!("Search must be at most {} characters.", MAX_QUERY_LEN))
+        );
+    }
+
+    let tsquery = prefix_tsquery(query);
+    if tsquery.is_empty() {
+        return Err(Error::SearchError("Search must contain a letter or digit.".to_string()));
+    }
+
+    let price = check_facet(params.price, &PRICE_BUCKETS, "Price")?;
+    let availability = check_facet(params.availability, &AVAILABILITIES, "Availability")?;
+
+    let badges = sqlx
+        ::query_as::<_, BadgeSearchResult>(
+            &format!(
+                "{}SELECT badge_address, owner, creator_id, creator_username, name, symbol, description, \
+                 image, price, decimals, supply, max_supply, paused, price_bucket, availability, rank, \
+                 COUNT(*) OVER () AS total FROM matches \
+                 WHERE ($3::VARCHAR IS NULL OR price_bucket = $3) \
+                 AND ($4::VARCHAR IS NULL OR availability = $4) \
+                 AND ($5::VARCHAR IS NULL OR creator = $5) \
+                 ORDER BY rank DESC, supply DESC, badge_address LIMIT $6 OFFSET $7",
+                MATCHES
+            )
+        )
+        .bind(&tsquery)
+        .bind(query)
+        .bind(price)
+        .bind(availability)
+        .bind(params.creator)
+        .bind(params.limit.unwrap_or(20).clamp(1, MAX_SEARCH_PAGE))
+        .bind(params.offset.unwrap_or(0).max(0))
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    let facet_rows = sqlx
+        ::query_as::<_, (String, String, i64)>(
+            &format!(
+                "{}SELECT 'price', price_bucket, COUNT(*) FROM matches GROUP BY price_bucket \
+                 UNION ALL SELECT 'availability', availability, COUNT(*) FROM matches GROUP BY availability \
+                 UNION ALL (SELECT 'creator', creator, COUNT(*) FROM matches GROUP BY creator \
+                 ORDER BY COUNT(*) DESC LIMIT {})",
+                MATCHES,
+                MAX_CREATOR_FACETS
+            )
+        )
+        .bind(&tsquery)
+        .bind(query)
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    let mut facets = SearchFacets::default();
+    for (facet, value, count) in facet_rows {
+        let counts = match facet.as_str() {
+            "price" => &mut facets.price,
+            "availability" => &mut facets.availability,
+            _ => &mut facets.creator,
+        };
+        counts.push(FacetCount { value, count });
+    }
+
+    let users = sqlx
+        ::query_as::<_, User>(
+            "SELECT id, wallet_address, username FROM users \
+             WHERE username ILIKE $1 || '%' OR username % $2 \
+             ORDER BY similarity(username, $2) DESC, username LIMIT $3"
+        )
+        .bind(escape_like(query))
+        .bind(query)
+        .bind(MAX_USER_RESULTS)
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    Ok(SearchResults {
+        total: badges.first().map_or(0, |badge| badge.total),
+        badges,
+        users,
+        facets,
+    })
+}
+
+// Rebuilds the search rows from every badge account, its cached metadata and its creator's
+// account, then drops badges that were not seen in this scan. Rows are stamped with the run's
+// generation rather than compared by slot, since a scan served by a lagging endpoint after a
+// failover reports an older slot than the rows it should replace.
+async fn index_search(app_state: &Arc<AppState>) -> Result<(), Error> {
+    let generation = Uuid::new_v4();
+    let mut badges = vec![];
+    let mut scan_slot = u64::MAX;
+
+    for account_type in ["BadgeAccount", "BadgeAccountV2"] {
+        let (slot, accounts) = app_state.rpc.get_filtered_program_accounts(
+            &app_state.program_id,
+            vec![
+                RpcFilterType::Memcmp(
+                    Memcmp::new_base58_encoded(0, &account_discriminator(account_type))
+                )
+            ]
+        ).await?;
+        scan_slot = scan_slot.min(slot);
+
+        for (address, account) in accounts {
+            match PebbleAccount::decode(&account.data) {
+                Ok(PebbleAccount::Badge(badge)) => badges.push(badge.into_badge(&address)),
+                Ok(_) => {}
+                Err(err) => {
+                    println!("Failed to decode badge {}: {}", address, err);
+                    app_state.metrics.incr("program_account_decode_failures");
+                }
+            }
+        }
+    }
+
+    let stale = app_state.metadata.attach_cached(&mut badges, &app_state.db).await?;
+    refresh_in_background(app_state.clone(), stale);
+
+    let mut session = app_state.db.begin().await.map_err(|_| Error::InternalServerError)?;
+
+    // One run at a time across instances, so a run cannot drop the rows of another
+    sqlx
+        ::query("SELECT pg_advisory_xact_lock(hashtext('badge_search'))")
+        .execute(&mut session).await
+        .map_err(|_| Error::InternalServerError)?;
+
+    for badge in &badges {
+        let (description, image) = badge.metadata
+            .as_ref()
+            .map(|metadata| (metadata.description.clone(), metadata.image.clone()))
+            .unwrap_or_default();
+
+        // Falls back to what the creator entered when the badge was built through this server
+        sqlx
+            ::query(
+                "INSERT INTO badge_search (badge_address, owner, creator_id, creator_username, name, symbol, description, image, price, decimals, supply, max_supply, paused, slot, generation) \
+                 SELECT $1, $2, u.id, u.username, $3, $4, \
+                 COALESCE(NULLIF($5, ''), (SELECT badge_description FROM badges WHERE badge_address = $1), ''), \
+                 COALESCE(NULLIF($6, ''), (SELECT badge_image FROM badges WHERE badge_address = $1), ''), \
+                 $7, $8, $9, $10, $11, $12, $13 \
+                 FROM (SELECT 1) AS one LEFT JOIN users u ON u.wallet_address = $2 \
+                 ON CONFLICT (badge_address) DO UPDATE SET owner = EXCLUDED.owner, creator_id = EXCLUDED.creator_id, \
+                 creator_username = EXCLUDED.creator_username, name = EXCLUDED.name, symbol = EXCLUDED.symbol, \
+                 description = EXCLUDED.description, image = EXCLUDED.image, price = EXCLUDED.price, \
+                 decimals = EXCLUDED.decimals, supply = EXCLUDED.supply, max_supply = EXCLUDED.max_supply, \
+                 paused = EXCLUDED.paused, slot = EXCLUDED.slot, generation = EXCLUDED.generation, \
+                 updated_at = NOW()"
+            )
+            .bind(&badge.address)
+            .bind(&badge.owner)
+            .bind(&badge.name)
+            .bind(&badge.symbol)
+            .bind(description)
+            .bind(image)
+            .bind(badge.price as i64)
+            .bind(badge.decimals as i16)
+            .bind(badge.supply as i64)
+            .bind(badge.max_supply as i64)
+            .bind(badge.paused)
+            .bind(scan_slot as i64)
+            .bind(generation)
+            .execute(&mut session).await
+            .map_err(|err| {
+                println!("Database insert failed: {}", err);
+                Error::InternalServerError
+            })?;
+    }
+
+    sqlx
+        ::query("DELETE FROM badge_search WHERE generation IS DISTINCT FROM $1")
+        .bind(generation)
+        .execute(&mut session).await
+        .map_err(|err| {
+            println!("Database delete failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    session.commit().await.map_err(|_| Error::InternalServerError)?;
+    app_state.metrics.set("search_indexed_slot", scan_slot);
+
+    Ok(())
+}
+
+// Background task rebuilding the search index every SEARCH_INDEX_INTERVAL_SECS
+pub async fn track_search(app_state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(
+        Duration::from_secs(env_or("SEARCH_INDEX_INTERVAL_SECS", 60))
+    );
+
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = index_search(&app_state).await {
+            println!("Search indexer failed: {:?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turns_every_term_into_a_prefix_match() {
+        assert_eq!(prefix_tsquery("Gold Pass"), "gold:* & pass:*");
+        assert_eq!(prefix_tsquery("  gold--pass!! 2024 "), "gold:* & pass:* & 2024:*");
+    }
+
+    #[test]
+    fn drops_tsquery_operators() {
+        assert_eq!(prefix_tsquery("gold & !pass | (vip):*"), "gold:* & pass:* & vip:*");
+        assert_eq!(prefix_tsquery("'&|!:*()"), "");
+    }
+
+    #[test]
+    fn caps_the_number_of_terms() {
+        let query = (0..20).map(|term| format!("t{}", term)).collect::<Vec<_>>().join(" ");
+
+        assert_eq!(prefix_tsquery(&query).split(" & ").count(), MAX_QUERY_TERMS);
+    }
+
+    #[test]
+    fn escapes_like_wildcards() {
+        assert_eq!(escape_like("gold"), "gold");
+        assert_eq!(escape_like("100%_off"), "100\\%\\_off");
+        assert_eq!(escape_like("a\\b"), "a\\\\b");
+    }
+
+    #[test]
+    fn accepts_known_facets() {
+        assert_eq!(check_facet(None, &PRICE_BUCKETS, "Price").ok(), Some(None));
+        assert_eq!(
+            check_facet(Some("sold_out".to_string()), &AVAILABILITIES, "Availability").ok(),
+            Some(Some("sold_out".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_facets() {
+        assert!(
+            matches!(
+                check_facet(Some("cheap".to_string()), &PRICE_BUCKETS, "Price"),
+                Err(Error::SearchError(message)) if message == "Price must be one of free, under_1, 1_to_10, over_10."
+            )
+        );
+    }
+}
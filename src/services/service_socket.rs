@@ -0,0 +1,297 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_socket::{ RoomPayload, RoomResponse, SocketAuth, SocketSession };
+use crate::services::service_auth::authenticate_socket;
+use crate::services::service_events::{ badge_room, user_room };
+use crate::services::service_rpc::env_or;
+use chrono::Utc;
+use socketioxide::{ extract::{ AckSender, Data, SocketRef, TryData }, SocketIo };
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+const MAX_SOCKET_ROOMS: usize = 50;
+
+// Checks a room a client asked for: any badge's room, or its own user's room once signed in
+fn check_room(session: Option<&SocketSession>, room: &str) -> Result<String, String> {
+    if let Some(address) = room.strip_prefix("badge:") {
+        let address = Pubkey::from_str(address).map_err(|_| "Badge address is invalid.")?;
+        return Ok(badge_room(&address.to_string()));
+    }
+
+    if let Some(id) = room.strip_prefix("user:") {
+        let session = session.ok_or("Sign in to join a user room.")?;
+
+        if Uuid::from_str(id).ok() != Some(session.user.id) {
+            return Err("Only your own user room can be joined.".to_string());
+        }
+
+        return Ok(user_room(&session.user.id));
+    }
+
+    Err("Room must be badge:<address> or user:<id>.".to_string())
+}
+
+fn room_response(result: Result<String, String>) -> RoomResponse {
+    match result {
+        Ok(room) => RoomResponse { ok: true, room: Some(room), error: None },
+        Err(error) => RoomResponse { ok: false, room: None, error: Some(error) },
+    }
+}
+
+// `room:join`, acknowledged with a RoomResponse
+async fn join_room(socket: SocketRef, Data(payload): Data<RoomPayload>, ack: AckSender) {
+    let session = socket.extensions.get::<SocketSession>();
+    let result = check_room(session.as_ref(), payload.room.trim()).and_then(|room| {
+        let joined = socket.rooms().unwrap_or_default();
+
+        if !joined.iter().any(|joined| *joined == room) && joined.len() >= MAX_SOCKET_ROOMS {
+            return Err(format!("A socket may be in at most {} rooms.", MAX_SOCKET_ROOMS));
+        }
+
+        let _ = socket.join(room.clone());
+        Ok(room)
+    });
+
+    let _ = ack.send(room_response(result));
+}
+
+// `room:leave`, acknowledged with a RoomResponse
+async fn leave_room(socket: SocketRef, Data(payload): Data<RoomPayload>, ack: AckSender) {
+    let session = socket.extensions.get::<SocketSession>();
+    let result = check_room(session.as_ref(), payload.room.trim()).map(|room| {
+        let _ = socket.leave(room.clone());
+        room
+    });
+
+    let _ = ack.send(room_response(result));
+}
+
+// Authenticates a new socket. Signed-in sockets keep their session in the socket extensions and
+// join their user's room. Anonymous sockets may only join badge rooms, or are disconnected when
+// SOCKET_REQUIRE_AUTH is set.
+pub async fn on_connect(
+    socket: SocketRef,
+    TryData(auth): TryData<SocketAuth>,
+    app_state: Arc<AppState>
+) {
+    let auth_token = auth.ok().and_then(|auth| auth.token);
+
+    socket.on("room:join", join_room);
+    socket.on("room:leave", leave_room);
+
+    match
+        authenticate_socket(
+            &socket.req_parts().headers,
+            auth_token.as_deref(),
+            &app_state.db
+        ).await
+    {
+        Ok(session) => {
+            println!("Socket connected: {:?} as user {}", socket.id, session.user.id);
+
+            let _ = socket.join(user_room(&session.user.id));
+            let _ = socket.emit("session", &session.user);
+            socket.extensions.insert(session);
+            app_state.metrics.incr("socket_connections_authenticated");
+        }
+        Err(err) => {
+            if env_or("SOCKET_REQUIRE_AUTH", false) {
+                println!("Socket rejected: {:?} ({:?})", socket.id, err);
+                app_state.metrics.incr("socket_connections_rejected");
+                let _ = socket.disconnect();
+                return;
+            }
+
+            println!("Socket connected: {:?} anonymously", socket.id);
+            app_state.metrics.incr("socket_connections_anonymous");
+        }
+    }
+}
+
+fn authenticated_sockets(io: &SocketIo) -> Vec<(SocketRef, SocketSession)> {
+    io.sockets()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|socket| {
+            let session = socket.extensions.get::<SocketSession>()?;
+            Some((socket, session))
+        })
+        .collect()
+}
+
+// Disconnects the sockets opened with a refresh token, once it is logged out
+pub fn disconnect_session(io: &SocketIo, refresh_token: &str) {
+    for (socket, session) in authenticated_sockets(io) {
+        if session.refresh_token.as_deref() == Some(refresh_token) {
+            let _ = socket.disconnect();
+        }
+    }
+}
+
+fn is_revoked(
+    session: &SocketSession,
+    now: i64,
+    live_users: &HashSet<Uuid>,
+    live_tokens: &HashSet<String>
+) -> bool {
+    session.expires_at <= now ||
+        !live_users.contains(&session.user.id) ||
+        session.refresh_token.as_ref().is_some_and(|token| !live_tokens.contains(token))
+}
+
+// Disconnects sockets whose session expired, whose refresh token was revoked or whose user
+// was deleted
+async fn check_socket_sessions(app_state: &AppState) -> Result<(), Error> {
+    let sockets = authenticated_sockets(&app_state.io);
+    if sockets.is_empty() {
+        return Ok(());
+    }
+
+    let tokens: Vec<String> = sockets
+        .iter()
+        .filter_map(|(_, session)| session.refresh_token.clone())
+        .collect();
+    let user_ids: Vec<Uuid> = sockets
+        .iter()
+        .map(|(_, session)| session.user.id)
+        .collect();
+
+    let live_tokens: HashSet<String> = sqlx
+        ::query_as::<_, (String,)>("SELECT token FROM refresh_tokens WHERE token = ANY($1)")
+        .bind(&tokens)
+        .fetch_all(&app_state.db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?
+        .into_iter()
+        .map(|(token,)| token)
+        .collect();
+
+    let live_users: HashSet<Uuid> = sqlx
+        ::query_as::<_, (Uuid,)>("SELECT id FROM users WHERE id = ANY($1)")
+        .bind(&user_ids)
+        .fetch_all(&app_state.db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?
+        .into_iter()
+        .map(|(id,)| id)
+        .collect();
+
+    let now = Utc::now().timestamp();
+    for (socket, session) in sockets {
+        if is_revoked(&session, now, &live_users, &live_tokens) {
+            println!("Socket session ended: {:?} for user {}", socket.id, session.user.id);
+            app_state.metrics.incr("socket_sessions_revoked");
+            let _ = socket.disconnect();
+        }
+    }
+
+    Ok(())
+}
+
+// Background task checking socket sessions every SOCKET_SESSION_CHECK_SECS
+pub async fn track_socket_sessions(app_state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(
+        Duration::from_secs(env_or("SOCKET_SESSION_CHECK_SECS", 60))
+    );
+
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = check_socket_sessions(&app_state).await {
+            println!("Socket session check failed: {:?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::model_user::User;
+
+    fn session(refresh_token: Option<&str>, expires_at: i64) -> SocketSession {
+        SocketSession {
+            user: User {
+                id: Uuid::new_v4(),
+                wallet_address: Pubkey::new_unique().to_string(),
+                username: "alice".to_string(),
+            },
+            refresh_token: refresh_token.map(str::to_string),
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn live_sessions_are_kept() {
+        let with_token = session(Some("refresh"), 200);
+        let without_token = session(None, 200);
+        let live_users = HashSet::from([with_token.user.id, without_token.user.id]);
+        let live_tokens = HashSet::from(["refresh".to_string()]);
+
+        assert!(!is_revoked(&with_token, 100, &live_users, &live_tokens));
+        assert!(!is_revoked(&without_token, 100, &live_users, &HashSet::new()));
+    }
+
+    #[test]
+    fn expired_sessions_are_revoked() {
+        let session = session(None, 100);
+        let live_users = HashSet::from([session.user.id]);
+
+        assert!(is_revoked(&session, 100, &live_users, &HashSet::new()));
+    }
+
+    #[test]
+    fn logged_out_tokens_and_deleted_users_are_revoked() {
+        let session = session(Some("refresh"), 200);
+        let live_users = HashSet::from([session.user.id]);
+        let live_tokens = HashSet::from(["refresh".to_string()]);
+
+        assert!(is_revoked(&session, 100, &live_users, &HashSet::new()));
+        assert!(is_revoked(&session, 100, &HashSet::new(), &live_tokens));
+    }
+
+    #[test]
+    fn anyone_may_join_badge_rooms() {
+        let address = Pubkey::new_unique().to_string();
+        let room = format!("badge:{}", address);
+
+        assert_eq!(check_room(None, &room), Ok(badge_room(&address)));
+        assert_eq!(check_room(Some(&session(None, 200)), &room), Ok(badge_room(&address)));
+        assert!(check_room(None, "badge:not-a-key").is_err());
+    }
+
+    #[test]
+    fn anonymous_sockets_cannot_join_user_rooms() {
+        let room = format!("user:{}", Uuid::new_v4());
+
+        assert_eq!(check_room(None, &room), Err("Sign in to join a user room.".to_string()));
+    }
+
+    #[test]
+    fn users_may_only_join_their_own_room() {
+        let session = session(None, 200);
+
+        assert_eq!(
+            check_room(Some(&session), &format!("user:{}", session.user.id)),
+            Ok(user_room(&session.user.id))
+        );
+        assert_eq!(
+            check_room(Some(&session), &format!("user:{}", Uuid::new_v4())),
+            Err("Only your own user room can be joined.".to_string())
+        );
+        assert!(check_room(Some(&session), "user:me").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_rooms() {
+        assert!(check_room(None, "lobby").is_err());
+        assert!(check_room(None, "").is_err());
+    }
+}
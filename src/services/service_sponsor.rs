@@ -0,0 +1,375 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::services::service_claim::settle_redemptions;
+use crate::services::service_keystore::{ Keystore, TransactionSigner };
+use crate::services::service_rpc::{ env_or, RpcPool };
+use solana_sdk::{ compute_budget, ed25519_program };
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::TransactionStatus;
+use sqlx::{ Postgres, Pool };
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+// getSignatureStatuses accepts at most 256 signatures per call
+const MAX_SETTLE_BATCH: i64 = 256;
+
+// Server fee payer that co-signs mint transactions for users without SOL
+pub struct FeeSponsor {
+    signer: Arc<dyn TransactionSigner>,
+    user_daily_cap: u64,
+    global_daily_cap: u64,
+    user_pending_limit: i64,
+}
+
+impl FeeSponsor {
+    // Uses the keystore key named by FEE_PAYER_KEY; sponsorship is disabled when it is missing
+    pub fn from_keystore(keystore: &Keystore) -> Option<FeeSponsor> {
+        let name = std::env::var("FEE_PAYER_KEY").unwrap_or("fee-payer".to_string());
+
+        let signer = match keystore.signer(&name) {
+            Some(signer) => signer,
+            None => {
+                println!("No \"{}\" key in the keystore, fee sponsorship is disabled.", name);
+                return None;
+            }
+        };
+
+        let cap = |name: &str, default: u64| {
+            std::env
+                ::var(name)
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+
+        println!("Sponsoring mint fees from {}.", signer.pubkey());
+
+        Some(FeeSponsor {
+            signer,
+            user_daily_cap: cap("FEE_PAYER_USER_DAILY_CAP_LAMPORTS", 100_000),
+            global_daily_cap: cap("FEE_PAYER_GLOBAL_DAILY_CAP_LAMPORTS", 10_000_000),
+            user_pending_limit: env_or("FEE_PAYER_USER_PENDING_LIMIT", 2),
+        })
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.signer.pubkey()
+    }
+
+    // Only Pebble program, compute budget and ed25519 precompile (claim voucher) instructions
+    // are sponsored, and none of them may reference the fee payer, so it can never be debited
+    // beyond the fee
+    pub fn check_allowlist(&self, transaction: &Transaction, program_id: &Pubkey) -> Result<(), Error> {
+        let message = &transaction.message;
+
+        if message.account_keys.first() != Some(&self.pubkey()) {
+            return Err(Error::SponsorshipError("Fee payer is not the server.".to_string()));
+        }
+
+        for instruction in &message.instructions {
+            let program = message.account_keys
+                .get(instruction.program_id_index as usize)
+                .ok_or_else(|| Error::SponsorshipError("Instruction is malformed.".to_string()))?;
+
+            if
+                program != program_id &&
+                program != &compute_budget::id() &&
+                program != &ed25519_program::id()
+            {
+                return Err(
+                    Error::SponsorshipError(format!("Program {} is not sponsored.", program))
+                );
+            }
+
+            if instruction.accounts.contains(&0) {
+                return Err(
+                    Error::SponsorshipError("Instructions may not use the fee payer.".to_string())
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    // Checks the caps and reserves the fee until the transaction lands or its blockhash
+    // expires. Reservations count against the daily caps like landed fees, and each user may
+    // hold only FEE_PAYER_USER_PENDING_LIMIT of them, so building transactions that are never
+    // sent cannot drain the budget. An advisory lock serializes concurrent reservations so the
+    // caps cannot be overrun by parallel requests.
+    pub async fn reserve(
+        &self,
+        user_id: Uuid,
+        badge_address: &Pubkey,
+        transaction: &Transaction,
+        lamports: u64,
+        db: &Pool<Postgres>
+    ) -> Result<(), Error> {
+        let mut session = db.begin().await.map_err(|_| Error::InternalServerError)?;
+
+        sqlx
+            ::query("SELECT pg_advisory_xact_lock(hashtext('sponsored_fees'))")
+            .execute(&mut session).await
+            .map_err(|_| Error::InternalServerError)?;
+
+        let (user_spent, global_spent, user_pending) = sqlx
+            ::query_as::<_, (i64, i64, i64)>(
+                "SELECT \
+                 COALESCE(SUM(lamports) FILTER (WHERE user_id = $1), 0)::BIGINT, \
+                 COALESCE(SUM(lamports), 0)::BIGINT, \
+                 COUNT(*) FILTER (WHERE user_id = $1 AND status = 'reserved') \
+                 FROM sponsored_fees WHERE status <> 'released' AND created_at >= date_trunc('day', NOW())"
+            )
+            .bind(user_id)
+            .fetch_one(&mut session).await
+            .map_err(|err| {
+                println!("Database query failed: {}", err);
+                Error::InternalServerError
+            })?;
+
+        if user_pending >= self.user_pending_limit {
+            return Err(
+                Error::SponsorshipError(
+                    "Too many sponsored transactions are waiting to land.".to_string()
+                )
+            );
+        }
+
+        if (user_spent as u64) + lamports > self.user_daily_cap {
+            return Err(Error::SponsorshipError("Daily sponsored fee limit reached.".to_string()));
+        }
+
+        if (global_spent as u64) + lamports > self.global_daily_cap {
+            return Err(Error::SponsorshipError("Sponsorship is exhausted for today.".to_string()));
+        }
+
+        sqlx
+            ::query(
+                "INSERT INTO sponsored_fees (user_id, badge_address, signature, lamports, status, recent_blockhash) \
+                 VALUES ($1, $2, $3, $4, 'reserved', $5)"
+            )
+            .bind(user_id)
+            .bind(badge_address.to_string())
+            .bind(transaction.signatures[0].to_string())
+            .bind(lamports as i64)
+            .bind(transaction.message.recent_blockhash.to_string())
+            .execute(&mut session).await
+            .map_err(|err| {
+                println!("Database insert failed: {}", err);
+                Error::InternalServerError
+            })?;
+
+        session.commit().await.map_err(|_| Error::InternalServerError)?;
+
+        Ok(())
+    }
+
+    // Gives back the reservation of a sponsored transaction that will never be handed out
+    pub async fn release(&self, signature: &str, db: &Pool<Postgres>) -> Result<(), Error> {
+        set_reservation_status(&[signature.to_string()], "released", db).await
+    }
+
+    // Adds the fee payer signature; the fee payer signs first so it also fixes the transaction id
+    pub fn sign(&self, transaction: &mut Transaction) -> Result<(), Error> {
+        self.signer.sign_transaction(transaction).map_err(|err| {
+            println!("Fee payer signing failed: {}", err);
+            Error::InternalServerError
+        })
+    }
+}
+
+async fn set_reservation_status(
+    signatures: &[String],
+    status: &str,
+    db: &Pool<Postgres>
+) -> Result<(), Error> {
+    if signatures.is_empty() {
+        return Ok(());
+    }
+
+    sqlx
+        ::query(
+            "UPDATE sponsored_fees SET status = $1 WHERE signature = ANY($2) AND status = 'reserved'"
+        )
+        .bind(status)
+        .bind(signatures)
+        .execute(db).await
+        .map_err(|err| {
+            println!("Database update failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    Ok(())
+}
+
+// How a reservation settles, from its signature status and whether its blockhash expired
+#[derive(Debug, PartialEq)]
+enum Settlement {
+    // Landed and succeeded
+    Landed,
+    // Landed but failed: the fee was still paid, but nothing was minted
+    Failed,
+    // Expired without landing
+    Released,
+    // Not landed yet, and may still
+    Pending,
+}
+
+fn settlement(status: Option<&TransactionStatus>, expired: bool) -> Settlement {
+    match status {
+        Some(status) if status.err.is_some() => Settlement::Failed,
+        Some(_) => Settlement::Landed,
+        None if expired => Settlement::Released,
+        None => Settlement::Pending,
+    }
+}
+
+// Settles reservations: fees of transactions that landed stay booked, failed ones included,
+// while those whose blockhash expired without landing are released. A claim redemption is
+// only kept when its mint succeeded. Blockhashes are checked before the signature statuses,
+// so a transaction cannot land between the two lookups unnoticed, and expired transactions
+// are looked up in the ledger history before they are released, in case settlement fell
+// behind the recent status cache.
+async fn settle_reservations(rpc: &RpcPool, db: &Pool<Postgres>) -> Result<(), Error> {
+    let reserved = sqlx
+        ::query_as::<_, (String, Option<String>)>(
+            "SELECT signature, recent_blockhash FROM sponsored_fees WHERE status = 'reserved' \
+             ORDER BY created_at LIMIT $1"
+        )
+        .bind(MAX_SETTLE_BATCH)
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    let reserved: Vec<(String, Signature, Option<Hash>)> = reserved
+        .into_iter()
+        .filter_map(|(signature, blockhash)| {
+            let parsed = Signature::from_str(&signature).ok()?;
+            let blockhash = blockhash.and_then(|blockhash| Hash::from_str(&blockhash).ok());
+            Some((signature, parsed, blockhash))
+        })
+        .collect();
+
+    if reserved.is_empty() {
+        return Ok(());
+    }
+
+    let mut expired_blockhashes: HashMap<Hash, bool> = HashMap::new();
+    for (_, _, blockhash) in &reserved {
+        if let Some(blockhash) = blockhash {
+            if !expired_blockhashes.contains_key(blockhash) {
+                let valid = rpc.is_blockhash_valid(blockhash).await?;
+                expired_blockhashes.insert(*blockhash, !valid);
+            }
+        }
+    }
+    let expired: Vec<bool> = reserved
+        .iter()
+        .map(|(_, _, blockhash)| {
+            blockhash.map_or(true, |blockhash| expired_blockhashes[&blockhash])
+        })
+        .collect();
+
+    let signatures: Vec<Signature> = reserved
+        .iter()
+        .map(|(_, signature, _)| *signature)
+        .collect();
+    let mut statuses = rpc.get_signature_statuses(&signatures).await?;
+
+    let unseen: Vec<usize> = (0..reserved.len())
+        .filter(|index| statuses[*index].is_none() && expired[*index])
+        .collect();
+
+    if !unseen.is_empty() {
+        let signatures: Vec<Signature> = unseen
+            .iter()
+            .map(|index| reserved[*index].1)
+            .collect();
+        let history = rpc.get_signature_statuses_with_history(&signatures).await?;
+
+        for (index, status) in unseen.into_iter().zip(history) {
+            statuses[index] = status;
+        }
+    }
+
+    let mut landed = vec![];
+    let mut failed = vec![];
+    let mut released = vec![];
+    for (((signature, _, _), status), expired) in reserved.into_iter().zip(statuses).zip(expired) {
+        match settlement(status.as_ref(), expired) {
+            Settlement::Landed => landed.push(signature),
+            Settlement::Failed => failed.push(signature),
+            Settlement::Released => released.push(signature),
+            Settlement::Pending => {}
+        }
+    }
+
+    set_reservation_status(&[landed.as_slice(), failed.as_slice()].concat(), "landed", db).await?;
+    set_reservation_status(&released, "released", db).await?;
+    settle_redemptions(&landed, &[failed, released].concat(), db).await?;
+
+    Ok(())
+}
+
+// Background task settling sponsored fee reservations every FEE_PAYER_SETTLE_INTERVAL_SECS
+pub async fn track_sponsored_fees(app_state: Arc<AppState>) {
+    if app_state.sponsor.is_none() {
+        return;
+    }
+
+    let mut interval = tokio::time::interval(
+        Duration::from_secs(env_or("FEE_PAYER_SETTLE_INTERVAL_SECS", 15))
+    );
+
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = settle_reservations(&app_state.rpc, &app_state.db).await {
+            println!("Sponsored fee settlement failed: {:?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::transaction::TransactionError;
+    use solana_transaction_status::TransactionConfirmationStatus;
+
+    fn status(err: Option<TransactionError>) -> TransactionStatus {
+        TransactionStatus {
+            slot: 42,
+            confirmations: None,
+            status: err.clone().map_or(Ok(()), Err),
+            err,
+            confirmation_status: Some(TransactionConfirmationStatus::Finalized),
+        }
+    }
+
+    #[test]
+    fn successful_transactions_land() {
+        assert_eq!(settlement(Some(&status(None)), false), Settlement::Landed);
+        assert_eq!(settlement(Some(&status(None)), true), Settlement::Landed);
+    }
+
+    #[test]
+    fn failed_transactions_are_told_apart() {
+        let failed = status(Some(TransactionError::AccountInUse));
+
+        assert_eq!(settlement(Some(&failed), false), Settlement::Failed);
+        assert_eq!(settlement(Some(&failed), true), Settlement::Failed);
+    }
+
+    #[test]
+    fn unseen_transactions_wait_for_their_blockhash_to_expire() {
+        assert_eq!(settlement(None, false), Settlement::Pending);
+        assert_eq!(settlement(None, true), Settlement::Released);
+    }
+}
@@ -0,0 +1,89 @@
+use async_trait::async_trait;
+use solana_sdk::hash::hash;
+use std::path::{ Path, PathBuf };
+use std::sync::Arc;
+use uuid::Uuid;
+
+// Where uploaded images and metadata live. Objects are content addressed, so
+// a key always maps to the same bytes and its URL can be put on chain.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    // Stores the object unless it already exists and returns its public URL
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<String, String>;
+
+    // Directory the HTTP server should serve uploads from, for backends that need it
+    fn local_dir(&self) -> Option<&Path> {
+        None
+    }
+}
+
+// Key for `bytes` under `prefix`: the sha256 of the content plus an extension
+pub fn content_key(prefix: &str, bytes: &[u8], extension: &str) -> String {
+    format!("{}/{}.{}", prefix, hash(bytes), extension)
+}
+
+// Writes objects below STORAGE_DIR. This server serves them at /uploads, which
+// STORAGE_PUBLIC_URL must expose over https, e.g. through the TLS proxy in front of it
+pub struct LocalStorage {
+    root: PathBuf,
+    public_url: String,
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, bytes: Vec<u8>, _content_type: &str) -> Result<String, String> {
+        let path = self.root.join(key);
+        let url = format!("{}/{}", self.public_url.trim_end_matches('/'), key);
+
+        if tokio::fs::try_exists(&path).await.map_err(|err| err.to_string())? {
+            return Ok(url);
+        }
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|err| err.to_string())?;
+        }
+
+        // Write then rename so readers never see a partial object
+        let temporary = path.with_extension(format!("{}.partial", Uuid::new_v4()));
+        tokio::fs::write(&temporary, bytes).await.map_err(|err| err.to_string())?;
+        tokio::fs::rename(&temporary, &path).await.map_err(|err| err.to_string())?;
+
+        Ok(url)
+    }
+
+    fn local_dir(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+}
+
+// STORAGE_PUBLIC_URL ends up in badge URIs on chain, which must use https, so plain http
+// is refused at startup instead of when the first badge is created
+fn public_url() -> Result<String, String> {
+    let url = std::env
+        ::var("STORAGE_PUBLIC_URL")
+        .map_err(|_| "STORAGE_PUBLIC_URL must be set.".to_string())?;
+
+    if !url.starts_with("https://") {
+        return Err(format!("STORAGE_PUBLIC_URL must be an https URL, got {}.", url));
+    }
+
+    Ok(url)
+}
+
+// Builds the backend named by STORAGE_BACKEND
+pub fn from_env() -> Arc<dyn Storage> {
+    match std::env::var("STORAGE_BACKEND").unwrap_or("local".to_string()).as_str() {
+        "local" =>
+            Arc::new(LocalStorage {
+                root: PathBuf::from(std::env::var("STORAGE_DIR").unwrap_or("./uploads".to_string())),
+                public_url: public_url().unwrap_or_else(|err| {
+                    println!("{}", err);
+                    std::process::exit(1);
+                }),
+            }),
+        backend => {
+            println!("Unknown STORAGE_BACKEND: {}", backend);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_transaction::TransactionRecord;
+use solana_sdk::hash::Hash;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::TransactionConfirmationStatus;
+use sqlx::{ Postgres, Pool };
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+// getSignatureStatuses accepts at most 256 signatures per call
+const MAX_STATUS_BATCH: i64 = 256;
+
+// Saves a freshly submitted transaction
+pub async fn insert_transaction(
+    signature: &Signature,
+    transaction: &Transaction,
+    user_id: Uuid,
+    db: &Pool<Postgres>
+) -> Result<TransactionRecord, Error> {
+    sqlx
+        ::query_as::<_, TransactionRecord>(
+            "INSERT INTO transactions (signature, user_id, recent_blockhash, status) VALUES ($1, $2, $3, 'submitted') \
+             ON CONFLICT (signature) DO UPDATE SET updated_at = NOW() RETURNING *"
+        )
+        .bind(signature.to_string())
+        .bind(user_id)
+        .bind(transaction.message.recent_blockhash.to_string())
+        .fetch_one(db).await
+        .map_err(|err| {
+            println!("Database insert failed: {}", err);
+            Error::InternalServerError
+        })
+}
+
+// Gets a relayed transaction by signature
+pub async fn fetch_transaction(
+    signature: &str,
+    db: &Pool<Postgres>
+) -> Result<TransactionRecord, Error> {
+    sqlx
+        ::query_as::<_, TransactionRecord>("SELECT * FROM transactions WHERE signature = $1")
+        .bind(signature)
+        .fetch_one(db).await
+        .map_err(|_| Error::GetTransactionError("Transaction not found.".to_string()))
+}
+
+// Moves a transaction on from the status it was read with. Every instance polls the same
+// transactions, so only the one whose update lands gets the row back and notifies.
+async fn update_status(
+    record: &TransactionRecord,
+    status: &str,
+    error: Option<String>,
+    slot: Option<u64>,
+    db: &Pool<Postgres>
+) -> Result<Option<TransactionRecord>, Error> {
+    sqlx
+        ::query_as::<_, TransactionRecord>(
+            "UPDATE transactions SET status = $1, error = $2, slot = COALESCE($3, slot), updated_at = NOW() \
+             WHERE signature = $4 AND status = $5 RETURNING *"
+        )
+        .bind(status)
+        .bind(error)
+        .bind(slot.map(|slot| slot as i64))
+        .bind(&record.signature)
+        .bind(&record.status)
+        .fetch_optional(db).await
+        .map_err(|err| {
+            println!("Database update failed: {}", err);
+            Error::InternalServerError
+        })
+}
+
+// Sends the submitting user a `notification:new` whenever a relayed transaction changes state
+async fn poll_pending_transactions(app_state: &AppState) -> Result<(), Error> {
+    let pending = sqlx
+        ::query_as::<_, TransactionRecord>(
+            "SELECT * FROM transactions WHERE status IN ('submitted', 'processed', 'confirmed') \
+             ORDER BY created_at LIMIT $1"
+        )
+        .bind(MAX_STATUS_BATCH)
+        .fetch_all(&app_state.db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    let pending: Vec<(TransactionRecord, Signature)> = pending
+        .into_iter()
+        .filter_map(|record| {
+            let signature = Signature::from_str(&record.signature).ok()?;
+            Some((record, signature))
+        })
+        .collect();
+
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    // Blockhashes are checked before the signature statuses, so a transaction landing between
+    // the two lookups is seen by the status lookup rather than marked expired. A bad record or
+    // a failed check only skips that transaction, not the batch.
+    let mut expired: HashMap<String, bool> = HashMap::new();
+    for (record, _) in &pending {
+        if expired.contains_key(&record.recent_blockhash) {
+            continue;
+        }
+
+        let blockhash = match Hash::from_str(&record.recent_blockhash) {
+            Ok(blockhash) => blockhash,
+            Err(_) => {
+                println!(
+                    "Invalid blockhash {} for transaction {}",
+                    record.recent_blockhash,
+                    record.signature
+                );
+                continue;
+            }
+        };
+
+        match app_state.rpc.is_blockhash_valid(&blockhash).await {
+            Ok(valid) => {
+                expired.insert(record.recent_blockhash.clone(), !valid);
+            }
+            Err(err) => {
+                println!("Blockhash check failed for transaction {}: {:?}", record.signature, err);
+            }
+        }
+    }
+
+    let signatures: Vec<Signature> = pending
+        .iter()
+        .map(|(_, signature)| *signature)
+        .collect();
+    let mut statuses = app_state.rpc.get_signature_statuses(&signatures).await?;
+
+    // Expired transactions missing from the recent status cache may still have landed, e.g.
+    // while the tracker was down, so the ledger history decides before they are given up on
+    let unseen: Vec<usize> = pending
+        .iter()
+        .zip(&statuses)
+        .enumerate()
+        .filter(|(_, ((record, _), status))| {
+            status.is_none() && expired.get(&record.recent_blockhash) == Some(&true)
+        })
+        .map(|(index, _)| index)
+        .collect();
+
+    if !unseen.is_empty() {
+        let signatures: Vec<Signature> = unseen
+            .iter()
+            .map(|index| pending[*index].1)
+            .collect();
+        let history = app_state.rpc.get_signature_statuses_with_history(&signatures).await?;
+
+        for (index, status) in unseen.into_iter().zip(history) {
+            statuses[index] = status;
+        }
+    }
+
+    for ((record, _), status) in pending.iter().zip(statuses) {
+        let (next_status, error, slot) = match status {
+            Some(status) =>
+                match (status.err, status.confirmation_status) {
+                    (Some(err), _) => ("failed", Some(err.to_string()), Some(status.slot)),
+                    (None, Some(TransactionConfirmationStatus::Finalized)) => {
+                        ("finalized", None, Some(status.slot))
+                    }
+                    (None, Some(TransactionConfirmationStatus::Confirmed)) => {
+                        ("confirmed", None, Some(status.slot))
+                    }
+                    (None, _) => ("processed", None, Some(status.slot)),
+                }
+            None if expired.get(&record.recent_blockhash) == Some(&true) => {
+                ("expired", Some("Blockhash expired before the transaction landed.".to_string()), None)
+            }
+            None => {
+                continue;
+            }
+        };
+
+        if next_status == record.status {
+            continue;
+        }
+
+        let updated = match update_status(record, next_status, error, slot, &app_state.db).await? {
+            Some(updated) => updated,
+            // Another instance got there first and sent the notification
+            None => {
+                continue;
+            }
+        };
+
+        if let Some(user_id) = updated.user_id {
+            app_state.events.notify(
+                &user_id,
+                "transaction_status",
+                format!("Transaction {} is {}.", updated.signature, updated.status),
+                serde_json::to_value(&updated).unwrap_or_default()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// Background task polling getSignatureStatuses until each transaction is finalized or expired
+pub async fn track_transactions(app_state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(2));
+
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = poll_pending_transactions(&app_state).await {
+            println!("Transaction tracker failed: {:?}", err);
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use crate::errors::error::Error;
+use crate::models::model_metadata::{ BadgeMetadata, MetadataAttribute };
+use crate::models::model_upload::ImageUpload;
+use crate::services::service_metadata::check_fields;
+use crate::services::service_rpc::env_or;
+use crate::services::service_storage::{ content_key, Storage };
+use image::{ ImageFormat, ImageOutputFormat };
+use std::io::Cursor;
+
+const THUMBNAIL_SIZE: u32 = 256;
+
+// Limits for uploaded badge images
+#[derive(Clone, Copy)]
+pub struct UploadLimits {
+    pub max_image_bytes: usize,
+    pub min_dimension: u32,
+    pub max_dimension: u32,
+}
+
+impl UploadLimits {
+    pub fn from_env() -> UploadLimits {
+        UploadLimits {
+            max_image_bytes: env_or("UPLOAD_MAX_IMAGE_BYTES", 5 * 1024 * 1024),
+            min_dimension: env_or("UPLOAD_MIN_IMAGE_DIMENSION", 64),
+            max_dimension: env_or("UPLOAD_MAX_IMAGE_DIMENSION", 4096),
+        }
+    }
+}
+
+// Decides the format from the bytes themselves rather than the client's content type
+fn sniff_format(bytes: &[u8]) -> Result<(ImageFormat, &'static str, &'static str), Error> {
+    let format = image
+        ::guess_format(bytes)
+        .map_err(|_| Error::UploadError("File is not a supported image.".to_string()))?;
+
+    match format {
+        ImageFormat::Png => Ok((format, "image/png", "png")),
+        ImageFormat::Jpeg => Ok((format, "image/jpeg", "jpg")),
+        ImageFormat::Gif => Ok((format, "image/gif", "gif")),
+        ImageFormat::WebP => Ok((format, "image/webp", "webp")),
+        _ => Err(Error::UploadError("Images must be PNG, JPEG, GIF or WebP.".to_string())),
+    }
+}
+
+// Checks the header dimensions before decoding, then renders a PNG thumbnail
+fn process_image(
+    bytes: &[u8],
+    format: ImageFormat,
+    limits: &UploadLimits
+) -> Result<(u32, u32, Vec<u8>), Error> {
+    let (width, height) = image::io::Reader
+        ::with_format(Cursor::new(bytes), format)
+        .into_dimensions()
+        .map_err(|_| Error::UploadError("Image could not be read.".to_string()))?;
+
+    if width.min(height) < limits.min_dimension || width.max(height) > limits.max_dimension {
+        return Err(
+            Error::UploadError(
+                format!(
+                    "Images must be between {0}x{0} and {1}x{1} pixels.",
+                    limits.min_dimension,
+                    limits.max_dimension
+                )
+            )
+        );
+    }
+
+    let decoded = image
+        ::load_from_memory_with_format(bytes, format)
+        .map_err(|_| Error::UploadError("Image could not be decoded.".to_string()))?;
+
+    let mut thumbnail = vec![];
+    decoded
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .write_to(&mut Cursor::new(&mut thumbnail), ImageOutputFormat::Png)
+        .map_err(|_| Error::InternalServerError)?;
+
+    Ok((width, height, thumbnail))
+}
+
+// Validates an image and stores it with its thumbnail
+pub async fn store_image(
+    storage: &dyn Storage,
+    limits: &UploadLimits,
+    bytes: Vec<u8>
+) -> Result<ImageUpload, Error> {
+    if bytes.len() > limits.max_image_bytes {
+        return Err(
+            Error::PayloadTooLarge(
+                format!("Images may be at most {} bytes.", limits.max_image_bytes)
+            )
+        );
+    }
+
+    let (format, content_type, extension) = sniff_format(&bytes)?;
+
+    // Decoding is CPU bound, keep it off the async workers
+    let limits = *limits;
+    let (bytes, (width, height, thumbnail)) = tokio::task
+        ::spawn_blocking(move || {
+            process_image(&bytes, format, &limits).map(|processed| (bytes, processed))
+        }).await
+        .map_err(|_| Error::InternalServerError)??;
+
+    let size = bytes.len();
+    let image_key = content_key("images", &bytes, extension);
+    let thumbnail_key = content_key("thumbnails", &thumbnail, "png");
+
+    let image = storage.put(&image_key, bytes, content_type).await.map_err(|err| {
+        println!("Failed to store image {}: {}", image_key, err);
+        Error::InternalServerError
+    })?;
+    let thumbnail = storage.put(&thumbnail_key, thumbnail, "image/png").await.map_err(|err| {
+        println!("Failed to store thumbnail {}: {}", thumbnail_key, err);
+        Error::InternalServerError
+    })?;
+
+    Ok(ImageUpload {
+        image,
+        thumbnail,
+        content_type: content_type.to_string(),
+        width,
+        height,
+        bytes: size,
+    })
+}
+
+// Builds and checks metadata from the upload form. `image` is filled in once the image is stored.
+pub fn build_metadata(
+    name: String,
+    symbol: String,
+    description: String,
+    external_url: Option<String>,
+    attributes: Option<&str>
+) -> Result<BadgeMetadata, Error> {
+    let attributes: Vec<MetadataAttribute> = match attributes {
+        Some(attributes) if !attributes.trim().is_empty() =>
+            serde_json
+                ::from_str(attributes)
+                .map_err(|_| {
+                    Error::UploadError(
+                        "Attributes must be a JSON array of {trait_type, value}.".to_string()
+                    )
+                })?,
+        _ => vec![],
+    };
+
+    let metadata = BadgeMetadata {
+        name,
+        symbol,
+        description,
+        image: String::new(),
+        animation_url: None,
+        external_url,
+        attributes,
+    };
+
+    check_fields(&metadata).map_err(Error::UploadError)?;
+
+    Ok(metadata)
+}
+
+// The JSON document `uri` points at, with the image listed under Metaplex `properties.files`
+pub fn metadata_document(
+    metadata: &BadgeMetadata,
+    image: &ImageUpload
+) -> Result<serde_json::Value, Error> {
+    let mut document = serde_json::to_value(metadata).map_err(|_| Error::InternalServerError)?;
+    document["properties"] = serde_json::json!({
+        "category": "image",
+        "files": [{ "uri": image.image, "type": image.content_type }],
+    });
+
+    Ok(document)
+}
+
+// Stores a metadata document and returns its content-addressed URI
+pub async fn store_metadata(
+    storage: &dyn Storage,
+    document: &serde_json::Value
+) -> Result<String, Error> {
+    let bytes = serde_json::to_vec_pretty(document).map_err(|_| Error::InternalServerError)?;
+    let key = content_key("metadata", &bytes, "json");
+
+    storage.put(&key, bytes, "application/json").await.map_err(|err| {
+        println!("Failed to store metadata {}: {}", key, err);
+        Error::InternalServerError
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use image::{ ImageBuffer, Rgb };
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    const LIMITS: UploadLimits = UploadLimits {
+        max_image_bytes: 64 * 1024,
+        min_dimension: 64,
+        max_dimension: 512,
+    };
+
+    #[derive(Default)]
+    struct MemoryStorage {
+        objects: Mutex<HashMap<String, (Vec<u8>, String)>>,
+    }
+
+    #[async_trait]
+    impl Storage for MemoryStorage {
+        async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<String, String> {
+            self.objects.lock().unwrap().insert(key.to_string(), (bytes, content_type.to_string()));
+            Ok(format!("https://badges.example.com/uploads/{}", key))
+        }
+    }
+
+    fn encoded(width: u32, height: u32, format: ImageOutputFormat) -> Vec<u8> {
+        let image = ImageBuffer::from_pixel(width, height, Rgb([200u8, 80, 20]));
+        let mut bytes = vec![];
+        image.write_to(&mut Cursor::new(&mut bytes), format).unwrap();
+        bytes
+    }
+
+    fn upload_error(result: Result<impl std::fmt::Debug, Error>) -> String {
+        match result {
+            Err(Error::UploadError(message)) => message,
+            other => panic!("expected an upload error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn formats_are_sniffed_from_the_bytes() {
+        let png = encoded(64, 64, ImageOutputFormat::Png);
+        let jpeg = encoded(64, 64, ImageOutputFormat::Jpeg(80));
+
+        assert_eq!(sniff_format(&png).unwrap(), (ImageFormat::Png, "image/png", "png"));
+        assert_eq!(sniff_format(&jpeg).unwrap(), (ImageFormat::Jpeg, "image/jpeg", "jpg"));
+        assert_eq!(
+            upload_error(sniff_format(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>")),
+            "File is not a supported image."
+        );
+
+        let mut bmp = b"BM".to_vec();
+        bmp.resize(64, 0);
+        assert_eq!(upload_error(sniff_format(&bmp)), "Images must be PNG, JPEG, GIF or WebP.");
+    }
+
+    #[test]
+    fn dimensions_are_checked_before_decoding() {
+        let expected = "Images must be between 64x64 and 512x512 pixels.";
+
+        let small = encoded(32, 128, ImageOutputFormat::Png);
+        assert_eq!(upload_error(process_image(&small, ImageFormat::Png, &LIMITS)), expected);
+
+        let large = encoded(600, 100, ImageOutputFormat::Png);
+        assert_eq!(upload_error(process_image(&large, ImageFormat::Png, &LIMITS)), expected);
+
+        let (width, height, thumbnail) = process_image(
+            &encoded(512, 128, ImageOutputFormat::Png),
+            ImageFormat::Png,
+            &LIMITS
+        ).unwrap();
+        assert_eq!((width, height), (512, 128));
+
+        let thumbnail = image::load_from_memory_with_format(&thumbnail, ImageFormat::Png).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (256, 64));
+    }
+
+    #[tokio::test]
+    async fn images_are_stored_under_content_keys() {
+        let storage = MemoryStorage::default();
+        let bytes = encoded(128, 128, ImageOutputFormat::Png);
+
+        let upload = store_image(&storage, &LIMITS, bytes.clone()).await.unwrap();
+        assert_eq!(upload.content_type, "image/png");
+        assert_eq!((upload.width, upload.height, upload.bytes), (128, 128, bytes.len()));
+        assert_eq!(
+            upload.image,
+            format!("https://badges.example.com/uploads/{}", content_key("images", &bytes, "png"))
+        );
+        assert_eq!(storage.objects.lock().unwrap().len(), 2);
+
+        let too_large = store_image(&storage, &LIMITS, vec![0; LIMITS.max_image_bytes + 1]).await;
+        assert!(matches!(too_large, Err(Error::PayloadTooLarge(_))));
+    }
+
+    #[test]
+    fn metadata_is_built_from_the_form() {
+        let metadata = build_metadata(
+            "Early Adopter".to_string(),
+            "EARLY".to_string(),
+            "First season".to_string(),
+            Some("https://pebble.example.com".to_string()),
+            Some("[{\"trait_type\":\"Season\",\"value\":1},{\"trait_type\":\"Tier\",\"value\":\"Gold\"}]")
+        ).unwrap();
+
+        assert_eq!(metadata.name, "Early Adopter");
+        assert_eq!(metadata.image, "");
+        assert_eq!(metadata.attributes.len(), 2);
+        assert_eq!(metadata.attributes[1].value, "Gold");
+
+        let blank = build_metadata("Early".to_string(), String::new(), String::new(), None, Some(" "));
+        assert!(blank.unwrap().attributes.is_empty());
+    }
+
+    #[test]
+    fn invalid_metadata_forms_are_rejected() {
+        let build = |name: &str, attributes: &str| {
+            build_metadata(name.to_string(), "EARLY".to_string(), String::new(), None, Some(attributes))
+        };
+
+        assert_eq!(
+            upload_error(build("Early", "{\"trait_type\":\"Season\"}")),
+            "Attributes must be a JSON array of {trait_type, value}."
+        );
+        assert_eq!(
+            upload_error(build("Early", "[{\"trait_type\":\"Season\",\"value\":[1]}]")),
+            "attribute Season must be a string or number"
+        );
+        assert_eq!(upload_error(build(" ", "[]")), "name must be 1 to 32 characters");
+        assert_eq!(upload_error(build(&"n".repeat(33), "[]")), "name must be 1 to 32 characters");
+    }
+
+    #[test]
+    fn documents_list_the_image_file() {
+        let mut metadata = build_metadata(
+            "Early Adopter".to_string(),
+            "EARLY".to_string(),
+            String::new(),
+            None,
+            None
+        ).unwrap();
+        metadata.image = "https://badges.example.com/uploads/images/abc.png".to_string();
+
+        let image = ImageUpload {
+            image: metadata.image.clone(),
+            thumbnail: "https://badges.example.com/uploads/thumbnails/def.png".to_string(),
+            content_type: "image/png".to_string(),
+            width: 128,
+            height: 128,
+            bytes: 1024,
+        };
+
+        let document = metadata_document(&metadata, &image).unwrap();
+        assert_eq!(document["name"], "Early Adopter");
+        assert_eq!(document["properties"]["files"][0]["uri"], metadata.image);
+        assert_eq!(document["properties"]["files"][0]["type"], "image/png");
+    }
+}
@@ -0,0 +1,735 @@
+use crate::errors::error::Error;
+use crate::database::db::AppState;
+use crate::models::model_activity::ProgramEvent;
+use crate::models::model_user::User;
+use crate::models::model_webhook::{
+    CreateWebhookPayload,
+    CreatedWebhook,
+    WebhookAttempt,
+    WebhookDelivery,
+    WebhookSubscription,
+};
+use crate::services::service_metadata::{ is_private_host, PublicResolver };
+use crate::services::service_rpc::env_or;
+use chacha20poly1305::aead::{ rand_core::RngCore, OsRng };
+use chrono::{ DateTime, Utc };
+use hmac::{ Hmac, Mac };
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Url;
+use serde_json::json;
+use sha2::Sha256;
+use solana_sdk::pubkey::Pubkey;
+use sqlx::{ FromRow, Postgres, Pool, Transaction };
+use std::fmt::Write;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{ Duration, Instant };
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const EVENT_TYPES: [&str; 4] = [
+    "badge.created",
+    "badge.minted",
+    "badge.sold_out",
+    "badge.price_changed",
+];
+const DELIVERY_STATUSES: [&str; 3] = ["pending", "delivered", "failed"];
+const MAX_WEBHOOKS_PER_USER: i64 = 20;
+const MAX_WEBHOOK_BADGES: usize = 100;
+const MAX_URL_LEN: usize = 2048;
+const MAX_DELIVERIES_PAGE: i64 = 100;
+const OUTBOX_BATCH: i64 = 500;
+const DELIVERY_BATCH: i64 = 50;
+const MAX_RETRY_DELAY_SECS: i64 = 6 * 3600;
+
+// Pending delivery joined with what is needed to send it
+#[derive(FromRow)]
+struct DueDelivery {
+    id: Uuid,
+    attempts: i32,
+    event_type: String,
+    url: String,
+    secret: String,
+    payload: serde_json::Value,
+    created_at: DateTime<Utc>,
+}
+
+// `t=<unix time>,v1=<hex HMAC-SHA256 of "<unix time>.<body>">`. Receivers recompute it with
+// their secret and reject stale timestamps to stop replays.
+pub fn signature_header(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect(
+        "HMAC accepts keys of any length"
+    );
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+
+    let mut header = format!("t={},v1=", timestamp);
+    for byte in mac.finalize().into_bytes() {
+        let _ = write!(header, "{:02x}", byte);
+    }
+
+    header
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("whsec_{}", solana_sdk::bs58::encode(bytes).into_string())
+}
+
+// `check_url` only sees literal addresses, so hostnames are resolved to public addresses only
+// when the request is sent
+fn build_client(timeout: Duration, allow_private_hosts: bool) -> reqwest::Result<reqwest::Client> {
+    let builder = reqwest::Client
+        ::builder()
+        .timeout(timeout)
+        // A redirect could point the request at a private host
+        .redirect(reqwest::redirect::Policy::none())
+        .user_agent("pebble-webhooks");
+
+    if allow_private_hosts {
+        builder.build()
+    } else {
+        builder.dns_resolver(Arc::new(PublicResolver)).build()
+    }
+}
+
+// Sends signed webhook requests and decides when failed ones are retried
+pub struct WebhookDispatcher {
+    client: reqwest::Client,
+    max_attempts: i32,
+    retry_base_secs: i64,
+    allow_private_hosts: bool,
+}
+
+impl WebhookDispatcher {
+    pub fn from_env() -> WebhookDispatcher {
+        let allow_private_hosts = env_or("WEBHOOK_ALLOW_PRIVATE_HOSTS", false);
+        let client = build_client(
+            Duration::from_secs(env_or("WEBHOOK_TIMEOUT_SECS", 10)),
+            allow_private_hosts
+        ).unwrap_or_else(|err| {
+            println!("Failed to build the webhook HTTP client: {}", err);
+            std::process::exit(1);
+        });
+
+        WebhookDispatcher {
+            client,
+            max_attempts: env_or("WEBHOOK_MAX_ATTEMPTS", 8),
+            retry_base_secs: env_or("WEBHOOK_RETRY_BASE_SECS", 30),
+            allow_private_hosts,
+        }
+    }
+
+    fn check_url(&self, url: &str) -> Result<Url, String> {
+        if url.len() > MAX_URL_LEN {
+            return Err(format!("URL must be at most {} characters", MAX_URL_LEN));
+        }
+
+        let url = Url::parse(url).map_err(|err| format!("URL is invalid: {}", err))?;
+
+        // Plain http is only accepted where private hosts are, i.e. for local receivers
+        if url.scheme() != "https" && !(self.allow_private_hosts && url.scheme() == "http") {
+            return Err("URL must use https".to_string());
+        }
+
+        if !self.allow_private_hosts && is_private_host(&url) {
+            return Err("URL points at a private host".to_string());
+        }
+
+        Ok(url)
+    }
+
+    // Seconds until the next attempt: the base delay doubled per failed attempt, capped at 6 hours
+    fn retry_delay(&self, attempts: i32) -> i64 {
+        let exponent = (attempts - 1).clamp(0, 20) as u32;
+        self.retry_base_secs.saturating_mul(1i64 << exponent).min(MAX_RETRY_DELAY_SECS)
+    }
+
+    // Posts a signed body and returns the response status
+    pub async fn send(
+        &self,
+        url: &str,
+        secret: &str,
+        delivery_id: Uuid,
+        event_type: &str,
+        body: String
+    ) -> Result<u16, String> {
+        let url = self.check_url(url)?;
+        let signature = signature_header(secret, Utc::now().timestamp(), &body);
+
+        let response = self.client
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .header("Pebble-Event", event_type)
+            .header("Pebble-Delivery", delivery_id.to_string())
+            .header("Pebble-Signature", signature)
+            .body(body)
+            .send().await
+            .map_err(|err| err.to_string())?;
+
+        Ok(response.status().as_u16())
+    }
+}
+
+async fn insert_outbox(
+    event_type: &str,
+    badge_address: &str,
+    payload: &serde_json::Value,
+    session: &mut Transaction<'_, Postgres>
+) -> Result<(), Error> {
+    sqlx
+        ::query("INSERT INTO webhook_outbox (event_type, badge_address, payload) VALUES ($1, $2, $3)")
+        .bind(event_type)
+        .bind(badge_address)
+        .bind(payload)
+        .execute(&mut *session).await
+        .map_err(|err| {
+            println!("Database insert failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    Ok(())
+}
+
+// Queues the webhook events for a program event inside the transaction that stores it. A mint
+// of the last number in supply (`sold_out`) also queues `badge.sold_out`.
+pub async fn enqueue_program_event(
+    event: &ProgramEvent,
+    sold_out: bool,
+    signature: &str,
+    slot: u64,
+    block_time: Option<i64>,
+    session: &mut Transaction<'_, Postgres>
+) -> Result<(), Error> {
+    let badge = event.badge().to_string();
+    let payload =
+        json!({
+        "badge": badge,
+        "signature": signature,
+        "slot": slot,
+        "block_time": block_time,
+        "event": event.data(),
+    });
+
+    let event_type = match event {
+        ProgramEvent::BadgeCreated(_) => "badge.created",
+        ProgramEvent::BadgeMinted(_) => "badge.minted",
+        ProgramEvent::PriceChanged(_) => "badge.price_changed",
+    };
+    insert_outbox(event_type, &badge, &payload, session).await?;
+
+    if sold_out {
+        insert_outbox("badge.sold_out", &badge, &payload, session).await?;
+    }
+
+    Ok(())
+}
+
+// Registers an endpoint for some event types, optionally limited to some badges
+pub async fn insert_webhook(
+    app_state: &AppState,
+    user: &User,
+    payload: CreateWebhookPayload
+) -> Result<CreatedWebhook, Error> {
+    let url = app_state.webhooks.check_url(payload.url.trim()).map_err(Error::WebhookError)?;
+
+    let mut event_types = vec![];
+    for event_type in payload.event_types {
+        if !EVENT_TYPES.contains(&event_type.as_str()) {
+            return Err(
+                Error::WebhookError(
+                    format!("Event types must be among {}.", EVENT_TYPES.join(", "))
+                )
+            );
+        }
+        if !event_types.contains(&event_type) {
+            event_types.push(event_type);
+        }
+    }
+
+    if event_types.is_empty() {
+        return Err(Error::WebhookError("Subscribe to at least one event type.".to_string()));
+    }
+
+    let badges = payload.badges.unwrap_or_default();
+    if badges.len() > MAX_WEBHOOK_BADGES {
+        return Err(
+            Error::WebhookError(format!("A webhook may watch at most {} badges.", MAX_WEBHOOK_BADGES))
+        );
+    }
+
+    let mut badge_addresses = vec![];
+    for badge in badges {
+        let address = Pubkey::from_str(badge.trim()).map_err(|_|
+            Error::WebhookError(format!("Badge address {} is invalid.", badge))
+        )?;
+        badge_addresses.push(address.to_string());
+    }
+
+    let (count,) = sqlx
+        ::query_as::<_, (i64,)>("SELECT COUNT(*) FROM webhook_subscriptions WHERE user_id = $1")
+        .bind(user.id)
+        .fetch_one(&app_state.db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    if count >= MAX_WEBHOOKS_PER_USER {
+        return Err(
+            Error::WebhookError(format!("At most {} webhooks are allowed.", MAX_WEBHOOKS_PER_USER))
+        );
+    }
+
+    let secret = generate_secret();
+    let subscription = sqlx
+        ::query_as::<_, WebhookSubscription>(
+            "INSERT INTO webhook_subscriptions (user_id, url, secret, event_types, badge_addresses) \
+             VALUES ($1, $2, $3, $4, $5) RETURNING *"
+        )
+        .bind(user.id)
+        .bind(url.to_string())
+        .bind(&secret)
+        .bind(&event_types)
+        .bind(&badge_addresses)
+        .fetch_one(&app_state.db).await
+        .map_err(|err| {
+            println!("Database insert failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    Ok(CreatedWebhook { subscription, secret })
+}
+
+pub async fn fetch_webhooks(user: &User, db: &Pool<Postgres>) -> Result<Vec<WebhookSubscription>, Error> {
+    sqlx
+        ::query_as::<_, WebhookSubscription>(
+            "SELECT * FROM webhook_subscriptions WHERE user_id = $1 ORDER BY created_at DESC"
+        )
+        .bind(user.id)
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })
+}
+
+// Other users' webhooks are reported as missing
+async fn fetch_owned_webhook(
+    id: Uuid,
+    user: &User,
+    db: &Pool<Postgres>
+) -> Result<WebhookSubscription, Error> {
+    sqlx
+        ::query_as::<_, WebhookSubscription>(
+            "SELECT * FROM webhook_subscriptions WHERE id = $1 AND user_id = $2"
+        )
+        .bind(id)
+        .bind(user.id)
+        .fetch_optional(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?
+        .ok_or(Error::GetWebhookError("Webhook not found.".to_string()))
+}
+
+pub async fn remove_webhook(id: Uuid, user: &User, db: &Pool<Postgres>) -> Result<(), Error> {
+    fetch_owned_webhook(id, user, db).await?;
+
+    sqlx
+        ::query("DELETE FROM webhook_subscriptions WHERE id = $1")
+        .bind(id)
+        .execute(db).await
+        .map_err(|err| {
+            println!("Database delete failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    Ok(())
+}
+
+// Gets a webhook's deliveries, newest first
+pub async fn fetch_deliveries(
+    id: Uuid,
+    user: &User,
+    status: Option<&str>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    db: &Pool<Postgres>
+) -> Result<Vec<WebhookDelivery>, Error> {
+    fetch_owned_webhook(id, user, db).await?;
+
+    if status.is_some_and(|status| !DELIVERY_STATUSES.contains(&status)) {
+        return Err(Error::WebhookError("Status must be pending, delivered or failed.".to_string()));
+    }
+
+    sqlx
+        ::query_as::<_, WebhookDelivery>(
+            "SELECT * FROM webhook_deliveries WHERE subscription_id = $1 \
+             AND ($2::VARCHAR IS NULL OR status = $2) ORDER BY created_at DESC LIMIT $3 OFFSET $4"
+        )
+        .bind(id)
+        .bind(status)
+        .bind(limit.unwrap_or(50).clamp(1, MAX_DELIVERIES_PAGE))
+        .bind(offset.unwrap_or(0).max(0))
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })
+}
+
+// Gets the HTTP attempts of one delivery, oldest first
+pub async fn fetch_attempts(
+    id: Uuid,
+    delivery_id: Uuid,
+    user: &User,
+    db: &Pool<Postgres>
+) -> Result<Vec<WebhookAttempt>, Error> {
+    fetch_owned_webhook(id, user, db).await?;
+
+    sqlx
+        ::query_as::<_, WebhookAttempt>(
+            "SELECT a.* FROM webhook_attempts a JOIN webhook_deliveries d ON d.id = a.delivery_id \
+             WHERE d.id = $1 AND d.subscription_id = $2 ORDER BY a.created_at"
+        )
+        .bind(delivery_id)
+        .bind(id)
+        .fetch_all(db).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })
+}
+
+// Queues a delivery to be sent again right away with a fresh set of attempts
+pub async fn replay_delivery(
+    id: Uuid,
+    delivery_id: Uuid,
+    user: &User,
+    db: &Pool<Postgres>
+) -> Result<WebhookDelivery, Error> {
+    fetch_owned_webhook(id, user, db).await?;
+
+    sqlx
+        ::query_as::<_, WebhookDelivery>(
+            "UPDATE webhook_deliveries SET status = 'pending', attempts = 0, next_attempt_at = NOW(), \
+             updated_at = NOW() WHERE id = $1 AND subscription_id = $2 RETURNING *"
+        )
+        .bind(delivery_id)
+        .bind(id)
+        .fetch_optional(db).await
+        .map_err(|err| {
+            println!("Database update failed: {}", err);
+            Error::InternalServerError
+        })?
+        .ok_or(Error::GetWebhookError("Delivery not found.".to_string()))
+}
+
+// Turns new outbox events into one delivery per matching subscription
+async fn dispatch_outbox(db: &Pool<Postgres>) -> Result<(), Error> {
+    let mut session = db.begin().await.map_err(|_| Error::InternalServerError)?;
+
+    let events = sqlx
+        ::query_as::<_, (i64, String, String)>(
+            "SELECT id, event_type, badge_address FROM webhook_outbox WHERE dispatched_at IS NULL \
+             ORDER BY id LIMIT $1 FOR UPDATE SKIP LOCKED"
+        )
+        .bind(OUTBOX_BATCH)
+        .fetch_all(&mut session).await
+        .map_err(|err| {
+            println!("Database query failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    for (id, event_type, badge_address) in &events {
+        sqlx
+            ::query(
+                "INSERT INTO webhook_deliveries (subscription_id, outbox_id, event_type) \
+                 SELECT id, $1, $2 FROM webhook_subscriptions WHERE $2 = ANY(event_types) \
+                 AND (cardinality(badge_addresses) = 0 OR $3 = ANY(badge_addresses)) \
+                 ON CONFLICT (subscription_id, outbox_id) DO NOTHING"
+            )
+            .bind(id)
+            .bind(event_type)
+            .bind(badge_address)
+            .execute(&mut session).await
+            .map_err(|err| {
+                println!("Database insert failed: {}", err);
+                Error::InternalServerError
+            })?;
+    }
+
+    let ids: Vec<i64> = events
+        .iter()
+        .map(|(id, _, _)| *id)
+        .collect();
+
+    sqlx
+        ::query("UPDATE webhook_outbox SET dispatched_at = NOW() WHERE id = ANY($1)")
+        .bind(&ids)
+        .execute(&mut session).await
+        .map_err(|err| {
+            println!("Database update failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    session.commit().await.map_err(|_| Error::InternalServerError)
+}
+
+// Sends one delivery and records the attempt, scheduling a retry or giving up on failure
+async fn deliver(app_state: &AppState, delivery: DueDelivery) -> Result<(), Error> {
+    let body = json!({
+        "id": delivery.id,
+        "type": delivery.event_type,
+        "created_at": delivery.created_at,
+        "data": delivery.payload,
+    }).to_string();
+
+    let started = Instant::now();
+    let result = app_state.webhooks.send(
+        &delivery.url,
+        &delivery.secret,
+        delivery.id,
+        &delivery.event_type,
+        body
+    ).await;
+    let duration_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+    let (status_code, error) = match result {
+        Ok(code) if (200..300).contains(&code) => (Some(code as i32), None),
+        Ok(code) => (Some(code as i32), Some(format!("HTTP {}", code))),
+        Err(err) => (None, Some(err)),
+    };
+
+    let attempts = delivery.attempts + 1;
+    let status = match &error {
+        None => "delivered",
+        Some(_) if attempts >= app_state.webhooks.max_attempts => "failed",
+        Some(_) => "pending",
+    };
+
+    let mut session = app_state.db.begin().await.map_err(|_| Error::InternalServerError)?;
+
+    sqlx
+        ::query(
+            "INSERT INTO webhook_attempts (delivery_id, status_code, error, duration_ms) VALUES ($1, $2, $3, $4)"
+        )
+        .bind(delivery.id)
+        .bind(status_code)
+        .bind(&error)
+        .bind(duration_ms)
+        .execute(&mut session).await
+        .map_err(|err| {
+            println!("Database insert failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    sqlx
+        ::query(
+            "UPDATE webhook_deliveries SET status = $2, attempts = $3, last_status_code = $4, last_error = $5, \
+             delivered_at = CASE WHEN $2 = 'delivered' THEN NOW() ELSE delivered_at END, \
+             next_attempt_at = NOW() + make_interval(secs => $6), updated_at = NOW() WHERE id = $1"
+        )
+        .bind(delivery.id)
+        .bind(status)
+        .bind(attempts)
+        .bind(status_code)
+        .bind(&error)
+        .bind(app_state.webhooks.retry_delay(attempts) as f64)
+        .execute(&mut session).await
+        .map_err(|err| {
+            println!("Database update failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    session.commit().await.map_err(|_| Error::InternalServerError)?;
+    app_state.metrics.incr(&format!("webhook_deliveries_{}", status));
+
+    Ok(())
+}
+
+// Leases due deliveries by pushing their next attempt past the request timeout, so another
+// worker will not pick them up, then sends them concurrently
+async fn deliver_due(app_state: &Arc<AppState>) -> Result<(), Error> {
+    let lease_secs = env_or::<f64>("WEBHOOK_TIMEOUT_SECS", 10.0) * 2.0 + 30.0;
+
+    let due = sqlx
+        ::query_as::<_, DueDelivery>(
+            "UPDATE webhook_deliveries d SET next_attempt_at = NOW() + make_interval(secs => $1), \
+             updated_at = NOW() FROM webhook_subscriptions s, webhook_outbox o \
+             WHERE d.id IN (SELECT id FROM webhook_deliveries WHERE status = 'pending' \
+             AND next_attempt_at <= NOW() ORDER BY next_attempt_at LIMIT $2 FOR UPDATE SKIP LOCKED) \
+             AND s.id = d.subscription_id AND o.id = d.outbox_id \
+             RETURNING d.id, d.attempts, d.event_type, s.url, s.secret, o.payload, o.created_at"
+        )
+        .bind(lease_secs)
+        .bind(DELIVERY_BATCH)
+        .fetch_all(&app_state.db).await
+        .map_err(|err| {
+            println!("Database update failed: {}", err);
+            Error::InternalServerError
+        })?;
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for delivery in due {
+        let app_state = app_state.clone();
+        tasks.spawn(async move { deliver(&app_state, delivery).await });
+    }
+
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => println!("Webhook delivery failed to record: {:?}", err),
+            Err(err) => println!("Webhook delivery task panicked: {}", err),
+        }
+    }
+
+    Ok(())
+}
+
+// Background task fanning out the outbox and sending due deliveries every WEBHOOK_POLL_INTERVAL_SECS
+pub async fn track_webhooks(app_state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(
+        Duration::from_secs(env_or("WEBHOOK_POLL_INTERVAL_SECS", 5))
+    );
+
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = dispatch_outbox(&app_state.db).await {
+            println!("Webhook outbox dispatch failed: {:?}", err);
+        }
+
+        if let Err(err) = deliver_due(&app_state).await {
+            println!("Webhook delivery failed: {:?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{ http::{ HeaderMap, StatusCode }, routing::post, Router };
+    use std::sync::Mutex;
+
+    fn dispatcher(allow_private_hosts: bool) -> WebhookDispatcher {
+        WebhookDispatcher {
+            client: build_client(Duration::from_secs(5), allow_private_hosts).unwrap(),
+            max_attempts: 3,
+            retry_base_secs: 30,
+            allow_private_hosts,
+        }
+    }
+
+    #[test]
+    fn signature_depends_on_secret_timestamp_and_body() {
+        let signature = signature_header("secret", 1_700_000_000, "{}");
+
+        assert!(signature.starts_with("t=1700000000,v1="));
+        assert_eq!(signature.len(), "t=1700000000,v1=".len() + 64);
+        assert_eq!(signature, signature_header("secret", 1_700_000_000, "{}"));
+        assert_ne!(signature, signature_header("other", 1_700_000_000, "{}"));
+        assert_ne!(signature, signature_header("secret", 1_700_000_001, "{}"));
+        assert_ne!(signature, signature_header("secret", 1_700_000_000, "{ }"));
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        let dispatcher = dispatcher(false);
+
+        assert_eq!(dispatcher.retry_delay(1), 30);
+        assert_eq!(dispatcher.retry_delay(2), 60);
+        assert_eq!(dispatcher.retry_delay(5), 480);
+        assert_eq!(dispatcher.retry_delay(40), MAX_RETRY_DELAY_SECS);
+    }
+
+    #[tokio::test]
+    async fn delivers_a_signed_body_to_a_local_receiver() {
+        let received: Arc<Mutex<Option<(HeaderMap, String)>>> = Arc::default();
+        let app = Router::new().route(
+            "/hook",
+            post({
+                let received = received.clone();
+                move |headers: HeaderMap, body: String| async move {
+                    *received.lock().unwrap() = Some((headers, body));
+                    StatusCode::NO_CONTENT
+                }
+            })
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let delivery_id = Uuid::new_v4();
+        let status = dispatcher(true)
+            .send(
+                &format!("http://{}/hook", address),
+                "whsec_test",
+                delivery_id,
+                "badge.minted",
+                "{\"ok\":true}".to_string()
+            ).await
+            .unwrap();
+        assert_eq!(status, 204);
+
+        let (headers, body) = received.lock().unwrap().take().expect("receiver was called");
+        assert_eq!(body, "{\"ok\":true}");
+        assert_eq!(headers["pebble-event"], "badge.minted");
+        assert_eq!(headers["pebble-delivery"], delivery_id.to_string().as_str());
+
+        let signature = headers["pebble-signature"].to_str().unwrap();
+        let timestamp: i64 = signature
+            .strip_prefix("t=")
+            .and_then(|rest| rest.split(',').next())
+            .and_then(|timestamp| timestamp.parse().ok())
+            .expect("signature has a timestamp");
+        assert_eq!(signature, signature_header("whsec_test", timestamp, &body));
+    }
+
+    #[tokio::test]
+    async fn refuses_private_and_plain_http_urls_by_default() {
+        let result = dispatcher(false).send(
+            "https://127.0.0.1/hook",
+            "whsec_test",
+            Uuid::new_v4(),
+            "badge.minted",
+            "{}".to_string()
+        ).await;
+        assert!(result.is_err());
+
+        assert!(dispatcher(false).check_url("http://example.com/hook").is_err());
+        assert!(dispatcher(false).check_url("https://example.com/hook").is_ok());
+    }
+
+    #[tokio::test]
+    async fn refuses_hostnames_resolving_to_private_addresses() {
+        let called = Arc::new(Mutex::new(false));
+        let app = Router::new().route(
+            "/hook",
+            post({
+                let called = called.clone();
+                move || async move {
+                    *called.lock().unwrap() = true;
+                    StatusCode::NO_CONTENT
+                }
+            })
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        // Sent through the client directly, so only the resolver stands in the way
+        let url = format!("http://localhost:{}/hook", port);
+
+        assert!(dispatcher(false).client.post(&url).send().await.is_err());
+        assert!(!*called.lock().unwrap());
+        assert_eq!(dispatcher(true).client.post(&url).send().await.unwrap().status(), 204);
+    }
+}